@@ -0,0 +1,153 @@
+//! Parsers for argument types shared by the subcommands.
+
+use std::{error::Error, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use muchat_providers::chat::{client::ChatClient, response::ChatInfoType};
+
+/// A chat given as `@<contact id>` or `#<group id>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChatRef {
+    pub chat_type: ChatInfoType,
+    pub chat_id: u64,
+}
+
+impl ChatRef {
+    /// A name for files holding this chat, like `direct-2`.
+    pub fn file_stem(&self) -> String {
+        match self.chat_type {
+            ChatInfoType::Direct => format!("direct-{}", self.chat_id),
+            ChatInfoType::Group => format!("group-{}", self.chat_id),
+            ChatInfoType::ContactRequest => format!("request-{}", self.chat_id),
+        }
+    }
+}
+
+/// A chat given by id, as `@2` or `#7`, or by name, as `alice`, `@alice` or `#team`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChatSelector {
+    Id(ChatRef),
+    Name(ChatInfoType, String),
+}
+
+impl ChatSelector {
+    /// Looks up chats given by name among the active user's contacts and groups.
+    pub async fn resolve(&self, client: &ChatClient) -> Result<ChatRef, Box<dyn Error>> {
+        let (chat_type, name) = match self {
+            ChatSelector::Id(chat) => return Ok(*chat),
+            ChatSelector::Name(chat_type, name) => (*chat_type, name),
+        };
+
+        let user = client.active_user().await?;
+        let chat_id = match chat_type {
+            ChatInfoType::Group => client
+                .list_groups(user.user_id)
+                .await?
+                .into_iter()
+                .find(|group| &group.group_info.local_display_name == name)
+                .map(|group| group.group_info.group_id),
+            _ => client
+                .list_contacts(user.user_id)
+                .await?
+                .into_iter()
+                .find(|contact| &contact.local_display_name == name)
+                .map(|contact| contact.contact_id),
+        };
+
+        match chat_id {
+            Some(chat_id) => Ok(ChatRef { chat_type, chat_id }),
+            None => Err(format!("no chat named {}", name).into()),
+        }
+    }
+}
+
+impl FromStr for ChatSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (chat_type, rest) = if let Some(rest) = s.strip_prefix('#') {
+            (ChatInfoType::Group, rest)
+        } else {
+            (ChatInfoType::Direct, s.strip_prefix('@').unwrap_or(s))
+        };
+
+        if rest.is_empty() {
+            return Err(format!("missing chat name or id in {}", s));
+        }
+
+        Ok(match rest.parse() {
+            Ok(chat_id) => ChatSelector::Id(ChatRef { chat_type, chat_id }),
+            Err(_) => ChatSelector::Name(chat_type, rest.to_string()),
+        })
+    }
+}
+
+/// Parses a date (`2024-12-01`, midnight UTC) or an RFC 3339 timestamp.
+pub fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+
+    DateTime::parse_from_rfc3339(s)
+        .map(|ts| ts.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "expected a date like 2024-12-01 or an RFC 3339 timestamp, got {}",
+                s
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chat_selector() {
+        let direct = |chat_id| {
+            ChatSelector::Id(ChatRef {
+                chat_type: ChatInfoType::Direct,
+                chat_id,
+            })
+        };
+
+        assert_eq!("@2".parse::<ChatSelector>(), Ok(direct(2)));
+        assert_eq!("2".parse::<ChatSelector>(), Ok(direct(2)));
+        assert_eq!(
+            "#team".parse::<ChatSelector>(),
+            Ok(ChatSelector::Name(ChatInfoType::Group, "team".to_string()))
+        );
+        assert_eq!(
+            "alice".parse::<ChatSelector>(),
+            Ok(ChatSelector::Name(
+                ChatInfoType::Direct,
+                "alice".to_string()
+            ))
+        );
+        assert!("#".parse::<ChatSelector>().is_err());
+    }
+
+    #[test]
+    fn test_chat_file_stem() {
+        let chat = ChatRef {
+            chat_type: ChatInfoType::Group,
+            chat_id: 7,
+        };
+        assert_eq!(chat.file_stem(), "group-7");
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("2024-12-01").unwrap().to_rfc3339(),
+            "2024-12-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_time("2024-12-01T10:00:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-12-01T08:00:00+00:00"
+        );
+        assert!(parse_time("yesterday").is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::Args;
+use muchat_providers::chat::{
+    archive::{backup_now, restore_backup},
+    response::ArchiveError,
+};
+
+use crate::connect;
+
+#[derive(Args)]
+pub struct BackupArgs {
+    /// Directory to write the archive to.
+    #[arg(long, short, default_value = ".")]
+    dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Archive created by `muchat backup`. Replaces the current database.
+    archive: PathBuf,
+}
+
+pub async fn backup(server: &str, args: BackupArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let dir = std::path::absolute(&args.dir)?;
+    std::fs::create_dir_all(&dir)?;
+
+    let report = backup_now(&client, &dir).await?;
+    report_errors(&report.archive_errors);
+    println!("{}", report.archive_path.display());
+    Ok(())
+}
+
+pub async fn restore(server: &str, args: RestoreArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let archive = std::path::absolute(&args.archive)?;
+
+    let errors = restore_backup(&client, &archive).await?;
+    report_errors(&errors);
+    Ok(())
+}
+
+fn report_errors(errors: &[ArchiveError]) {
+    for error in errors {
+        match error {
+            ArchiveError::Import { import_error } => eprintln!("warning: {}", import_error),
+            ArchiveError::ImportFile { file, file_error } => {
+                eprintln!("warning: {}: {}", file, file_error)
+            }
+            ArchiveError::Unknown(value) => eprintln!("warning: {}", value),
+        }
+    }
+}
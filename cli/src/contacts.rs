@@ -0,0 +1,155 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use clap::{Args, Subcommand};
+use muchat_providers::chat::{client::Connecting, link::ConnectionLink};
+
+use crate::connect;
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Print one JSON object per line instead of a table.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+pub enum AddressCommand {
+    /// Print the user's contact address.
+    Show,
+    /// Create a contact address for the user.
+    Create,
+}
+
+#[derive(Args)]
+pub struct AcceptArgs {
+    /// Id of the contact request, as reported by `tail` or simplex-chat.
+    contact_request_id: u64,
+
+    /// Show the contact a new random profile instead of the user's.
+    #[arg(long)]
+    incognito: bool,
+}
+
+#[derive(Args)]
+pub struct ConnectArgs {
+    /// A contact address, group link or one-time invitation, in its simplex: or https:// form.
+    link: ConnectionLink,
+
+    /// Show the contact a new random profile instead of the user's.
+    #[arg(long)]
+    incognito: bool,
+}
+
+#[derive(Args)]
+pub struct InviteArgs {
+    /// Show whoever uses the invitation a new random profile instead of the user's.
+    #[arg(long)]
+    incognito: bool,
+}
+
+pub async fn contacts(server: &str, args: ListArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let user = client.active_user().await?;
+    let mut stdout = io::stdout().lock();
+
+    for contact in client.list_contacts(user.user_id).await? {
+        if args.json {
+            serde_json::to_writer(&mut stdout, &contact)?;
+            writeln!(stdout)?;
+        } else {
+            writeln!(
+                stdout,
+                "@{}\t{}\t{}",
+                contact.contact_id, contact.local_display_name, contact.profile.full_name
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn groups(server: &str, args: ListArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let user = client.active_user().await?;
+    let mut stdout = io::stdout().lock();
+
+    for group in client.list_groups(user.user_id).await? {
+        if args.json {
+            serde_json::to_writer(&mut stdout, &group)?;
+            writeln!(stdout)?;
+        } else {
+            writeln!(
+                stdout,
+                "#{}\t{}\t{} members",
+                group.group_info.group_id,
+                group.group_info.local_display_name,
+                group.group_summary.current_members
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn address(server: &str, command: AddressCommand) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+
+    let link = match command {
+        AddressCommand::Show => client
+            .show_address()
+            .await?
+            .ok_or("no contact address, create one with `muchat address create`")?,
+        AddressCommand::Create => client.create_address().await?,
+    };
+
+    println!("{}", link);
+    Ok(())
+}
+
+pub async fn accept(server: &str, args: AcceptArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let contact = if args.incognito {
+        client
+            .accept_contact_incognito(args.contact_request_id)
+            .await?
+    } else {
+        client.accept_contact(args.contact_request_id).await?
+    };
+
+    println!("@{}\t{}", contact.contact_id, contact.local_display_name);
+    Ok(())
+}
+
+pub async fn connect_link(server: &str, args: ConnectArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let user = client.active_user().await?;
+
+    let connecting = if args.incognito {
+        client.connect_incognito(user.user_id, &args.link).await?
+    } else {
+        client.connect(user.user_id, &args.link).await?
+    };
+
+    match connecting {
+        Connecting::Pending(_) => println!("Connection request sent"),
+        Connecting::AlreadyConnected(contact) => println!(
+            "Already connected: @{}\t{}",
+            contact.contact_id, contact.local_display_name
+        ),
+    }
+    Ok(())
+}
+
+pub async fn invite(server: &str, args: InviteArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let user = client.active_user().await?;
+    let invitation = client
+        .create_invitation(user.user_id, args.incognito)
+        .await?;
+
+    println!("{}", invitation.link);
+    Ok(())
+}
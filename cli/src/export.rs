@@ -0,0 +1,93 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Utc};
+use clap::Args;
+use muchat_providers::chat::export::{export_chat, ExportFormat, ExportRange};
+
+use crate::{
+    args::{parse_time, ChatSelector},
+    connect,
+};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Chats to export, as `@<contact id>`, `#<group id>` or by name (quote `#` in most shells).
+    #[arg(required = true)]
+    chats: Vec<ChatSelector>,
+
+    /// One of `jsonl`, `html` or `text`.
+    #[arg(long, short, default_value = "jsonl")]
+    format: ExportFormat,
+
+    /// Only export items sent at or after this date.
+    #[arg(long, value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+
+    /// Only export items sent before this date.
+    #[arg(long, value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+
+    /// File to write a single chat to, or directory to write several chats to. A single chat is
+    /// written to stdout by default.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub async fn run(server: &str, args: ExportArgs) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let range = ExportRange {
+        since: args.since,
+        until: args.until,
+    };
+
+    let mut chats = Vec::new();
+    for chat in &args.chats {
+        chats.push(chat.resolve(&client).await?);
+    }
+
+    if let [chat] = chats.as_slice() {
+        if args.output.as_ref().is_none_or(|path| !path.is_dir()) {
+            let mut writer: Box<dyn Write> = match &args.output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(BufWriter::new(io::stdout().lock())),
+            };
+            export_chat(
+                &client,
+                chat.chat_type,
+                chat.chat_id,
+                &range,
+                args.format,
+                &mut writer,
+            )
+            .await?;
+            writer.flush()?;
+            return Ok(());
+        }
+    }
+
+    let dir = args.output.unwrap_or_else(|| PathBuf::from("."));
+    fs::create_dir_all(&dir)?;
+
+    for chat in &chats {
+        let path = dir.join(format!("{}.{}", chat.file_stem(), args.format.extension()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        export_chat(
+            &client,
+            chat.chat_type,
+            chat.chat_id,
+            &range,
+            args.format,
+            &mut writer,
+        )
+        .await?;
+        writer.flush()?;
+        eprintln!("exported {}", path.display());
+    }
+
+    Ok(())
+}
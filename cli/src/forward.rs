@@ -0,0 +1,60 @@
+use std::{error::Error, path::PathBuf, sync::Arc};
+
+use clap::Args;
+use muchat_providers::chat::{
+    client::ChatClient,
+    webhook::{forward_events, Forwarder, WebhookConfig, WebhookEvent},
+};
+
+use crate::connect;
+
+#[derive(Args)]
+pub struct ForwardArgs {
+    /// Url events are POSTed to. A non-empty response body is sent back to the chat as a reply.
+    url: String,
+
+    /// Key for the `x-muchat-signature` header, an HMAC-SHA256 of `<timestamp>.<body>`.
+    #[arg(long, env = "MUCHAT_WEBHOOK_SECRET", hide_env_values = true)]
+    secret: String,
+
+    /// Events to forward: newChatItems, receivedContactRequest, contactConnected or
+    /// fileComplete. Forwards all of them if not given.
+    #[arg(long = "event", value_name = "EVENT")]
+    events: Vec<WebhookEvent>,
+
+    /// Attempts made before a payload is written to the dead-letter file.
+    #[arg(long, default_value_t = 5)]
+    max_attempts: u32,
+
+    /// JSON Lines file for payloads that could not be delivered.
+    #[arg(long, default_value = "muchat-webhooks.jsonl")]
+    dead_letters: PathBuf,
+
+    /// Deliver the payloads in the dead-letter file again and exit.
+    #[arg(long)]
+    replay: bool,
+}
+
+/// Forwards events to a webhook until the connection closes.
+pub async fn run(server: &str, args: ForwardArgs) -> Result<(), Box<dyn Error>> {
+    let mut config = WebhookConfig::new(args.url, args.secret, args.dead_letters);
+    config.max_attempts = args.max_attempts.max(1);
+    if !args.events.is_empty() {
+        config.events = args.events.into_iter().collect();
+    }
+    let forwarder = Arc::new(Forwarder::new(config)?);
+
+    if args.replay {
+        let client = connect(server).await?;
+        let report = forwarder.replay_dead_letters(&client).await?;
+        eprintln!(
+            "delivered {} payloads, {} failed again",
+            report.delivered, report.failed
+        );
+        return Ok(());
+    }
+
+    let (client, stream) = ChatClient::new(server.to_string()).await?;
+    forward_events(Arc::new(client), Box::pin(stream.await), forwarder).await;
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::{path::PathBuf, process::ExitCode, sync::Arc};
+
+use clap::{Parser, Subcommand};
+use futures::StreamExt;
+use muchat_providers::chat::{client::ChatClient, error::TransportError};
+
+mod args;
+mod backup;
+mod contacts;
+mod export;
+mod forward;
+mod qr;
+mod send;
+mod servers;
+mod spawn;
+mod tail;
+
+#[derive(Parser)]
+#[command(
+    name = "muchat",
+    version,
+    about = "Command line client for simplex-chat"
+)]
+struct Cli {
+    /// Websocket url of a running simplex-chat instance (`simplex-chat -p 5225`).
+    #[arg(
+        long,
+        global = true,
+        env = "MUCHAT_SERVER",
+        default_value = "ws://localhost:5225"
+    )]
+    server: String,
+
+    /// Start simplex-chat for the duration of the command, instead of connecting to a running
+    /// instance.
+    #[arg(long, global = true)]
+    spawn: bool,
+
+    /// Database prefix used by a spawned simplex-chat.
+    #[arg(long, global = true, env = "MUCHAT_DATABASE", default_value = "muchat")]
+    database: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Send a text message to a contact or group.
+    Send(send::SendArgs),
+    /// Print new messages as JSON lines.
+    Tail(tail::TailArgs),
+    /// POST chat events to a webhook, sending its responses back as replies.
+    Forward(forward::ForwardArgs),
+    /// List the user's contacts.
+    Contacts(contacts::ListArgs),
+    /// List the user's groups.
+    Groups(contacts::ListArgs),
+    /// Show or create the user's contact address.
+    #[command(subcommand)]
+    Address(contacts::AddressCommand),
+    /// Accept a contact request.
+    Accept(contacts::AcceptArgs),
+    /// Connect to a contact address, group link or invitation.
+    Connect(contacts::ConnectArgs),
+    /// Create a one-time invitation link.
+    Invite(contacts::InviteArgs),
+    /// Show a link as a QR code, or read one from an image.
+    Qr(qr::QrArgs),
+    /// List, pin or test the SMP and XFTP servers new connections use.
+    #[command(subcommand)]
+    Servers(servers::ServersCommand),
+    /// Export the history of one or more chats.
+    Export(export::ExportArgs),
+    /// Archive the simplex-chat database, stopping the chat while it is written.
+    Backup(backup::BackupArgs),
+    /// Replace the simplex-chat database with an archive.
+    Restore(backup::RestoreArgs),
+}
+
+/// Connects to simplex-chat, reading the message stream in the background so that requests
+/// get their responses.
+async fn connect(url: &str) -> Result<Arc<ChatClient>, TransportError> {
+    let (client, stream) = ChatClient::new(url.to_string()).await?;
+    let stream = Box::pin(stream.await);
+    tokio::spawn(stream.for_each(|_| async {}));
+    Ok(Arc::new(client))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let _chat = if cli.spawn {
+        match spawn::spawn_chat(&cli.server, &cli.database).await {
+            Ok(child) => Some(child),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        None
+    };
+
+    let result = match cli.command {
+        Command::Send(args) => send::run(&cli.server, args).await,
+        Command::Tail(args) => tail::run(&cli.server, args).await,
+        Command::Forward(args) => forward::run(&cli.server, args).await,
+        Command::Contacts(args) => contacts::contacts(&cli.server, args).await,
+        Command::Groups(args) => contacts::groups(&cli.server, args).await,
+        Command::Address(command) => contacts::address(&cli.server, command).await,
+        Command::Accept(args) => contacts::accept(&cli.server, args).await,
+        Command::Connect(args) => contacts::connect_link(&cli.server, args).await,
+        Command::Invite(args) => contacts::invite(&cli.server, args).await,
+        Command::Qr(args) => qr::run(&cli.server, args).await,
+        Command::Servers(command) => servers::run(&cli.server, command).await,
+        Command::Export(args) => export::run(&cli.server, args).await,
+        Command::Backup(args) => backup::backup(&cli.server, args).await,
+        Command::Restore(args) => backup::restore(&cli.server, args).await,
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::{error::Error, fs, path::PathBuf};
+
+use clap::Args;
+use muchat_providers::chat::{link::ConnectionLink, qr};
+
+use crate::connect;
+
+#[derive(Args)]
+pub struct QrArgs {
+    /// Link to show as a QR code. Defaults to the user's contact address.
+    #[arg(conflicts_with = "decode")]
+    link: Option<String>,
+
+    /// Write the code to an SVG file instead of printing it.
+    #[arg(long)]
+    svg: Option<PathBuf>,
+
+    /// Write the code to a PNG file instead of printing it.
+    #[arg(long)]
+    png: Option<PathBuf>,
+
+    /// Print the link in a PNG image of a QR code instead.
+    #[arg(long, value_name = "IMAGE", conflicts_with_all = ["svg", "png"])]
+    decode: Option<PathBuf>,
+}
+
+pub async fn run(server: &str, args: QrArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(image) = args.decode {
+        println!("{}", qr::scan_link(image)?);
+        return Ok(());
+    }
+
+    let link = match args.link {
+        Some(link) => link,
+        None => connect(server)
+            .await?
+            .show_address()
+            .await?
+            .ok_or("no contact address, create one with `muchat address create`")?,
+    };
+    link.parse::<ConnectionLink>()?;
+
+    if let Some(path) = &args.svg {
+        fs::write(path, qr::to_svg(&link)?)?;
+    }
+    if let Some(path) = &args.png {
+        fs::write(path, qr::to_png(&link, 8)?)?;
+    }
+    if args.svg.is_none() && args.png.is_none() {
+        print!("{}", qr::to_terminal(&link)?);
+    }
+
+    Ok(())
+}
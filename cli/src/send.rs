@@ -0,0 +1,72 @@
+use std::{
+    error::Error,
+    io::{self, Read},
+    time::Duration,
+};
+
+use clap::Args;
+use muchat_providers::chat::{
+    commands::ComposedMessage,
+    response::{MCText, MsgContent},
+};
+
+use crate::{args::ChatSelector, connect};
+
+#[derive(Args)]
+pub struct SendArgs {
+    /// Contact or group to send to, as `alice`, `@2`, `#team` or `#7`.
+    chat: ChatSelector,
+
+    /// Text to send. Read from stdin when omitted.
+    text: Vec<String>,
+
+    /// Make the message disappear this many seconds after it is read.
+    #[arg(long, value_name = "SECONDS")]
+    ttl: Option<u64>,
+}
+
+pub async fn run(server: &str, args: SendArgs) -> Result<(), Box<dyn Error>> {
+    let text = if args.text.is_empty() {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text.trim_end().to_string()
+    } else {
+        args.text.join(" ")
+    };
+
+    if text.is_empty() {
+        return Err("nothing to send".into());
+    }
+
+    let client = connect(server).await?;
+    let chat = args.chat.resolve(&client).await?;
+    let message = ComposedMessage {
+        file_path: None,
+        quoted_item_id: None,
+        msg_content: MsgContent::Text(MCText { text }),
+    };
+
+    let items = match args.ttl {
+        Some(ttl) => {
+            client
+                .send_timed_messages(
+                    chat.chat_type,
+                    chat.chat_id,
+                    &[message],
+                    Duration::from_secs(ttl),
+                )
+                .await?
+        }
+        None => {
+            client
+                .send_messages(chat.chat_type, chat.chat_id, &[message])
+                .await?
+        }
+    };
+
+    for item in items {
+        println!("{}", item.chat_item.meta.item_id);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use clap::{Args, Subcommand};
+use muchat_providers::chat::servers::{ServerAddress, ServerProtocol};
+
+use crate::connect;
+
+#[derive(Subcommand)]
+pub enum ServersCommand {
+    /// List the user's servers.
+    List(ListServersArgs),
+    /// Make new connections use only the given servers. The presets are kept, disabled.
+    Pin(PinArgs),
+    /// Check that a server can be connected to.
+    Test(TestArgs),
+}
+
+#[derive(Args)]
+pub struct ListServersArgs {
+    /// `smp` for message relays, `xftp` for file relays.
+    #[arg(default_value = "smp")]
+    protocol: ServerProtocol,
+
+    /// Print one JSON object per line instead of a table.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct PinArgs {
+    /// Server addresses, all `smp://` or all `xftp://`.
+    #[arg(required = true)]
+    servers: Vec<ServerAddress>,
+}
+
+#[derive(Args)]
+pub struct TestArgs {
+    server: ServerAddress,
+}
+
+pub async fn run(server: &str, command: ServersCommand) -> Result<(), Box<dyn Error>> {
+    let client = connect(server).await?;
+    let user = client.active_user().await?;
+    let mut stdout = io::stdout().lock();
+
+    match command {
+        ServersCommand::List(args) => {
+            let servers = client.get_servers(user.user_id, args.protocol).await?;
+            for cfg in servers.proto_servers {
+                if args.json {
+                    serde_json::to_writer(&mut stdout, &cfg)?;
+                    writeln!(stdout)?;
+                } else {
+                    writeln!(
+                        stdout,
+                        "{}\t{}\t{}",
+                        cfg.server,
+                        if cfg.preset { "preset" } else { "custom" },
+                        if cfg.enabled { "enabled" } else { "disabled" }
+                    )?;
+                }
+            }
+        }
+        ServersCommand::Pin(args) => {
+            let protocol = args.servers[0].protocol;
+            if args
+                .servers
+                .iter()
+                .any(|server| server.protocol != protocol)
+            {
+                return Err("cannot pin smp and xftp servers together".into());
+            }
+
+            let servers = client.get_servers(user.user_id, protocol).await?;
+            client
+                .set_servers(user.user_id, protocol, &servers.pinned_to(args.servers))
+                .await?;
+        }
+        ServersCommand::Test(args) => match client.test_server(user.user_id, &args.server).await? {
+            None => writeln!(stdout, "ok")?,
+            Some(failure) => {
+                return Err(format!("failed to {}: {}", failure.test_step, failure.test_error).into())
+            }
+        },
+    }
+
+    Ok(())
+}
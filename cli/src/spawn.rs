@@ -0,0 +1,123 @@
+//! Running simplex-chat as a child process, for machines without a long-running instance.
+
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use tokio::{
+    io,
+    net::TcpStream,
+    process::{Child, Command},
+    time::{sleep, Instant},
+};
+
+/// How long to wait for a spawned simplex-chat to accept connections.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Starts `simplex-chat` serving websockets on the port of `server`, and waits until its host
+/// accepts connections. The process is killed when the returned handle is dropped.
+///
+/// The database must already have a profile: simplex-chat asks for one on a terminal, which a
+/// spawned instance does not have. Its output goes to stderr, to keep stdout for the command.
+pub async fn spawn_chat(server: &str, database: &Path) -> Result<Child, Box<dyn Error>> {
+    let (host, port) =
+        server_addr(server).ok_or_else(|| format!("no port in server url {}", server))?;
+
+    if !chat_database(database).exists() {
+        return Err(format!(
+            "no simplex-chat database at {}, run `simplex-chat -d {}` once to create a profile",
+            chat_database(database).display(),
+            database.display()
+        )
+        .into());
+    }
+
+    let mut child = Command::new("simplex-chat")
+        .arg("-p")
+        .arg(port.to_string())
+        .arg("-d")
+        .arg(database)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to start simplex-chat: {}", e))?;
+
+    if let Some(mut stdout) = child.stdout.take() {
+        tokio::spawn(async move { io::copy(&mut stdout, &mut io::stderr()).await });
+    }
+
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if TcpStream::connect((host, port)).await.is_ok() {
+            return Ok(child);
+        }
+
+        if let Some(status) = child.try_wait()? {
+            // With no terminal to ask on, simplex-chat exits if the database has no profile.
+            return Err(format!(
+                "simplex-chat exited with {}, check that {} has a profile",
+                status,
+                database.display()
+            )
+            .into());
+        }
+
+        if Instant::now() > deadline {
+            return Err("timed out waiting for simplex-chat to start".into());
+        }
+
+        sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// The chat database simplex-chat keeps at a database prefix, next to the agent's.
+fn chat_database(prefix: &Path) -> PathBuf {
+    let mut name = prefix.as_os_str().to_owned();
+    name.push("_chat.db");
+    PathBuf::from(name)
+}
+
+/// The host and port of a `ws://host:port/path` url.
+fn server_addr(server: &str) -> Option<(&str, u16)> {
+    let authority = server.split("://").nth(1)?.split('/').next()?;
+    let (host, port) = authority.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host, port.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_addr() {
+        assert_eq!(
+            server_addr("ws://localhost:5225"),
+            Some(("localhost", 5225))
+        );
+        assert_eq!(
+            server_addr("ws://127.0.0.1:6000/chat"),
+            Some(("127.0.0.1", 6000))
+        );
+        assert_eq!(server_addr("ws://[::1]:5225"), Some(("::1", 5225)));
+        assert_eq!(server_addr("ws://localhost"), None);
+    }
+
+    #[tokio::test]
+    async fn test_missing_database() {
+        let prefix = std::env::temp_dir().join(format!("muchat-missing-{}", std::process::id()));
+        assert_eq!(
+            chat_database(&prefix),
+            std::env::temp_dir().join(format!("muchat-missing-{}_chat.db", std::process::id()))
+        );
+
+        let error = spawn_chat("ws://localhost:5225", &prefix)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("no simplex-chat database"));
+    }
+}
@@ -0,0 +1,47 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use clap::Args;
+use futures::StreamExt;
+use muchat_providers::chat::{
+    client::ChatClient,
+    response::{ChatResponse, DirectionType},
+};
+
+#[derive(Args)]
+pub struct TailArgs {
+    /// Also print messages sent by the user.
+    #[arg(long)]
+    sent: bool,
+}
+
+/// Prints new chat items as JSON lines until the connection closes.
+pub async fn run(server: &str, args: TailArgs) -> Result<(), Box<dyn Error>> {
+    let (_client, stream) = ChatClient::new(server.to_string()).await?;
+    let mut stream = Box::pin(stream.await);
+    let mut stdout = io::stdout().lock();
+
+    while let Some(message) = stream.next().await {
+        let ChatResponse::NewChatItems { chat_items, .. } = message?.resp else {
+            continue;
+        };
+
+        for item in chat_items {
+            let sent = matches!(
+                item.chat_item.chat_dir.direction_type,
+                DirectionType::DirectSnd | DirectionType::GroupSnd
+            );
+            if sent && !args.sent {
+                continue;
+            }
+
+            serde_json::to_writer(&mut stdout, &item)?;
+            writeln!(stdout)?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
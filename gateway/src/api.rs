@@ -0,0 +1,685 @@
+//! The REST API: its routes, handlers and OpenAPI document.
+
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use async_stream::stream;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, post},
+    Json, Router,
+};
+use futures::Stream;
+use muchat_providers::chat::{
+    client::ChatClient,
+    commands::ComposedMessage,
+    openapi::UnknownVariants,
+    response::{
+        AChatItem, Chat, ChatInfoType, ChatItemId, Contact, GroupInfoSummary, MCText, MsgContent,
+        ServerResponse,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    IntoParams, Modify, OpenApi, ToSchema,
+};
+
+use crate::{auth, error::ErrorBody, error::GatewayError};
+
+/// How many items a chat page has when the request does not say.
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// How many events a slow SSE subscriber can fall behind before it misses some.
+const EVENT_BUFFER: usize = 1024;
+
+#[derive(Clone)]
+pub struct Gateway {
+    pub client: Arc<ChatClient>,
+    pub events: broadcast::Sender<ServerResponse>,
+    pub token: Arc<str>,
+}
+
+impl Gateway {
+    pub fn new(client: ChatClient, token: impl Into<Arc<str>>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+
+        Self {
+            client: Arc::new(client),
+            events,
+            token: token.into(),
+        }
+    }
+}
+
+pub fn router(gateway: Gateway) -> Router {
+    let api = Router::new()
+        .route("/v1/chats", get(list_chats))
+        .route(
+            "/v1/chats/{chat_type}/{chat_id}",
+            get(get_chat).delete(delete_chat),
+        )
+        .route(
+            "/v1/chats/{chat_type}/{chat_id}/messages",
+            post(send_message),
+        )
+        .route("/v1/contacts", get(list_contacts))
+        .route("/v1/groups", get(list_groups))
+        .route(
+            "/v1/contact-requests/{contact_request_id}/accept",
+            post(accept_contact),
+        )
+        .route(
+            "/v1/contact-requests/{contact_request_id}/reject",
+            post(reject_contact),
+        )
+        .route("/v1/address", get(show_address).post(create_address))
+        .route("/v1/events", get(events))
+        .route_layer(middleware::from_fn_with_state(
+            gateway.clone(),
+            auth::require_token,
+        ));
+
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .merge(api)
+        .with_state(gateway)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "muchat gateway",
+        description = "HTTP access to a simplex-chat instance. Chat payloads are simplex-chat's own JSON."
+    ),
+    paths(
+        list_chats,
+        get_chat,
+        delete_chat,
+        send_message,
+        list_contacts,
+        list_groups,
+        accept_contact,
+        reject_contact,
+        show_address,
+        create_address,
+        events
+    ),
+    components(schemas(
+        ChatList,
+        ChatPage,
+        SendMessage,
+        SentMessages,
+        ContactList,
+        GroupList,
+        AcceptedContact,
+        Address,
+        ErrorBody
+    )),
+    modifiers(&BearerAuth, &UnknownVariants),
+    security(("bearer" = []))
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+    }
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatList {
+    /// simplex-chat `Chat`s, each with its latest item.
+    pub chats: Vec<Chat>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatPage {
+    /// A simplex-chat `Chat` with the requested items, oldest first.
+    pub chat: Chat,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct PageQuery {
+    /// Return the items before this one, instead of the latest.
+    pub before: Option<ChatItemId>,
+    /// How many items to return, 50 if unset.
+    pub count: Option<usize>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteQuery {
+    /// Tell the contact they were deleted, false if unset.
+    pub notify: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendMessage {
+    pub text: String,
+    /// The item this message replies to.
+    pub quoted_item_id: Option<ChatItemId>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SentMessages {
+    /// simplex-chat `AChatItem`s for the sent messages.
+    pub chat_items: Vec<AChatItem>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactList {
+    /// simplex-chat `Contact`s.
+    pub contacts: Vec<Contact>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupList {
+    /// simplex-chat `GroupInfoSummary`s.
+    pub groups: Vec<GroupInfoSummary>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptedContact {
+    /// The simplex-chat `Contact` created for the request.
+    pub contact: Contact,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Address {
+    /// The user's contact address, unset if they have none.
+    pub address: Option<String>,
+}
+
+/// The active user's chats, most recently active first.
+#[utoipa::path(
+    get,
+    path = "/v1/chats",
+    responses(
+        (status = 200, body = ChatList),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn list_chats(State(gateway): State<Gateway>) -> Result<Json<ChatList>, GatewayError> {
+    let user = gateway.client.active_user().await?;
+    let chats = gateway.client.get_chats(user.user_id).await?;
+
+    Ok(Json(ChatList { chats }))
+}
+
+/// A page of a chat's history.
+#[utoipa::path(
+    get,
+    path = "/v1/chats/{chat_type}/{chat_id}",
+    params(
+        ("chat_type" = String, Path, description = "`direct` or `group`"),
+        ("chat_id" = u64, Path, description = "Contact or group ID"),
+        PageQuery
+    ),
+    responses(
+        (status = 200, body = ChatPage),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn get_chat(
+    State(gateway): State<Gateway>,
+    Path((chat_type, chat_id)): Path<(ChatInfoType, u64)>,
+    Query(page): Query<PageQuery>,
+) -> Result<Json<ChatPage>, GatewayError> {
+    let chat = gateway
+        .client
+        .get_chat(
+            chat_type,
+            chat_id,
+            page.before,
+            page.count.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .await?;
+
+    Ok(Json(ChatPage { chat }))
+}
+
+/// Deletes a contact, or leaves and deletes a group, with its history.
+#[utoipa::path(
+    delete,
+    path = "/v1/chats/{chat_type}/{chat_id}",
+    params(
+        ("chat_type" = String, Path, description = "`direct` or `group`"),
+        ("chat_id" = u64, Path, description = "Contact or group ID"),
+        DeleteQuery
+    ),
+    responses(
+        (status = 204, description = "The chat was deleted"),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn delete_chat(
+    State(gateway): State<Gateway>,
+    Path((chat_type, chat_id)): Path<(ChatInfoType, u64)>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<StatusCode, GatewayError> {
+    gateway
+        .client
+        .delete_chat(chat_type, chat_id, query.notify.unwrap_or(false))
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sends a text message to a contact or group.
+#[utoipa::path(
+    post,
+    path = "/v1/chats/{chat_type}/{chat_id}/messages",
+    params(
+        ("chat_type" = String, Path, description = "`direct` or `group`"),
+        ("chat_id" = u64, Path, description = "Contact or group ID")
+    ),
+    request_body = SendMessage,
+    responses(
+        (status = 200, body = SentMessages),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn send_message(
+    State(gateway): State<Gateway>,
+    Path((chat_type, chat_id)): Path<(ChatInfoType, u64)>,
+    Json(message): Json<SendMessage>,
+) -> Result<Json<SentMessages>, GatewayError> {
+    let composed = ComposedMessage {
+        file_path: None,
+        quoted_item_id: message.quoted_item_id,
+        msg_content: MsgContent::Text(MCText { text: message.text }),
+    };
+    let chat_items = gateway
+        .client
+        .send_messages(chat_type, chat_id, &[composed])
+        .await?;
+
+    Ok(Json(SentMessages { chat_items }))
+}
+
+/// The active user's contacts.
+#[utoipa::path(
+    get,
+    path = "/v1/contacts",
+    responses(
+        (status = 200, body = ContactList),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn list_contacts(State(gateway): State<Gateway>) -> Result<Json<ContactList>, GatewayError> {
+    let user = gateway.client.active_user().await?;
+    let contacts = gateway.client.list_contacts(user.user_id).await?;
+
+    Ok(Json(ContactList { contacts }))
+}
+
+/// The active user's groups.
+#[utoipa::path(
+    get,
+    path = "/v1/groups",
+    responses(
+        (status = 200, body = GroupList),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn list_groups(State(gateway): State<Gateway>) -> Result<Json<GroupList>, GatewayError> {
+    let user = gateway.client.active_user().await?;
+    let groups = gateway.client.list_groups(user.user_id).await?;
+
+    Ok(Json(GroupList { groups }))
+}
+
+/// Accepts a contact request made to the user's address.
+#[utoipa::path(
+    post,
+    path = "/v1/contact-requests/{contact_request_id}/accept",
+    params(("contact_request_id" = u64, Path, description = "Contact request ID")),
+    responses(
+        (status = 200, body = AcceptedContact),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn accept_contact(
+    State(gateway): State<Gateway>,
+    Path(contact_request_id): Path<u64>,
+) -> Result<Json<AcceptedContact>, GatewayError> {
+    let contact = gateway.client.accept_contact(contact_request_id).await?;
+
+    Ok(Json(AcceptedContact { contact }))
+}
+
+/// Rejects a contact request made to the user's address, without telling the requester.
+#[utoipa::path(
+    post,
+    path = "/v1/contact-requests/{contact_request_id}/reject",
+    params(("contact_request_id" = u64, Path, description = "Contact request ID")),
+    responses(
+        (status = 204, description = "The request was rejected"),
+        (status = 400, body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn reject_contact(
+    State(gateway): State<Gateway>,
+    Path(contact_request_id): Path<u64>,
+) -> Result<StatusCode, GatewayError> {
+    gateway.client.reject_contact(contact_request_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The user's contact address.
+#[utoipa::path(
+    get,
+    path = "/v1/address",
+    responses(
+        (status = 200, body = Address),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn show_address(State(gateway): State<Gateway>) -> Result<Json<Address>, GatewayError> {
+    let address = gateway.client.show_address().await?;
+
+    Ok(Json(Address { address }))
+}
+
+/// Creates a contact address for the user.
+#[utoipa::path(
+    post,
+    path = "/v1/address",
+    responses(
+        (status = 200, body = Address),
+        (status = 400, body = ErrorBody, description = "The user already has an address"),
+        (status = 401, body = ErrorBody),
+        (status = 502, body = ErrorBody)
+    )
+)]
+async fn create_address(State(gateway): State<Gateway>) -> Result<Json<Address>, GatewayError> {
+    let address = gateway.client.create_address().await?;
+
+    Ok(Json(Address {
+        address: Some(address),
+    }))
+}
+
+/// Everything simplex-chat reports, as Server-Sent Events.
+///
+/// Each event is named after the response type, like `newChatItems`, and its data is the
+/// `ServerResponse` as JSON. A subscriber that falls too far behind gets a `lagged` event with
+/// the number of events it missed.
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    responses(
+        (status = 200, content_type = "text/event-stream", description = "A stream of simplex-chat responses"),
+        (status = 401, body = ErrorBody)
+    )
+)]
+async fn events(
+    State(gateway): State<Gateway>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut receiver = gateway.events.subscribe();
+
+    let stream = stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(response) => yield Ok(server_event(&response)),
+                Err(RecvError::Lagged(missed)) => {
+                    yield Ok(Event::default().event("lagged").data(missed.to_string()))
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+}
+
+fn server_event(response: &ServerResponse) -> Event {
+    let value = serde_json::to_value(response).unwrap_or_default();
+    let name = value["resp"]["type"]
+        .as_str()
+        .unwrap_or("unknown")
+        .to_string();
+
+    Event::default().event(name).data(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header::AUTHORIZATION, Request},
+    };
+    use futures::StreamExt;
+    use muchat_providers::chat::fixtures::{contact, mock_server, user};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const TOKEN: &str = "secret";
+
+    /// A simplex-chat stand-in answering every command with `handler(cmd)`.
+    async fn gateway(handler: impl Fn(&str) -> Value + Send + 'static) -> Gateway {
+        let url = mock_server(handler).await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        let gateway = Gateway::new(client, TOKEN);
+        let events = gateway.events.clone();
+        tokio::spawn(Box::pin(stream.await).for_each(move |message| {
+            if let Ok(message) = message {
+                let _ = events.send(message);
+            }
+            async {}
+        }));
+
+        gateway
+    }
+
+    async fn call(gateway: &Gateway, request: Request<Body>) -> (StatusCode, Value) {
+        let response = router(gateway.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn authorized(method: &str, uri: &str, body: Option<Value>) -> Request<Body> {
+        let builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(AUTHORIZATION, format!("Bearer {}", TOKEN));
+
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requires_token() {
+        let gateway = gateway(|_| json!({ "type": "cmdOk" })).await;
+
+        let (status, body) = call(
+            &gateway,
+            Request::get("/v1/address").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "missing or invalid bearer token");
+
+        let (status, _) = call(
+            &gateway,
+            Request::get("/v1/address")
+                .header(AUTHORIZATION, "Bearer wrong")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // The API description is public.
+        let (status, body) = call(
+            &gateway,
+            Request::get("/openapi.json").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["paths"]["/v1/chats/{chat_type}/{chat_id}/messages"]["post"].is_object());
+        assert!(body["components"]["securitySchemes"]["bearer"].is_object());
+        assert_eq!(
+            body["components"]["schemas"]["ChatList"]["properties"]["chats"]["items"]["$ref"],
+            "#/components/schemas/Chat"
+        );
+        assert!(body["components"]["schemas"]["ChatItem"]["properties"]["meta"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_send_message() {
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = Arc::clone(&sent);
+        let gateway = gateway(move |cmd| {
+            log.lock().unwrap().push(cmd.to_string());
+            json!({ "type": "newChatItems", "user": user(), "chatItems": [] })
+        })
+        .await;
+
+        let (status, body) = call(
+            &gateway,
+            authorized(
+                "POST",
+                "/v1/chats/group/7/messages",
+                Some(json!({ "text": "hi", "quotedItemId": 3 })),
+            ),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "chatItems": [] }));
+        assert_eq!(
+            sent.lock().unwrap().as_slice(),
+            [r#"/_send #7 json [{"quotedItemId":3,"msgContent":{"type":"text","text":"hi"}}]"#]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manage_contacts() {
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = Arc::clone(&sent);
+        let gateway = gateway(move |cmd| {
+            log.lock().unwrap().push(cmd.to_string());
+            match cmd {
+                "/_delete @2 notify=off" => {
+                    json!({ "type": "contactDeleted", "user": user(), "contact": contact(2, "alice") })
+                }
+                "/_reject 4" => json!({
+                    "type": "contactRequestRejected",
+                    "user": user(),
+                    "contactRequest": {
+                        "contactRequestId": 4,
+                        "localDisplayName": "bob",
+                        "profile": {
+                            "profileId": 104,
+                            "displayName": "bob",
+                            "fullName": "",
+                            "localAlias": ""
+                        },
+                        "createdAt": "2024-12-20T10:00:00Z"
+                    }
+                }),
+                _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+            }
+        })
+        .await;
+
+        let (status, _) = call(&gateway, authorized("DELETE", "/v1/chats/direct/2", None)).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (status, _) = call(
+            &gateway,
+            authorized("POST", "/v1/contact-requests/4/reject", None),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        assert_eq!(
+            sent.lock().unwrap().as_slice(),
+            ["/_delete @2 notify=off", "/_reject 4"]
+        );
+
+        let (_, body) = call(
+            &gateway,
+            Request::get("/openapi.json").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert!(body["paths"]["/v1/chats/{chat_type}/{chat_id}"]["delete"].is_object());
+        assert!(
+            body["paths"]["/v1/contact-requests/{contact_request_id}/reject"]["post"].is_object()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chat_errors_are_bad_requests() {
+        let gateway = gateway(|_| {
+            json!({
+                "type": "chatCmdError",
+                "chatError": { "type": "errorStore", "storeError": { "type": "contactNotFound" } }
+            })
+        })
+        .await;
+
+        let (status, body) = call(
+            &gateway,
+            authorized("POST", "/v1/contact-requests/4/accept", None),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("simplex-chat rejected the command"));
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use muchat_providers::chat::error::{CommandError, TransportError};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug)]
+pub enum GatewayError {
+    /// The request is missing the bearer token, or has the wrong one.
+    Unauthorized,
+    /// simplex-chat rejected the command.
+    Chat(String),
+    /// simplex-chat could not be reached, or answered with something unexpected.
+    Upstream(String),
+}
+
+/// The body of every error response.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+impl GatewayError {
+    fn status(&self) -> StatusCode {
+        match self {
+            GatewayError::Unauthorized => StatusCode::UNAUTHORIZED,
+            GatewayError::Chat(_) => StatusCode::BAD_REQUEST,
+            GatewayError::Upstream(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+impl From<CommandError> for GatewayError {
+    fn from(err: CommandError) -> Self {
+        match err {
+            CommandError::Chat(chat_error) => GatewayError::Chat(format!("{:?}", chat_error)),
+            other => GatewayError::Upstream(other.to_string()),
+        }
+    }
+}
+
+impl From<TransportError> for GatewayError {
+    fn from(err: TransportError) -> Self {
+        GatewayError::Upstream(err.to_string())
+    }
+}
+
+impl fmt::Display for GatewayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GatewayError::Unauthorized => write!(f, "missing or invalid bearer token"),
+            GatewayError::Chat(msg) => write!(f, "simplex-chat rejected the command: {}", msg),
+            GatewayError::Upstream(msg) => write!(f, "simplex-chat is unavailable: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GatewayError {}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.to_string(),
+        };
+
+        (self.status(), Json(body)).into_response()
+    }
+}
@@ -0,0 +1,85 @@
+use std::{net::SocketAddr, process::ExitCode};
+
+use clap::Parser;
+use futures::StreamExt;
+use muchat_providers::chat::client::ChatClient;
+use tokio::net::TcpListener;
+
+mod api;
+mod auth;
+mod error;
+
+#[derive(Parser)]
+#[command(
+    name = "muchat-gateway",
+    version,
+    about = "HTTP and Server-Sent Events gateway for simplex-chat"
+)]
+struct Cli {
+    /// Websocket url of a running simplex-chat instance (`simplex-chat -p 5225`).
+    #[arg(long, env = "MUCHAT_SERVER", default_value = "ws://localhost:5225")]
+    server: String,
+
+    /// Address the gateway listens on.
+    #[arg(long, env = "MUCHAT_GATEWAY_LISTEN", default_value = "127.0.0.1:5226")]
+    listen: SocketAddr,
+
+    /// Token clients must send as `Authorization: Bearer <token>`.
+    #[arg(long, env = "MUCHAT_GATEWAY_TOKEN", hide_env_values = true)]
+    token: String,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    if cli.token.is_empty() {
+        eprintln!("error: the token must not be empty");
+        return ExitCode::FAILURE;
+    }
+
+    let (client, stream) = match ChatClient::new(cli.server.clone()).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("error: could not connect to {}: {}", cli.server, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let gateway = api::Gateway::new(client, cli.token);
+
+    // Reading the stream is what delivers responses to requests, so it runs for as long as the
+    // gateway does. Everything read is passed on to SSE subscribers, if there are any.
+    let events = gateway.events.clone();
+    let mut stream = Box::pin(stream.await);
+    let reader = tokio::spawn(async move {
+        while let Some(message) = stream.next().await {
+            if let Ok(message) = message {
+                let _ = events.send(message);
+            }
+        }
+    });
+
+    let listener = match TcpListener::bind(cli.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: could not listen on {}: {}", cli.listen, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!("listening on http://{}", cli.listen);
+
+    tokio::select! {
+        result = axum::serve(listener, api::router(gateway)) => {
+            if let Err(e) = result {
+                eprintln!("error: {}", e);
+                return ExitCode::FAILURE;
+            }
+            ExitCode::SUCCESS
+        }
+        _ = reader => {
+            eprintln!("error: connection to simplex-chat closed");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Managing simplex-chat's storage: stopping and starting the chat engine, and exporting,
+//! importing and deleting its database.
+
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::{on_off, ChatClient},
+    error::{CommandError, TransportError},
+    response::{ArchiveError, ChatResponse},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveConfig {
+    pub archive_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_compression: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_temp_directory: Option<String>,
+}
+
+impl ArchiveConfig {
+    pub fn new(archive_path: impl Into<String>) -> Self {
+        Self {
+            archive_path: archive_path.into(),
+            disable_compression: None,
+            parent_temp_directory: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartChatOptions {
+    pub subscribe_connections: bool,
+    pub enable_expire_chat_items: bool,
+    pub start_xftp_workers: bool,
+}
+
+impl Default for StartChatOptions {
+    fn default() -> Self {
+        Self {
+            subscribe_connections: true,
+            enable_expire_chat_items: true,
+            start_xftp_workers: true,
+        }
+    }
+}
+
+impl ChatClient {
+    /// Starts the chat engine. Returns `false` if it was already running.
+    pub async fn start_chat(&self, options: &StartChatOptions) -> Result<bool, CommandError> {
+        let command = format!(
+            "/_start subscribe={} expire={} xftp={}",
+            on_off(options.subscribe_connections),
+            on_off(options.enable_expire_chat_items),
+            on_off(options.start_xftp_workers)
+        );
+
+        match self.execute(command).await? {
+            ChatResponse::ChatStarted => Ok(true),
+            ChatResponse::ChatRunning => Ok(false),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Stops the chat engine. Messages are neither sent nor received until it is started again.
+    pub async fn stop_chat(&self) -> Result<(), CommandError> {
+        match self.execute("/_stop".to_string()).await? {
+            ChatResponse::ChatStopped => Ok(()),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Whether the chat engine is started.
+    pub async fn is_chat_running(&self) -> Result<bool, CommandError> {
+        match self.execute("/_check running".to_string()).await? {
+            ChatResponse::ChatRunning => Ok(true),
+            ChatResponse::ChatStopped => Ok(false),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    pub async fn set_temp_folder(&self, path: &str) -> Result<(), CommandError> {
+        self.expect_ok(format!("/_temp_folder {}", path)).await
+    }
+
+    pub async fn set_files_folder(&self, path: &str) -> Result<(), CommandError> {
+        self.expect_ok(format!("/_files_folder {}", path)).await
+    }
+
+    /// Writes the database and files to an archive. The chat must be stopped.
+    pub async fn export_archive(
+        &self,
+        config: &ArchiveConfig,
+    ) -> Result<Vec<ArchiveError>, CommandError> {
+        let command = format!(
+            "/_db export {}",
+            serde_json::to_string(config).map_err(TransportError::from)?
+        );
+
+        match self.execute(command).await? {
+            ChatResponse::ArchiveExported { archive_errors } => Ok(archive_errors),
+            // Older versions of simplex-chat do not report errors.
+            ChatResponse::CmdOk { .. } => Ok(vec![]),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Replaces the database and files with an archive's. The chat must be stopped.
+    ///
+    /// Files that could not be restored are reported rather than failing the import.
+    pub async fn import_archive(
+        &self,
+        config: &ArchiveConfig,
+    ) -> Result<Vec<ArchiveError>, CommandError> {
+        let command = format!(
+            "/_db import {}",
+            serde_json::to_string(config).map_err(TransportError::from)?
+        );
+
+        match self.execute(command).await? {
+            ChatResponse::ArchiveImported { archive_errors } => Ok(archive_errors),
+            ChatResponse::CmdOk { .. } => Ok(vec![]),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Deletes the database and all files. The chat must be stopped.
+    pub async fn delete_storage(&self) -> Result<(), CommandError> {
+        self.expect_ok("/_db delete".to_string()).await
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupReport {
+    pub archive_path: PathBuf,
+    /// Parts of the database that could not be archived.
+    pub archive_errors: Vec<ArchiveError>,
+}
+
+/// Stops the chat, exports an archive into `dir` and starts the chat again.
+///
+/// The chat is started again even if the export fails, so a failed backup never leaves the
+/// chat stopped. A chat that was already stopped is left stopped.
+pub async fn backup_now(client: &ChatClient, dir: &Path) -> Result<BackupReport, CommandError> {
+    let archive_path = dir.join(format!(
+        "simplex-chat.{}.zip",
+        Utc::now().format("%Y%m%dT%H%M%S")
+    ));
+    let config = ArchiveConfig::new(archive_path.to_string_lossy());
+
+    let was_running = stop_if_running(client).await?;
+    let exported = client.export_archive(&config).await;
+    let restarted = restart_if(client, was_running).await;
+
+    let archive_errors = exported?;
+    restarted?;
+
+    Ok(BackupReport {
+        archive_path,
+        archive_errors,
+    })
+}
+
+/// Stops the chat, replaces its database with the archive at `path` and starts it again, if it
+/// was running.
+///
+/// Returns the parts of the archive that could not be restored.
+pub async fn restore_backup(
+    client: &ChatClient,
+    path: &Path,
+) -> Result<Vec<ArchiveError>, CommandError> {
+    let config = ArchiveConfig::new(path.to_string_lossy());
+
+    let was_running = stop_if_running(client).await?;
+    let imported = client.import_archive(&config).await;
+    let restarted = restart_if(client, was_running).await;
+
+    let archive_errors = imported?;
+    restarted?;
+
+    Ok(archive_errors)
+}
+
+/// Stops the chat, returning whether it was running.
+async fn stop_if_running(client: &ChatClient) -> Result<bool, CommandError> {
+    let running = client.is_chat_running().await?;
+    if running {
+        client.stop_chat().await?;
+    }
+    Ok(running)
+}
+
+async fn restart_if(client: &ChatClient, was_running: bool) -> Result<(), CommandError> {
+    if was_running {
+        client.start_chat(&StartChatOptions::default()).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::fixtures::mock_server;
+
+    async fn client(handler: impl Fn(&str) -> Value + Send + 'static) -> ChatClient {
+        let (client, stream) = ChatClient::new(mock_server(handler).await).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+        client
+    }
+
+    fn recording(
+        reply: impl Fn(&str) -> Value + Send + 'static,
+    ) -> (
+        Arc<Mutex<Vec<String>>>,
+        impl Fn(&str) -> Value + Send + 'static,
+    ) {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&commands);
+        (commands, move |cmd: &str| {
+            log.lock().unwrap().push(cmd.to_string());
+            reply(cmd)
+        })
+    }
+
+    fn engine(cmd: &str, export: Value) -> Value {
+        if cmd == "/_check running" {
+            json!({ "type": "chatRunning" })
+        } else if cmd == "/_stop" {
+            json!({ "type": "chatStopped" })
+        } else if cmd.starts_with("/_start") {
+            json!({ "type": "chatStarted" })
+        } else if cmd.starts_with("/_db export") {
+            export
+        } else {
+            json!({ "type": "cmdOk" })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backup_now() {
+        let (commands, handler) = recording(|cmd| {
+            engine(
+                cmd,
+                json!({
+                    "type": "archiveExported",
+                    "archiveErrors": [{ "type": "importFile", "file": "a.jpg", "fileError": "missing" }]
+                }),
+            )
+        });
+        let client = client(handler).await;
+
+        let report = backup_now(&client, Path::new("/backups")).await.unwrap();
+
+        assert!(report.archive_path.starts_with("/backups"));
+        assert!(matches!(
+            report.archive_errors.as_slice(),
+            [ArchiveError::ImportFile { file, .. }] if file == "a.jpg"
+        ));
+
+        let commands = commands.lock().unwrap();
+        assert_eq!(commands[..2], ["/_check running", "/_stop"]);
+        assert!(commands[2].starts_with("/_db export {\"archivePath\":\"/backups/simplex-chat."));
+        assert_eq!(commands[3], "/_start subscribe=on expire=on xftp=on");
+    }
+
+    #[tokio::test]
+    async fn test_backup_leaves_stopped_chat_stopped() {
+        let (commands, handler) = recording(|cmd| match cmd {
+            "/_check running" => json!({ "type": "chatStopped" }),
+            _ => engine(
+                cmd,
+                json!({ "type": "archiveExported", "archiveErrors": [] }),
+            ),
+        });
+        let client = client(handler).await;
+
+        backup_now(&client, Path::new("/backups")).await.unwrap();
+
+        let commands = commands.lock().unwrap();
+        assert_eq!(commands.len(), 2);
+        assert!(commands[1].starts_with("/_db export"));
+    }
+
+    #[tokio::test]
+    async fn test_backup_restarts_after_failure() {
+        let (commands, handler) = recording(|cmd| {
+            engine(
+                cmd,
+                json!({
+                    "type": "chatCmdError",
+                    "chatError": { "type": "error" }
+                }),
+            )
+        });
+        let client = client(handler).await;
+
+        assert!(matches!(
+            backup_now(&client, Path::new("/backups")).await,
+            Err(CommandError::Chat(_))
+        ));
+        assert!(commands.lock().unwrap()[3].starts_with("/_start"));
+    }
+}
@@ -0,0 +1,818 @@
+//! Sending one message to many contacts, politely.
+//!
+//! A broadcast goes to an [`Audience`], resolved to contact IDs when it starts. Sends are spread
+//! over a few concurrent workers and held to a rate limit, and every recipient's result is
+//! appended to a journal, so a broadcast interrupted by a crash can be resumed without messaging
+//! anyone whose result was recorded. Contacts who reply `/stop` are left out of every later
+//! send, until they reply `/start`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::time::{interval, MissedTickBehavior};
+
+use super::{
+    client::ChatClient,
+    commands::ComposedMessage,
+    error::{CommandError, TransportError},
+    response::{ChatInfo, ChatInfoType, ChatItemId, ChatResponse, Contact, DirectionType},
+    utils::{extract_text_content, write_atomically},
+};
+
+const OPT_OUTS_FILE: &str = "opt-outs.json";
+const JOURNAL_EXTENSION: &str = "jsonl";
+
+/// Who a broadcast goes to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Audience {
+    /// Every active contact.
+    AllContacts,
+    /// Active contacts whose chat has the tag with this text.
+    #[serde(rename_all = "camelCase")]
+    Tag { tag: String },
+    /// Members of a group that the user has a direct contact with.
+    #[serde(rename_all = "camelCase")]
+    GroupMembers { group_id: u64 },
+    #[serde(rename_all = "camelCase")]
+    Contacts { contact_ids: Vec<u64> },
+}
+
+impl Audience {
+    /// The IDs of the contacts in the audience.
+    pub async fn resolve(&self, client: &ChatClient) -> Result<Vec<u64>, CommandError> {
+        match self {
+            Audience::AllContacts => {
+                let user = client.active_user().await?;
+                Ok(active_contacts(client.list_contacts(user.user_id).await?)
+                    .map(|contact| contact.contact_id)
+                    .collect())
+            }
+            Audience::Tag { tag } => {
+                let user = client.active_user().await?;
+                let tag_ids: HashSet<u64> = client
+                    .list_chat_tags(user.user_id)
+                    .await?
+                    .into_iter()
+                    .filter(|chat_tag| &chat_tag.chat_tag_text == tag)
+                    .map(|chat_tag| chat_tag.chat_tag_id)
+                    .collect();
+
+                Ok(active_contacts(client.list_contacts(user.user_id).await?)
+                    .filter(|contact| contact.chat_tags.iter().any(|id| tag_ids.contains(id)))
+                    .map(|contact| contact.contact_id)
+                    .collect())
+            }
+            Audience::GroupMembers { group_id } => Ok(client
+                .list_members(*group_id)
+                .await?
+                .members
+                .into_iter()
+                .filter_map(|member| member.member_contact_id)
+                .collect()),
+            Audience::Contacts { contact_ids } => Ok(contact_ids.clone()),
+        }
+    }
+}
+
+fn active_contacts(contacts: Vec<Contact>) -> impl Iterator<Item = Contact> {
+    contacts
+        .into_iter()
+        .filter(|contact| contact.contact_status == "active" && !contact.chat_deleted)
+}
+
+#[derive(Clone, Debug)]
+pub struct BroadcastConfig {
+    /// How many sends can wait for simplex-chat at once.
+    pub concurrency: usize,
+    /// The shortest time between two sends, across all workers.
+    pub min_interval: Duration,
+}
+
+impl Default for BroadcastConfig {
+    /// Up to 4 sends at a time and 5 sends a second.
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            min_interval: Duration::from_millis(200),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum RecipientResult {
+    #[serde(rename_all = "camelCase")]
+    Sent {
+        item_id: ChatItemId,
+    },
+    Failed {
+        error: String,
+    },
+    /// The contact replied `/stop` before their turn came.
+    OptedOut,
+}
+
+#[derive(Clone, Debug)]
+pub struct BroadcastJob {
+    pub id: String,
+    pub message: ComposedMessage,
+    /// Contact IDs, in the order they are messaged.
+    pub recipients: Vec<u64>,
+    pub results: HashMap<u64, RecipientResult>,
+    pub created_at: DateTime<Utc>,
+    pub finished: bool,
+}
+
+impl BroadcastJob {
+    /// Recipients that have no result yet.
+    pub fn pending(&self) -> Vec<u64> {
+        self.recipients
+            .iter()
+            .filter(|contact_id| !self.results.contains_key(contact_id))
+            .copied()
+            .collect()
+    }
+
+    pub fn report(&self) -> BroadcastReport {
+        let mut report = BroadcastReport {
+            pending: self.pending().len(),
+            ..Default::default()
+        };
+
+        for result in self.results.values() {
+            match result {
+                RecipientResult::Sent { .. } => report.sent += 1,
+                RecipientResult::Failed { .. } => report.failed += 1,
+                RecipientResult::OptedOut => report.opted_out += 1,
+            }
+        }
+
+        report
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastReport {
+    pub sent: usize,
+    pub failed: usize,
+    pub opted_out: usize,
+    pub pending: usize,
+}
+
+/// The broadcasts found by [`Broadcaster::unfinished`].
+#[derive(Debug, Default)]
+pub struct Unfinished {
+    /// Broadcasts that were started but not finished, oldest first.
+    pub jobs: Vec<BroadcastJob>,
+    /// The IDs of broadcasts whose journal could not be read, with the reason.
+    pub corrupt: Vec<(String, BroadcastError)>,
+}
+
+/// A contact opting out of broadcasts, or back in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptOutChange {
+    pub contact_id: u64,
+    pub opted_out: bool,
+}
+
+#[derive(Debug)]
+pub enum BroadcastError {
+    Io(String),
+    /// simplex-chat could not be reached, so the broadcast stopped.
+    Transport(String),
+    InvalidFormat(String),
+    /// There is no broadcast with this ID.
+    NotFound(String),
+}
+
+impl From<io::Error> for BroadcastError {
+    fn from(err: io::Error) -> Self {
+        BroadcastError::Io(err.to_string())
+    }
+}
+
+impl From<TransportError> for BroadcastError {
+    fn from(err: TransportError) -> Self {
+        BroadcastError::Transport(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for BroadcastError {
+    fn from(err: serde_json::Error) -> Self {
+        BroadcastError::InvalidFormat(err.to_string())
+    }
+}
+
+impl fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BroadcastError::Io(msg) => write!(f, "BroadcastError: io error: {}", msg),
+            BroadcastError::Transport(msg) => {
+                write!(f, "BroadcastError: transport error: {}", msg)
+            }
+            BroadcastError::InvalidFormat(msg) => {
+                write!(f, "BroadcastError: invalid format: {}", msg)
+            }
+            BroadcastError::NotFound(id) => write!(f, "BroadcastError: no broadcast {}", id),
+        }
+    }
+}
+
+impl std::error::Error for BroadcastError {}
+
+/// A line of a broadcast's journal.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum JournalEntry {
+    #[serde(rename_all = "camelCase")]
+    Started {
+        id: String,
+        message: Box<ComposedMessage>,
+        recipients: Vec<u64>,
+        created_at: DateTime<Utc>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Result {
+        contact_id: u64,
+        result: RecipientResult,
+    },
+    Finished,
+}
+
+/// Runs broadcasts and keeps their journals and the opt-out list in a directory.
+pub struct Broadcaster {
+    dir: PathBuf,
+    opt_outs: Mutex<HashSet<u64>>,
+    /// Serializes appends to journals.
+    journal: Mutex<()>,
+}
+
+impl Broadcaster {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, BroadcastError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let opt_outs = match fs::read(dir.join(OPT_OUTS_FILE)) {
+            Ok(contents) => serde_json::from_slice(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            dir,
+            opt_outs: Mutex::new(opt_outs),
+            journal: Mutex::new(()),
+        })
+    }
+
+    pub fn is_opted_out(&self, contact_id: u64) -> bool {
+        self.opt_outs.lock().unwrap().contains(&contact_id)
+    }
+
+    pub fn set_opted_out(&self, contact_id: u64, opted_out: bool) -> Result<(), BroadcastError> {
+        let mut opt_outs = self.opt_outs.lock().unwrap();
+        let mut updated = opt_outs.clone();
+        let changed = if opted_out {
+            updated.insert(contact_id)
+        } else {
+            updated.remove(&contact_id)
+        };
+
+        if changed {
+            let mut sorted: Vec<_> = updated.iter().collect();
+            sorted.sort();
+            write_atomically(&self.dir.join(OPT_OUTS_FILE), &serde_json::to_vec(&sorted)?)?;
+            *opt_outs = updated;
+        }
+        Ok(())
+    }
+
+    /// Looks for `/stop` and `/start` in messages received from contacts, and opts them out of
+    /// or back into broadcasts. Returns the contacts whose choice changed, so the bot can
+    /// confirm it to them.
+    pub fn observe(&self, response: &ChatResponse) -> Result<Vec<OptOutChange>, BroadcastError> {
+        let ChatResponse::NewChatItems { chat_items, .. } = response else {
+            return Ok(vec![]);
+        };
+
+        let mut changes = Vec::new();
+        for item in chat_items {
+            let ChatInfo::Direct(direct) = &item.chat_info else {
+                continue;
+            };
+            if !matches!(
+                item.chat_item.chat_dir.direction_type,
+                DirectionType::DirectRcv
+            ) {
+                continue;
+            }
+
+            let opted_out = match extract_text_content(item.chat_item.content.clone())
+                .map(|text| text.trim().to_lowercase())
+                .as_deref()
+            {
+                Some("/stop") => true,
+                Some("/start") => false,
+                _ => continue,
+            };
+
+            let contact_id = direct.contact.contact_id;
+            if self.is_opted_out(contact_id) != opted_out {
+                self.set_opted_out(contact_id, opted_out)?;
+                changes.push(OptOutChange {
+                    contact_id,
+                    opted_out,
+                });
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Records a new broadcast. Nothing is sent until it is passed to [`Broadcaster::run`].
+    pub fn start(
+        &self,
+        message: ComposedMessage,
+        recipients: Vec<u64>,
+    ) -> Result<BroadcastJob, BroadcastError> {
+        let created_at = Utc::now();
+        let mut id = created_at.format("%Y%m%dT%H%M%S%3f").to_string();
+        while self.journal_path(&id).exists() {
+            id.push('_');
+        }
+
+        let mut seen = HashSet::new();
+        let recipients: Vec<u64> = recipients
+            .into_iter()
+            .filter(|contact_id| seen.insert(*contact_id))
+            .collect();
+
+        self.append(
+            &id,
+            &JournalEntry::Started {
+                id: id.clone(),
+                message: Box::new(message.clone()),
+                recipients: recipients.clone(),
+                created_at,
+            },
+        )?;
+
+        Ok(BroadcastJob {
+            id,
+            message,
+            recipients,
+            results: HashMap::new(),
+            created_at,
+            finished: false,
+        })
+    }
+
+    pub fn load(&self, id: &str) -> Result<BroadcastJob, BroadcastError> {
+        let file = match fs::File::open(self.journal_path(id)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(BroadcastError::NotFound(id.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut job: Option<BroadcastJob> = None;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            // A crash can leave the last line half written.
+            let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) else {
+                continue;
+            };
+
+            match (entry, job.as_mut()) {
+                (
+                    JournalEntry::Started {
+                        id,
+                        message,
+                        recipients,
+                        created_at,
+                    },
+                    None,
+                ) => {
+                    job = Some(BroadcastJob {
+                        id,
+                        message: *message,
+                        recipients,
+                        results: HashMap::new(),
+                        created_at,
+                        finished: false,
+                    })
+                }
+                (JournalEntry::Result { contact_id, result }, Some(job)) => {
+                    job.results.insert(contact_id, result);
+                }
+                (JournalEntry::Finished, Some(job)) => job.finished = true,
+                _ => {}
+            }
+        }
+
+        job.ok_or_else(|| BroadcastError::InvalidFormat(format!("journal {} has no start", id)))
+    }
+
+    /// Broadcasts that were started but not finished. A journal that cannot be read is
+    /// reported and skipped, so it does not hold up the others.
+    pub fn unfinished(&self) -> Result<Unfinished, BroadcastError> {
+        let mut unfinished = Unfinished::default();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(JOURNAL_EXTENSION) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            match self.load(id) {
+                Ok(job) if !job.finished => unfinished.jobs.push(job),
+                Ok(_) => {}
+                Err(e) => unfinished.corrupt.push((id.to_string(), e)),
+            }
+        }
+
+        unfinished.jobs.sort_by_key(|job| job.created_at);
+        unfinished.corrupt.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(unfinished)
+    }
+
+    /// Sends the broadcast to every recipient without a result, skipping contacts who opted
+    /// out. Resuming a broadcast is running it again.
+    ///
+    /// Sends simplex-chat rejects are recorded as failed and not retried. If simplex-chat cannot
+    /// be reached, the run stops with [`BroadcastError::Transport`] and leaves the recipients
+    /// not messaged yet pending. A send that was made but not yet recorded when the process died
+    /// is made again on resumption.
+    pub async fn run(
+        &self,
+        client: &ChatClient,
+        job: &mut BroadcastJob,
+        config: &BroadcastConfig,
+    ) -> Result<BroadcastReport, BroadcastError> {
+        let limiter = tokio::sync::Mutex::new({
+            let mut ticks = interval(config.min_interval.max(Duration::from_millis(1)));
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticks
+        });
+        let results = Mutex::new(Vec::new());
+        let error = Mutex::new(None);
+        let message = &job.message;
+        let id = &job.id;
+
+        stream::iter(job.pending())
+            .for_each_concurrent(config.concurrency.max(1), |contact_id| {
+                let (limiter, results, error) = (&limiter, &results, &error);
+                async move {
+                    let result = if self.is_opted_out(contact_id) {
+                        RecipientResult::OptedOut
+                    } else {
+                        limiter.lock().await.tick().await;
+                        // Once simplex-chat is unreachable, leave the rest for a later run.
+                        if error.lock().unwrap().is_some() {
+                            return;
+                        }
+                        match client
+                            .send_messages(
+                                ChatInfoType::Direct,
+                                contact_id,
+                                std::slice::from_ref(message),
+                            )
+                            .await
+                        {
+                            Ok(items) => match items.first() {
+                                Some(item) => RecipientResult::Sent {
+                                    item_id: item.chat_item.meta.item_id,
+                                },
+                                None => RecipientResult::Failed {
+                                    error: "no chat item was created".to_string(),
+                                },
+                            },
+                            Err(CommandError::Transport(e)) => {
+                                error.lock().unwrap().get_or_insert(e.into());
+                                return;
+                            }
+                            Err(e) => RecipientResult::Failed {
+                                error: e.to_string(),
+                            },
+                        }
+                    };
+
+                    let entry = JournalEntry::Result {
+                        contact_id,
+                        result: result.clone(),
+                    };
+                    if let Err(e) = self.append(id, &entry) {
+                        error.lock().unwrap().get_or_insert(e);
+                    }
+                    results.lock().unwrap().push((contact_id, result));
+                }
+            })
+            .await;
+
+        job.results.extend(results.into_inner().unwrap());
+        if let Some(e) = error.into_inner().unwrap() {
+            return Err(e);
+        }
+
+        if job.pending().is_empty() && !job.finished {
+            self.append(&job.id, &JournalEntry::Finished)?;
+            job.finished = true;
+        }
+
+        Ok(job.report())
+    }
+
+    fn journal_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, JOURNAL_EXTENSION))
+    }
+
+    fn append(&self, id: &str, entry: &JournalEntry) -> Result<(), BroadcastError> {
+        let _guard = self.journal.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(self.journal_path(id))?;
+
+        // A crash can leave the last line half written; start a new one after it.
+        let mut line = Vec::new();
+        if file.metadata()?.len() > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                line.push(b'\n');
+            }
+        }
+
+        serde_json::to_writer(&mut line, entry)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::{
+        fixtures::{contact, direct_item, mock_server, response, user},
+        response::{MCText, MsgContent},
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("muchat-broadcast-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn text(text: &str) -> ComposedMessage {
+        ComposedMessage {
+            file_path: None,
+            quoted_item_id: None,
+            msg_content: MsgContent::Text(MCText {
+                text: text.to_string(),
+            }),
+        }
+    }
+
+    async fn client(handler: impl Fn(&str) -> Value + Send + 'static) -> ChatClient {
+        let (client, stream) = ChatClient::new(mock_server(handler).await).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+        client
+    }
+
+    /// Answers sends with an item whose ID is the contact ID plus 100, except for contact 3.
+    fn sends(log: Arc<Mutex<Vec<u64>>>) -> impl Fn(&str) -> Value + Send + 'static {
+        move |cmd: &str| {
+            let contact_id: u64 = cmd
+                .strip_prefix("/_send @")
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|id| id.parse().ok())
+                .unwrap();
+            log.lock().unwrap().push(contact_id);
+
+            if contact_id == 3 {
+                return json!({ "type": "chatCmdError", "chatError": { "type": "error" } });
+            }
+            json!({
+                "type": "newChatItems",
+                "user": user(),
+                "chatItems": [direct_item(contact_id, contact_id + 100, "directSnd", "hi", json!({ "type": "sndNew" }))]
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_tag() {
+        let client = client(|cmd| match cmd {
+            "/u" => json!({ "type": "activeUser", "user": user() }),
+            "/_get tags 1" => json!({
+                "type": "chatTags",
+                "user": user(),
+                "userTags": [
+                    { "chatTagId": 1, "chatTagText": "customers" },
+                    { "chatTagId": 2, "chatTagText": "family", "chatTagEmoji": "🏠" }
+                ]
+            }),
+            _ => {
+                let mut alice = contact(2, "alice");
+                alice["chatTags"] = json!([1]);
+                let mut bob = contact(3, "bob");
+                bob["chatTags"] = json!([2]);
+                let mut carol = contact(4, "carol");
+                carol["chatTags"] = json!([1, 2]);
+                carol["chatDeleted"] = json!(true);
+                json!({ "type": "contactsList", "user": user(), "contacts": [alice, bob, carol] })
+            }
+        })
+        .await;
+
+        let audience = Audience::Tag {
+            tag: "customers".to_string(),
+        };
+        assert_eq!(audience.resolve(&client).await.unwrap(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_with_opt_outs() {
+        let dir = temp_dir("opt-outs");
+        let broadcaster = Broadcaster::open(&dir).unwrap();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let client = client(sends(Arc::clone(&sent))).await;
+
+        let stop = response(json!({
+            "type": "newChatItems",
+            "user": user(),
+            "chatItems": [direct_item(4, 1, "directRcv", " /STOP ", json!({ "type": "rcvNew" }))]
+        }));
+        assert_eq!(
+            broadcaster.observe(&stop).unwrap(),
+            vec![OptOutChange {
+                contact_id: 4,
+                opted_out: true
+            }]
+        );
+        assert!(broadcaster.observe(&stop).unwrap().is_empty());
+
+        let mut job = broadcaster
+            .start(text("sale!"), vec![2, 3, 4, 2, 5])
+            .unwrap();
+        let config = BroadcastConfig {
+            concurrency: 2,
+            min_interval: Duration::from_millis(1),
+        };
+        let report = broadcaster.run(&client, &mut job, &config).await.unwrap();
+
+        assert_eq!(
+            report,
+            BroadcastReport {
+                sent: 2,
+                failed: 1,
+                opted_out: 1,
+                pending: 0
+            }
+        );
+        assert_eq!(job.results[&5], RecipientResult::Sent { item_id: 105 });
+        assert!(matches!(job.results[&3], RecipientResult::Failed { .. }));
+        let mut sent = sent.lock().unwrap().clone();
+        sent.sort();
+        assert_eq!(sent, vec![2, 3, 5]);
+
+        // Opting out is remembered across restarts.
+        assert!(Broadcaster::open(&dir).unwrap().is_opted_out(4));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_crash() {
+        let dir = temp_dir("resume");
+        let broadcaster = Broadcaster::open(&dir).unwrap();
+        let job = broadcaster.start(text("news"), vec![2, 5, 6]).unwrap();
+
+        // The process died after messaging contact 2, halfway through writing the next result.
+        broadcaster
+            .append(
+                &job.id,
+                &JournalEntry::Result {
+                    contact_id: 2,
+                    result: RecipientResult::Sent { item_id: 102 },
+                },
+            )
+            .unwrap();
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(broadcaster.journal_path(&job.id))
+            .unwrap();
+        journal
+            .write_all(br#"{"type":"result","contactId":5,"#)
+            .unwrap();
+
+        let restarted = Broadcaster::open(&dir).unwrap();
+        let mut unfinished = restarted.unfinished().unwrap();
+        assert!(unfinished.corrupt.is_empty());
+        assert_eq!(unfinished.jobs.len(), 1);
+        let mut job = unfinished.jobs.remove(0);
+        assert_eq!(job.pending(), vec![5, 6]);
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let client = client(sends(Arc::clone(&sent))).await;
+        let report = restarted
+            .run(&client, &mut job, &BroadcastConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(report.sent, 3);
+        assert!(restarted.unfinished().unwrap().jobs.is_empty());
+
+        // The results written after the torn line were kept, so running the broadcast again
+        // messages nobody a second time.
+        let mut reloaded = Broadcaster::open(&dir).unwrap().load(&job.id).unwrap();
+        assert!(reloaded.finished);
+        assert_eq!(reloaded.results, job.results);
+        restarted
+            .run(&client, &mut reloaded, &BroadcastConfig::default())
+            .await
+            .unwrap();
+        let mut sent = sent.lock().unwrap().clone();
+        sent.sort();
+        assert_eq!(sent, vec![5, 6]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stop_when_disconnected() {
+        let dir = temp_dir("disconnected");
+        let broadcaster = Broadcaster::open(&dir).unwrap();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let send = sends(Arc::clone(&sent));
+        // simplex-chat goes away while messaging contact 5.
+        let client = client(move |cmd| {
+            if cmd.starts_with("/_send @5 ") {
+                Value::Null
+            } else {
+                send(cmd)
+            }
+        })
+        .await;
+
+        let mut job = broadcaster.start(text("news"), vec![2, 5, 6]).unwrap();
+        let config = BroadcastConfig {
+            concurrency: 1,
+            min_interval: Duration::from_millis(1),
+        };
+        let result = broadcaster.run(&client, &mut job, &config).await;
+
+        assert!(matches!(result, Err(BroadcastError::Transport(_))));
+        assert_eq!(job.results[&2], RecipientResult::Sent { item_id: 102 });
+        assert_eq!(job.pending(), vec![5, 6]);
+        let journal = broadcaster.load(&job.id).unwrap();
+        assert!(!journal.finished);
+        assert_eq!(journal.pending(), vec![5, 6]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unfinished_skips_corrupt_journals() {
+        let dir = temp_dir("corrupt");
+        let broadcaster = Broadcaster::open(&dir).unwrap();
+        let job = broadcaster.start(text("news"), vec![2]).unwrap();
+        fs::write(dir.join("broken.jsonl"), "not json\n").unwrap();
+
+        let unfinished = broadcaster.unfinished().unwrap();
+        assert_eq!(unfinished.jobs.len(), 1);
+        assert_eq!(unfinished.jobs[0].id, job.id);
+        assert_eq!(unfinished.corrupt.len(), 1);
+        assert_eq!(unfinished.corrupt[0].0, "broken");
+        assert!(matches!(
+            unfinished.corrupt[0].1,
+            BroadcastError::InvalidFormat(_)
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     sync::{
         atomic::{AtomicU16, Ordering},
         Arc,
@@ -11,25 +12,64 @@ use futures::{
     stream::{SplitSink, SplitStream},
     Future, SinkExt, Stream, StreamExt,
 };
+use serde::Deserialize;
 use tokio::{
     net::TcpStream,
     sync::{
         mpsc::{self, Receiver, Sender},
-        Mutex,
+        oneshot, Mutex,
     },
 };
 use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
 use super::{
-    commands::{CommandData, CommandPayload, ComposedMessage},
-    error::TransportError,
-    response::{ChatInfoType, MCText, MsgContent, ServerResponse},
+    commands::{ChatCommand, CommandPayload, ComposedMessage},
+    error::{CommandError, TransportError},
+    link::ConnectionLink,
+    network::NetworkConfig,
+    response::{
+        AChatItem, AllowPreference, Chat, ChatInfo, ChatInfoType, ChatItemId, ChatResponse,
+        ChatSettings, ChatTag, Contact, Group, GroupFeatureEnabled, GroupInfo, GroupInfoSummary,
+        GroupPreference, GroupProfile, MCText, MsgContent, MsgReaction, PendingContactConnection,
+        Preferences, Profile, ServerResponse, User, Verification,
+    },
+    servers::{ProtocolTestFailure, ServerAddress, ServerCfg, ServerProtocol, UserProtoServers},
 };
 
+/// The result of [`ChatClient::connect`].
+#[derive(Clone, Debug)]
+pub enum Connecting {
+    /// The request was sent; the contact appears once the other side accepts it.
+    Pending(PendingContactConnection),
+    AlreadyConnected(Box<Contact>),
+}
+
+/// A one-time invitation created with [`ChatClient::create_invitation`].
+#[derive(Clone, Debug)]
+pub struct Invitation {
+    /// The link to give to the other side, parseable as a [`ConnectionLink`].
+    pub link: String,
+    pub connection: PendingContactConnection,
+}
+
+/// How long [`ChatClient::request`] waits for the response to a command.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+type PendingRequests = Arc<std::sync::Mutex<HashMap<String, oneshot::Sender<serde_json::Value>>>>;
+
+/// A message from simplex-chat, before its response is typed.
+#[derive(Deserialize)]
+struct RawServerResponse {
+    #[serde(rename = "corrId")]
+    corr_id: Option<String>,
+    resp: serde_json::Value,
+}
+
 pub struct ChatClient {
     command_sender: Sender<CommandPayload>,
     command_reader: Arc<Mutex<Receiver<CommandPayload>>>,
     corr_id: Arc<AtomicU16>,
+    pending: PendingRequests,
 }
 
 pub type StreamMessage = Result<ServerResponse, TransportError>;
@@ -49,6 +89,7 @@ impl ChatClient {
             command_sender,
             command_reader: Arc::new(Mutex::new(command_reader)),
             corr_id: Arc::new(AtomicU16::new(0)),
+            pending: Arc::default(),
         };
 
         let ws_stream = tokio::time::timeout(Duration::from_secs(15), Self::create_connection(&url))
@@ -61,7 +102,7 @@ impl ChatClient {
             write,
         ));
 
-        let stream = Self::read_server_messages(read);
+        let stream = Self::read_server_messages(read, Arc::clone(&client.pending));
         Ok((client, stream))
     }
 
@@ -101,22 +142,38 @@ impl ChatClient {
 
     async fn read_server_messages(
         mut read: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+        pending: PendingRequests,
     ) -> impl Stream<Item = StreamMessage> {
         try_stream! {
-            loop {
-              if let Some(Ok(message)) = read.next().await {
-                if let Ok(response) = Self::handle_server_message(message).await {
-                  yield response;
+            while let Some(message) = read.next().await {
+              if let Ok(message) = message {
+                if let Ok(raw) = Self::handle_server_message(message).await {
+                  Self::resolve_request(&pending, &raw);
+                  if let Ok(resp) = serde_json::from_value(raw.resp) {
+                    yield ServerResponse { corr_id: raw.corr_id, resp };
+                  }
                 }
               }
             }
+            // Nothing answers requests once the connection is gone.
+            pending.lock().unwrap().clear();
+        }
+    }
+
+    fn resolve_request(pending: &PendingRequests, response: &RawServerResponse) {
+        let Some(corr_id) = &response.corr_id else {
+            return;
+        };
+
+        if let Some(waiter) = pending.lock().unwrap().remove(corr_id) {
+            let _ = waiter.send(response.resp.clone());
         }
     }
 
-    async fn handle_server_message(msg: Message) -> Result<ServerResponse, TransportError> {
+    async fn handle_server_message(msg: Message) -> Result<RawServerResponse, TransportError> {
         match msg {
             Message::Text(text) => {
-                let response: ServerResponse = serde_json::from_str(&text)
+                let response: RawServerResponse = serde_json::from_str(&text)
                     .map_err(|e| TransportError::InvalidFormat(e.to_string()))?;
                 Ok(response)
             }
@@ -148,6 +205,57 @@ impl ChatClient {
         Ok(())
     }
 
+    /// Sends a command and waits for its response.
+    ///
+    /// Responses are matched while the message stream is read, so the stream returned by
+    /// [`ChatClient::new`] must be polled for this to return. The response is still yielded by
+    /// the stream as well.
+    pub async fn request(&self, command_text: String) -> Result<ChatResponse, TransportError> {
+        let response = self.request_json(command_text).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Like [`ChatClient::request`], but returns the response as simplex-chat sent it, with the
+    /// fields [`ChatResponse`] leaves out.
+    pub async fn request_json(
+        &self,
+        command_text: String,
+    ) -> Result<serde_json::Value, TransportError> {
+        let corr_id = self.corr_id.fetch_add(1, Ordering::SeqCst).to_string();
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(corr_id.clone(), sender);
+
+        if let Err(e) = self.send_command(command_text, Some(corr_id.clone())).await {
+            self.pending.lock().unwrap().remove(&corr_id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, receiver).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(TransportError::ConnectionClosed),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&corr_id);
+                Err(TransportError::Timeout)
+            }
+        }
+    }
+
+    /// Like [`ChatClient::request`], but turns a `chatCmdError` response into an error.
+    pub async fn execute(&self, command_text: String) -> Result<ChatResponse, CommandError> {
+        match self.request(command_text).await? {
+            ChatResponse::ChatCmdError { chat_error, .. } => Err(CommandError::Chat(chat_error)),
+            response => Ok(response),
+        }
+    }
+
+    /// Like [`ChatClient::execute`], for commands that only answer `cmdOk`.
+    pub(crate) async fn expect_ok(&self, command_text: String) -> Result<(), CommandError> {
+        match self.execute(command_text).await? {
+            ChatResponse::CmdOk { .. } => Ok(()),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
     pub async fn send_text(
         &self,
         chat_type: ChatInfoType,
@@ -160,18 +268,1269 @@ impl ChatClient {
             msg_content: MsgContent::Text(MCText { text }),
         };
 
-        let command_data = CommandData {
-            chat_type: chat_type.value().to_string(),
-            chat_id,
-            messages: vec![message],
+        self.send_command(
+            send_command_text(chat_type, chat_id, &[message], None)?,
+            None,
+        )
+        .await
+    }
+
+    /// Sends messages and waits for simplex-chat to create their chat items.
+    pub async fn send_messages(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        messages: &[ComposedMessage],
+    ) -> Result<Vec<AChatItem>, CommandError> {
+        let command = send_command_text(chat_type, chat_id, messages, None)?;
+
+        match self.execute(command).await? {
+            ChatResponse::NewChatItems { chat_items, .. } => Ok(chat_items),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Sends messages that disappear `ttl` after they are read, whatever the chat's timer is.
+    /// simplex-chat rejects them unless both sides allow timed messages.
+    pub async fn send_timed_messages(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        messages: &[ComposedMessage],
+        ttl: Duration,
+    ) -> Result<Vec<AChatItem>, CommandError> {
+        let command = send_command_text(chat_type, chat_id, messages, Some(ttl))?;
+
+        match self.execute(command).await? {
+            ChatResponse::NewChatItems { chat_items, .. } => Ok(chat_items),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Sets the timer of messages sent to a contact, or turns disappearing messages off with
+    /// `None`. The contact sees the change and has to allow timed messages too.
+    pub async fn set_contact_timer(
+        &self,
+        contact: &Contact,
+        ttl: Option<Duration>,
+    ) -> Result<Contact, CommandError> {
+        let allow = match ttl {
+            Some(_) => AllowPreference::Yes,
+            None => AllowPreference::No,
+        };
+        let preferences = contact
+            .user_preferences
+            .clone()
+            .with_timed_messages(allow, ttl);
+
+        self.set_contact_preferences(contact.contact_id, &preferences)
+            .await
+    }
+
+    /// Sets the timer of messages sent to a group by all of its members, or turns disappearing
+    /// messages off with `None`. Only the group's owners can change it.
+    pub async fn set_group_timer(
+        &self,
+        group: &GroupInfo,
+        ttl: Option<Duration>,
+    ) -> Result<GroupInfo, CommandError> {
+        let mut profile = group.group_profile.clone();
+        profile
+            .group_preferences
+            .get_or_insert_with(Default::default)
+            .timed_messages = Some(GroupPreference {
+            enable: match ttl {
+                Some(_) => GroupFeatureEnabled::On,
+                None => GroupFeatureEnabled::Off,
+            },
+            ttl: ttl.map(|ttl| ttl.as_secs()),
+        });
+
+        self.update_group_profile(group.group_id, &profile).await
+    }
+
+    /// Replaces a group's profile, which its members receive as an update. Only the group's
+    /// owners can change it.
+    pub async fn update_group_profile(
+        &self,
+        group_id: u64,
+        profile: &GroupProfile,
+    ) -> Result<GroupInfo, CommandError> {
+        match self
+            .execute(format!(
+                "/_group_profile #{} {}",
+                group_id,
+                serde_json::to_string(profile).map_err(TransportError::from)?
+            ))
+            .await?
+        {
+            ChatResponse::GroupUpdated { to_group, .. } => Ok(*to_group),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// How long the user's chat items are kept before simplex-chat deletes them, if it does.
+    pub async fn chat_item_ttl(&self, user_id: u64) -> Result<Option<Duration>, CommandError> {
+        match self.execute(format!("/_ttl {}", user_id)).await? {
+            ChatResponse::ChatItemTTL { chat_item_ttl, .. } => {
+                Ok(chat_item_ttl.map(Duration::from_secs))
+            }
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Deletes the user's chat items once they are `ttl` old, including the ones already older.
+    /// `None` keeps the history forever.
+    pub async fn set_chat_item_ttl(
+        &self,
+        user_id: u64,
+        ttl: Option<Duration>,
+    ) -> Result<(), CommandError> {
+        let ttl = match ttl {
+            Some(ttl) => ttl.as_secs().to_string(),
+            None => "none".to_string(),
         };
 
+        self.expect_ok(format!("/_ttl {} {}", user_id, ttl)).await
+    }
+
+    pub async fn send_reaction(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        item_id: ChatItemId,
+        add: bool,
+        reaction: MsgReaction,
+    ) -> Result<(), TransportError> {
         let command_string = format!(
-            "/_send @{} json {}",
-            command_data.chat_id,
-            serde_json::to_string(&command_data.messages).unwrap()
+            "/_reaction {}{} {} {} {}",
+            chat_type.value(),
+            chat_id,
+            item_id,
+            if add { "on" } else { "off" },
+            serde_json::to_string(&reaction)?
         );
 
         self.send_command(command_string, None).await
     }
 }
+
+impl ChatClient {
+    pub async fn active_user(&self) -> Result<User, CommandError> {
+        match self
+            .execute(ChatCommand::ShowActiveUser.value().to_string())
+            .await?
+        {
+            ChatResponse::ActiveUser { user } => Ok(user),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    pub async fn list_contacts(&self, user_id: u64) -> Result<Vec<Contact>, CommandError> {
+        match self.execute(format!("/_contacts {}", user_id)).await? {
+            ChatResponse::ContactsList { contacts, .. } => Ok(contacts),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    pub async fn list_groups(&self, user_id: u64) -> Result<Vec<GroupInfoSummary>, CommandError> {
+        match self.execute(format!("/_groups {}", user_id)).await? {
+            ChatResponse::GroupsList { groups, .. } => Ok(groups),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// A group's members, not including the user.
+    pub async fn list_members(&self, group_id: u64) -> Result<Group, CommandError> {
+        match self.execute(format!("/_members #{}", group_id)).await? {
+            ChatResponse::GroupMembers { group, .. } => Ok(group),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// The tags the user created to sort chats.
+    pub async fn list_chat_tags(&self, user_id: u64) -> Result<Vec<ChatTag>, CommandError> {
+        match self.execute(format!("/_get tags {}", user_id)).await? {
+            ChatResponse::ChatTags { user_tags, .. } => Ok(user_tags),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// The user's contact address, if they created one.
+    pub async fn show_address(&self) -> Result<Option<String>, CommandError> {
+        match self
+            .request(ChatCommand::ShowMyAddress.value().to_string())
+            .await?
+        {
+            ChatResponse::ChatCmdError { chat_error, .. }
+                if chat_error
+                    .store_error
+                    .as_ref()
+                    .is_some_and(|error| error.error_type == "userContactLinkNotFound") =>
+            {
+                Ok(None)
+            }
+            ChatResponse::ChatCmdError { chat_error, .. } => Err(CommandError::Chat(chat_error)),
+            other => match other.as_user_contact_link() {
+                Some(link) => Ok(Some(link)),
+                None => Err(CommandError::unexpected(other)),
+            },
+        }
+    }
+
+    pub async fn create_address(&self) -> Result<String, CommandError> {
+        let response = self
+            .execute(ChatCommand::CreateMyAddress.value().to_string())
+            .await?;
+
+        response
+            .as_create_address()
+            .ok_or_else(|| CommandError::unexpected(response))
+    }
+
+    /// The user's chats, each with its latest item.
+    pub async fn get_chats(&self, user_id: u64) -> Result<Vec<Chat>, CommandError> {
+        match self.execute(format!("/_get chats {}", user_id)).await? {
+            ChatResponse::ApiChats { chats, .. } => Ok(chats),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// The latest `count` items of a chat, or the `count` items before `before`.
+    pub async fn get_chat(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        before: Option<ChatItemId>,
+        count: usize,
+    ) -> Result<Chat, CommandError> {
+        let command = get_chat_command(chat_type, chat_id, before, count);
+
+        match self.execute(command).await? {
+            ChatResponse::ApiChat { chat, .. } => Ok(*chat),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Like [`ChatClient::get_chat`], but returns the chat as simplex-chat sent it, with the
+    /// fields [`Chat`] leaves out.
+    pub async fn get_chat_json(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        before: Option<ChatItemId>,
+        count: usize,
+    ) -> Result<serde_json::Value, CommandError> {
+        let command = get_chat_command(chat_type, chat_id, before, count);
+
+        let mut response = self.request_json(command).await?;
+        if response["type"] == "apiChat" {
+            return Ok(response["chat"].take());
+        }
+        match serde_json::from_value(response).map_err(TransportError::from)? {
+            ChatResponse::ChatCmdError { chat_error, .. } => Err(CommandError::Chat(chat_error)),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Replaces the content of a sent message.
+    pub async fn update_message(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        item_id: ChatItemId,
+        msg_content: &MsgContent,
+    ) -> Result<AChatItem, CommandError> {
+        let command = format!(
+            "/_update item {}{} {} json {}",
+            chat_type.value(),
+            chat_id,
+            item_id,
+            serde_json::to_string(msg_content).map_err(TransportError::from)?
+        );
+
+        match self.execute(command).await? {
+            ChatResponse::ChatItemUpdated { chat_item, .. }
+            | ChatResponse::ChatItemNotChanged { chat_item, .. } => Ok(*chat_item),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    pub async fn mark_chat_read(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+    ) -> Result<(), CommandError> {
+        self.expect_ok(format!("/_read chat {}{}", chat_type.value(), chat_id))
+            .await
+    }
+
+    /// Marks a chat as unread, or takes the mark away, without changing its items.
+    pub async fn mark_chat_unread(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        unread: bool,
+    ) -> Result<(), CommandError> {
+        self.expect_ok(format!(
+            "/_unread chat {}{} {}",
+            chat_type.value(),
+            chat_id,
+            on_off(unread)
+        ))
+        .await
+    }
+
+    /// Changes the notifications, receipts and favorite flag of a contact or group chat. Start
+    /// from the chat's current settings to change only some of them.
+    pub async fn set_chat_settings(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        settings: &ChatSettings,
+    ) -> Result<(), CommandError> {
+        self.expect_ok(format!(
+            "/_settings {}{} {}",
+            chat_type.value(),
+            chat_id,
+            serde_json::to_string(settings).map_err(TransportError::from)?
+        ))
+        .await
+    }
+
+    /// Deletes every item of a chat for the user, keeping the contact or group.
+    pub async fn clear_chat(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+    ) -> Result<ChatInfo, CommandError> {
+        match self
+            .execute(format!("/_clear chat {}{}", chat_type.value(), chat_id))
+            .await?
+        {
+            ChatResponse::ChatCleared { chat_info, .. } => Ok(chat_info),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Deletes a contact, or leaves and deletes a group, with its history. With `notify`, the
+    /// contact is told the user deleted them.
+    pub async fn delete_chat(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        notify: bool,
+    ) -> Result<(), CommandError> {
+        match self
+            .execute(format!(
+                "/_delete {}{} notify={}",
+                chat_type.value(),
+                chat_id,
+                on_off(notify)
+            ))
+            .await?
+        {
+            ChatResponse::ContactDeleted { .. } | ChatResponse::GroupDeletedUser { .. } => Ok(()),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Connects to a contact address, group link or invitation.
+    pub async fn connect(
+        &self,
+        user_id: u64,
+        link: &ConnectionLink,
+    ) -> Result<Connecting, CommandError> {
+        self.connect_with(user_id, link, false).await
+    }
+
+    /// Connects with a new random profile instead of the user's, that only this contact sees.
+    pub async fn connect_incognito(
+        &self,
+        user_id: u64,
+        link: &ConnectionLink,
+    ) -> Result<Connecting, CommandError> {
+        self.connect_with(user_id, link, true).await
+    }
+
+    async fn connect_with(
+        &self,
+        user_id: u64,
+        link: &ConnectionLink,
+        incognito: bool,
+    ) -> Result<Connecting, CommandError> {
+        match self
+            .execute(format!(
+                "/_connect {} incognito={} {}",
+                user_id,
+                on_off(incognito),
+                link.to_simplex_uri()
+            ))
+            .await?
+        {
+            ChatResponse::SentConfirmation { connection, .. }
+            | ChatResponse::SentInvitation { connection, .. } => Ok(Connecting::Pending(connection)),
+            ChatResponse::ContactAlreadyExists { contact, .. } => {
+                Ok(Connecting::AlreadyConnected(Box::new(contact)))
+            }
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Replaces the user's profile, which contacts receive as an update. Start from
+    /// [`LocalProfile::to_profile`](super::response::LocalProfile::to_profile) to change only
+    /// some fields. Returns false if nothing changed.
+    pub async fn update_profile(
+        &self,
+        user_id: u64,
+        profile: &Profile,
+    ) -> Result<bool, CommandError> {
+        match self
+            .execute(format!(
+                "/_profile {} {}",
+                user_id,
+                serde_json::to_string(profile).map_err(TransportError::from)?
+            ))
+            .await?
+        {
+            ChatResponse::UserProfileUpdated { .. } => Ok(true),
+            ChatResponse::UserProfileNoChange { .. } => Ok(false),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Overrides the profile's preferences for one contact, returning the updated contact.
+    pub async fn set_contact_preferences(
+        &self,
+        contact_id: u64,
+        preferences: &Preferences,
+    ) -> Result<Contact, CommandError> {
+        match self
+            .execute(format!(
+                "/_set prefs @{} {}",
+                contact_id,
+                serde_json::to_string(preferences).map_err(TransportError::from)?
+            ))
+            .await?
+        {
+            ChatResponse::ContactPrefsUpdated { to_contact, .. } => Ok(to_contact),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Sets the name the user sees for a contact instead of theirs. An empty alias removes it.
+    pub async fn set_contact_alias(
+        &self,
+        contact_id: u64,
+        alias: &str,
+    ) -> Result<Contact, CommandError> {
+        match self
+            .execute(format!("/_set alias @{} {}", contact_id, alias.trim()))
+            .await?
+        {
+            ChatResponse::ContactAliasUpdated { to_contact, .. } => Ok(to_contact),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Creates a one-time invitation link. With `incognito`, whoever uses it sees a random profile
+    /// instead of the user's.
+    pub async fn create_invitation(
+        &self,
+        user_id: u64,
+        incognito: bool,
+    ) -> Result<Invitation, CommandError> {
+        match self
+            .execute(format!(
+                "/_connect {} incognito={}",
+                user_id,
+                on_off(incognito)
+            ))
+            .await?
+        {
+            ChatResponse::Invitation {
+                conn_req_invitation,
+                connection,
+                ..
+            } => Ok(Invitation {
+                link: conn_req_invitation,
+                connection,
+            }),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Switches an invitation nobody used yet to or from a random profile.
+    pub async fn set_connection_incognito(
+        &self,
+        pcc_conn_id: u64,
+        incognito: bool,
+    ) -> Result<PendingContactConnection, CommandError> {
+        match self
+            .execute(format!(
+                "/_set incognito :{} {}",
+                pcc_conn_id,
+                on_off(incognito)
+            ))
+            .await?
+        {
+            ChatResponse::ConnectionIncognitoUpdated { to_connection, .. } => Ok(to_connection),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// The profile a contact sees: the user's own, or the random one of an incognito connection.
+    /// Bots that mention their name should use this one, so they do not give the user away.
+    pub async fn profile_seen_by(
+        &self,
+        user: &User,
+        contact: &Contact,
+    ) -> Result<Profile, CommandError> {
+        if !contact.is_incognito() {
+            return Ok(user.profile.to_profile());
+        }
+
+        match self
+            .execute(format!("/_info @{}", contact.contact_id))
+            .await?
+        {
+            ChatResponse::ContactInfo {
+                custom_user_profile,
+                ..
+            } => Ok(custom_user_profile.unwrap_or_else(|| user.profile.to_profile())),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// The security code of the connection with a contact, to compare with theirs.
+    pub async fn contact_code(&self, contact_id: u64) -> Result<String, CommandError> {
+        match self.execute(format!("/_get code @{}", contact_id)).await? {
+            ChatResponse::ContactCode {
+                connection_code, ..
+            } => Ok(connection_code),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Marks a contact as verified if `code` matches the connection's security code. Passing
+    /// `None` clears the verification.
+    pub async fn verify_contact(
+        &self,
+        contact_id: u64,
+        code: Option<&str>,
+    ) -> Result<Verification, CommandError> {
+        let command = match code {
+            Some(code) => format!("/_verify code @{} {}", contact_id, code),
+            None => format!("/_verify code @{}", contact_id),
+        };
+
+        self.verify(command).await
+    }
+
+    /// The security code of the connection with a group member, to compare with theirs.
+    pub async fn member_code(
+        &self,
+        group_id: u64,
+        group_member_id: u64,
+    ) -> Result<String, CommandError> {
+        match self
+            .execute(format!("/_get code #{} {}", group_id, group_member_id))
+            .await?
+        {
+            ChatResponse::GroupMemberCode {
+                connection_code, ..
+            } => Ok(connection_code),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Marks a group member as verified if `code` matches the connection's security code.
+    /// Passing `None` clears the verification.
+    pub async fn verify_member(
+        &self,
+        group_id: u64,
+        group_member_id: u64,
+        code: Option<&str>,
+    ) -> Result<Verification, CommandError> {
+        let command = match code {
+            Some(code) => format!("/_verify code #{} {} {}", group_id, group_member_id, code),
+            None => format!("/_verify code #{} {}", group_id, group_member_id),
+        };
+
+        self.verify(command).await
+    }
+
+    async fn verify(&self, command: String) -> Result<Verification, CommandError> {
+        match self.execute(command).await? {
+            ChatResponse::ConnectionVerified {
+                verified,
+                expected_code,
+                ..
+            } => Ok(Verification {
+                verified,
+                expected_code,
+            }),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Accepts a contact request, returning the new contact.
+    pub async fn accept_contact(&self, contact_request_id: u64) -> Result<Contact, CommandError> {
+        self.accept_contact_with(contact_request_id, false).await
+    }
+
+    /// Accepts a contact request with a new random profile instead of the user's.
+    pub async fn accept_contact_incognito(
+        &self,
+        contact_request_id: u64,
+    ) -> Result<Contact, CommandError> {
+        self.accept_contact_with(contact_request_id, true).await
+    }
+
+    async fn accept_contact_with(
+        &self,
+        contact_request_id: u64,
+        incognito: bool,
+    ) -> Result<Contact, CommandError> {
+        match self
+            .execute(format!(
+                "/_accept incognito={} {}",
+                on_off(incognito),
+                contact_request_id
+            ))
+            .await?
+        {
+            ChatResponse::AcceptingContactRequest { contact, .. } => Ok(contact),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Rejects a contact request. The requester is not told.
+    pub async fn reject_contact(&self, contact_request_id: u64) -> Result<(), CommandError> {
+        match self
+            .execute(format!("/_reject {}", contact_request_id))
+            .await?
+        {
+            ChatResponse::ContactRequestRejected { .. } => Ok(()),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    pub async fn network_config(&self) -> Result<NetworkConfig, CommandError> {
+        match self.execute("/network".to_string()).await? {
+            ChatResponse::NetworkConfig { network_config } => Ok(network_config),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Replaces the network settings. Existing connections to relays are reopened with them.
+    pub async fn set_network_config(&self, config: &NetworkConfig) -> Result<(), CommandError> {
+        self.expect_ok(format!(
+            "/_network {}",
+            serde_json::to_string(config).map_err(TransportError::from)?
+        ))
+        .await
+    }
+
+    /// The user's SMP or XFTP servers, with the presets simplex-chat ships with.
+    pub async fn get_servers(
+        &self,
+        user_id: u64,
+        protocol: ServerProtocol,
+    ) -> Result<UserProtoServers, CommandError> {
+        match self
+            .execute(format!("/_servers {} {}", user_id, protocol.value()))
+            .await?
+        {
+            ChatResponse::UserProtoServers { servers, .. } => Ok(servers),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+
+    /// Replaces the user's SMP or XFTP servers. New connections use the enabled ones, or the
+    /// presets if none are enabled.
+    pub async fn set_servers(
+        &self,
+        user_id: u64,
+        protocol: ServerProtocol,
+        servers: &[ServerCfg],
+    ) -> Result<(), CommandError> {
+        let servers = serde_json::json!({ "servers": servers });
+        self.expect_ok(format!(
+            "/_servers {} {} {}",
+            user_id,
+            protocol.value(),
+            servers
+        ))
+        .await
+    }
+
+    /// Connects to a server as the user would, returning why it failed if it did.
+    pub async fn test_server(
+        &self,
+        user_id: u64,
+        server: &ServerAddress,
+    ) -> Result<Option<ProtocolTestFailure>, CommandError> {
+        match self
+            .execute(format!("/_server test {} {}", user_id, server))
+            .await?
+        {
+            ChatResponse::ServerTestResult { test_failure, .. } => Ok(test_failure),
+            other => Err(CommandError::unexpected(other)),
+        }
+    }
+}
+
+pub(crate) fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn get_chat_command(
+    chat_type: ChatInfoType,
+    chat_id: u64,
+    before: Option<ChatItemId>,
+    count: usize,
+) -> String {
+    let pagination = match before {
+        Some(item_id) => format!("before={} count={}", item_id, count),
+        None => format!("count={}", count),
+    };
+    format!("/_get chat {}{} {}", chat_type.value(), chat_id, pagination)
+}
+
+fn send_command_text(
+    chat_type: ChatInfoType,
+    chat_id: u64,
+    messages: &[ComposedMessage],
+    ttl: Option<Duration>,
+) -> Result<String, TransportError> {
+    let ttl = match ttl {
+        Some(ttl) => format!(" ttl={}", ttl.as_secs()),
+        None => String::new(),
+    };
+
+    Ok(format!(
+        "/_send {}{}{} json {}",
+        chat_type.value(),
+        chat_id,
+        ttl,
+        serde_json::to_string(messages)?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::chat::fixtures::{contact, direct_item, group_info, mock_server, user};
+    use crate::chat::response::{NotificationMode, Preference};
+
+    #[tokio::test]
+    async fn test_request_matches_response() {
+        let url = mock_server(|cmd| match cmd {
+            "/u" => json!({ "type": "activeUser", "user": user() }),
+            _ => json!({ "type": "apiParsedMarkdown", "formattedText": null }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        let stream = Box::pin(stream.await);
+        let responses = tokio::spawn(stream.take(2).collect::<Vec<_>>());
+
+        let other = client.request("/_parse hi".to_string()).await.unwrap();
+        assert!(matches!(other, ChatResponse::ApiParsedMarkdown { .. }));
+
+        let user = client.request("/u".to_string()).await.unwrap();
+        assert!(matches!(user, ChatResponse::ActiveUser { .. }));
+
+        // Responses to requests still reach the stream.
+        assert_eq!(responses.await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_typed_commands() {
+        let url = mock_server(|cmd| match cmd {
+            "/_contacts 1" => json!({
+                "type": "contactsList",
+                "user": user(),
+                "contacts": [contact(2, "alice")]
+            }),
+            // Older versions send groups as pairs.
+            "/_groups 1" => json!({
+                "type": "groupsList",
+                "user": user(),
+                "groups": [[group_info(7, "team"), { "currentMembers": 3 }]]
+            }),
+            "/show_address" => json!({
+                "type": "chatCmdError",
+                "chatError": {
+                    "type": "errorStore",
+                    "storeError": { "type": "userContactLinkNotFound" }
+                }
+            }),
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let contacts = client.list_contacts(1).await.unwrap();
+        assert_eq!(contacts[0].local_display_name, "alice");
+
+        let groups = client.list_groups(1).await.unwrap();
+        assert_eq!(groups[0].group_info.group_id, 7);
+        assert_eq!(groups[0].group_summary.current_members, 3);
+
+        assert_eq!(client.show_address().await.unwrap(), None);
+        assert!(matches!(
+            client.accept_contact(5).await,
+            Err(CommandError::Chat(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        let url = mock_server(|cmd| match cmd {
+            "/_connect 1 incognito=off simplex:/contact#/?v=2-7&smp=smp%3A%2F%2Fhash%40smp4.simplex.im%2Fq%23%2F%3Fv%3D1-3%26dh%3DMCowBQYDK2VuAyEA%253D" => json!({
+                "type": "sentInvitation",
+                "user": user(),
+                "connection": {
+                    "pccConnId": 9,
+                    "viaContactUri": true,
+                    "createdAt": "2024-12-21T10:00:00Z"
+                }
+            }),
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        // The padding of the key is encoded twice, and must reach simplex-chat that way.
+        let link: ConnectionLink = "https://simplex.chat/contact#/?v=2-7&smp=smp%3A%2F%2Fhash%40smp4.simplex.im%2Fq%23%2F%3Fv%3D1-3%26dh%3DMCowBQYDK2VuAyEA%253D"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            client.connect(1, &link).await.unwrap(),
+            Connecting::Pending(PendingContactConnection { pcc_conn_id: 9, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_verification() {
+        let url = mock_server(|cmd| match cmd {
+            "/_get code @2" => json!({
+                "type": "contactCode",
+                "user": user(),
+                "contact": contact(2, "alice"),
+                "connectionCode": "12345 67890"
+            }),
+            _ => json!({
+                "type": "connectionVerified",
+                "user": user(),
+                "verified": cmd == "/_verify code @2 12345 67890",
+                "expectedCode": "12345 67890"
+            }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        assert_eq!(client.contact_code(2).await.unwrap(), "12345 67890");
+        assert!(
+            client
+                .verify_contact(2, Some("12345 67890"))
+                .await
+                .unwrap()
+                .verified
+        );
+        assert_eq!(
+            client.verify_member(7, 3, Some("00000")).await.unwrap(),
+            Verification {
+                verified: false,
+                expected_code: "12345 67890".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_servers() {
+        const PRESET: &str = "smp://u2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU=@smp8.simplex.im";
+        const OURS: &str = "smp://u2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU=@relay.example.com";
+
+        let url = mock_server(|cmd| match cmd {
+            "/_servers 1 smp" => json!({
+                "type": "userProtoServers",
+                "user": user(),
+                "servers": {
+                    "serverProtocol": "smp",
+                    "protoServers": [{ "server": PRESET, "preset": true, "tested": true, "enabled": true }],
+                    "presetServers": [PRESET]
+                }
+            }),
+            r#"/_servers 1 smp {"servers":[{"enabled":false,"preset":true,"server":"smp://u2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU=@smp8.simplex.im","tested":true},{"enabled":true,"preset":false,"server":"smp://u2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU=@relay.example.com"}]}"# => {
+                json!({ "type": "cmdOk", "user": user() })
+            }
+            "/_server test 1 smp://u2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU=@relay.example.com" => json!({
+                "type": "serverTestResult",
+                "user": user(),
+                "testServer": OURS,
+                "testFailure": {
+                    "testStep": "connect",
+                    "testError": { "type": "BROKER", "brokerErr": { "type": "TIMEOUT" } }
+                }
+            }),
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let servers = client.get_servers(1, ServerProtocol::Smp).await.unwrap();
+        assert_eq!(
+            servers.proto_servers[0].server.hosts,
+            vec!["smp8.simplex.im"]
+        );
+
+        let ours: ServerAddress = OURS.parse().unwrap();
+        client
+            .set_servers(
+                1,
+                ServerProtocol::Smp,
+                &servers.pinned_to(vec![ours.clone()]),
+            )
+            .await
+            .unwrap();
+
+        let failure = client.test_server(1, &ours).await.unwrap().unwrap();
+        assert_eq!(failure.test_step, "connect");
+    }
+
+    #[tokio::test]
+    async fn test_network_config() {
+        let url = mock_server(|cmd| match cmd {
+            "/network" => json!({
+                "type": "networkConfig",
+                "networkConfig": {
+                    "socksMode": "always",
+                    "hostMode": "onionViaSocks",
+                    "requiredHostMode": false,
+                    "sessionMode": "user",
+                    "tcpConnectTimeout": 25000000,
+                    "tcpTimeout": 15000000,
+                    "tcpTimeoutPerKb": 10000,
+                    "smpPingInterval": 600000000
+                }
+            }),
+            r#"/_network {"socksProxy":"127.0.0.1:9050","socksMode":"always","hostMode":"onionViaSocks","requiredHostMode":false,"sessionMode":"user","tcpConnectTimeout":25000000,"tcpTimeout":15000000,"tcpTimeoutPerKb":10000,"smpPingInterval":600000000}"# => {
+                json!({ "type": "cmdOk" })
+            }
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let config = client.network_config().await.unwrap();
+        assert_eq!(config.tcp_timeout, Duration::from_secs(15));
+        client
+            .set_network_config(&config.via_tor("127.0.0.1:9050"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_incognito() {
+        let pending = |incognito: bool| {
+            json!({
+                "pccConnId": 9,
+                "viaContactUri": false,
+                "customUserProfileId": if incognito { Some(40) } else { None },
+                "createdAt": "2024-12-21T10:00:00Z"
+            })
+        };
+        let url = mock_server(move |cmd| match cmd {
+            "/_connect 1 incognito=on" => json!({
+                "type": "invitation",
+                "user": user(),
+                "connReqInvitation": "simplex:/invitation#/?v=2-7&smp=smp%3A%2F%2Fhash%40smp4.simplex.im%2Fq%23%2F%3Fv%3D1-3%26dh%3DMCo&e2e=v%3D2-3",
+                "connection": pending(true)
+            }),
+            "/_set incognito :9 off" => json!({
+                "type": "connectionIncognitoUpdated",
+                "user": user(),
+                "toConnection": pending(false)
+            }),
+            "/_accept incognito=on 5" => {
+                let mut contact = contact(2, "alice");
+                contact["activeConn"]["customUserProfileId"] = json!(40);
+                json!({ "type": "acceptingContactRequest", "user": user(), "contact": contact })
+            }
+            "/_info @2" => json!({
+                "type": "contactInfo",
+                "user": user(),
+                "contact": contact(2, "alice"),
+                "connectionStats": {},
+                "customUserProfile": { "displayName": "BraveOtter", "fullName": "" }
+            }),
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let invitation = client.create_invitation(1, true).await.unwrap();
+        assert!(invitation.connection.is_incognito());
+        assert!(invitation.link.parse::<ConnectionLink>().is_ok());
+        assert!(!client
+            .set_connection_incognito(9, false)
+            .await
+            .unwrap()
+            .is_incognito());
+
+        let alice = client.accept_contact_incognito(5).await.unwrap();
+        assert!(alice.is_incognito());
+
+        let user: User = serde_json::from_value(user()).unwrap();
+        assert_eq!(
+            client
+                .profile_seen_by(&user, &alice)
+                .await
+                .unwrap()
+                .display_name,
+            "BraveOtter"
+        );
+
+        let bob: Contact = serde_json::from_value(contact(3, "bob")).unwrap();
+        assert_eq!(
+            client
+                .profile_seen_by(&user, &bob)
+                .await
+                .unwrap()
+                .display_name,
+            user.profile.display_name
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_and_preferences() {
+        let url = mock_server(|cmd| match cmd {
+            r#"/_profile 1 {"displayName":"squarer","fullName":"Squaring bot","preferences":{"timedMessages":{"allow":"yes","ttl":86400},"calls":{"allow":"no"}}}"# => {
+                json!({
+                    "type": "userProfileUpdated",
+                    "user": user(),
+                    "fromProfile": { "displayName": "bot", "fullName": "" },
+                    "toProfile": { "displayName": "squarer", "fullName": "Squaring bot" },
+                    "updateSummary": { "updateSuccesses": 1, "updateFailures": 0 }
+                })
+            }
+            r#"/_set prefs @2 {"reactions":{"allow":"always"}}"# => {
+                let mut to_contact = contact(2, "alice");
+                to_contact["userPreferences"] = json!({ "reactions": { "allow": "always" } });
+                json!({
+                    "type": "contactPrefsUpdated",
+                    "user": user(),
+                    "fromContact": contact(2, "alice"),
+                    "toContact": to_contact
+                })
+            }
+            "/_set alias @2 Alice from work" => {
+                let mut to_contact = contact(2, "alice");
+                to_contact["profile"]["localAlias"] = json!("Alice from work");
+                json!({ "type": "contactAliasUpdated", "user": user(), "toContact": to_contact })
+            }
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let user: User = serde_json::from_value(user()).unwrap();
+        let mut profile = user.profile.to_profile();
+        profile.display_name = "squarer".to_string();
+        profile.full_name = "Squaring bot".to_string();
+        profile.preferences = Some(Box::new(Preferences {
+            calls: Some(Preference::new(AllowPreference::No)),
+            ..Preferences::default().with_timed_messages(
+                AllowPreference::Yes,
+                Some(Duration::from_secs(24 * 60 * 60)),
+            )
+        }));
+        assert!(client.update_profile(1, &profile).await.unwrap());
+
+        let reactions = Preferences {
+            reactions: Some(Preference::new(AllowPreference::Always)),
+            ..Default::default()
+        };
+        let alice = client.set_contact_preferences(2, &reactions).await.unwrap();
+        assert_eq!(alice.user_preferences, reactions);
+
+        let alice = client
+            .set_contact_alias(2, "Alice from work")
+            .await
+            .unwrap();
+        assert_eq!(alice.profile.local_alias, "Alice from work");
+    }
+
+    #[tokio::test]
+    async fn test_timed_messages() {
+        let url = mock_server(|cmd| match cmd {
+            r#"/_send @2 ttl=30 json [{"msgContent":{"type":"text","text":"one-time code: 4711"}}]"# => {
+                let mut item = direct_item(2, 40, "directSnd", "one-time code: 4711", json!({ "type": "sndNew" }));
+                item["chatItem"]["meta"]["itemTimed"] = json!({ "ttl": 30, "deleteAt": "2024-12-20T10:30:30Z" });
+                json!({ "type": "newChatItems", "user": user(), "chatItems": [item] })
+            }
+            r#"/_set prefs @2 {"timedMessages":{"allow":"yes","ttl":3600}}"# => {
+                let mut to_contact = contact(2, "alice");
+                to_contact["userPreferences"] = json!({ "timedMessages": { "allow": "yes", "ttl": 3600 } });
+                json!({ "type": "contactPrefsUpdated", "user": user(), "toContact": to_contact })
+            }
+            r#"/_group_profile #7 {"displayName":"team","fullName":"","image":null,"groupPreferences":{"timedMessages":{"enable":"on","ttl":86400}},"description":"Our team"}"# => {
+                let mut to_group = group_info(7, "team");
+                to_group["groupProfile"]["groupPreferences"] = json!({ "timedMessages": { "enable": "on", "ttl": 86400 } });
+                json!({ "type": "groupUpdated", "user": user(), "fromGroup": group_info(7, "team"), "toGroup": to_group })
+            }
+            "/_ttl 1 604800" => json!({ "type": "cmdOk", "user_": user() }),
+            "/_ttl 1" => json!({ "type": "chatItemTTL", "user": user(), "chatItemTTL": 604800 }),
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let message = ComposedMessage {
+            file_path: None,
+            quoted_item_id: None,
+            msg_content: MsgContent::Text(MCText {
+                text: "one-time code: 4711".to_string(),
+            }),
+        };
+        let items = client
+            .send_timed_messages(ChatInfoType::Direct, 2, &[message], Duration::from_secs(30))
+            .await
+            .unwrap();
+        assert_eq!(
+            items[0].chat_item.meta.expires_at(),
+            Some("2024-12-20T10:30:30Z".parse().unwrap())
+        );
+
+        let alice: Contact = serde_json::from_value(contact(2, "alice")).unwrap();
+        let alice = client
+            .set_contact_timer(&alice, Some(Duration::from_secs(3600)))
+            .await
+            .unwrap();
+        assert_eq!(
+            alice.user_preferences.timed_messages.unwrap().ttl,
+            Some(3600)
+        );
+
+        // The rest of the group's profile is sent back as it was.
+        let mut team = group_info(7, "team");
+        team["groupProfile"]["description"] = json!("Our team");
+        let team: GroupInfo = serde_json::from_value(team).unwrap();
+        let team = client
+            .set_group_timer(&team, Some(Duration::from_secs(24 * 60 * 60)))
+            .await
+            .unwrap();
+        let timed = team
+            .group_profile
+            .group_preferences
+            .unwrap()
+            .timed_messages
+            .unwrap();
+        assert_eq!(timed.enable, GroupFeatureEnabled::On);
+        assert_eq!(timed.ttl, Some(86400));
+
+        let week = Duration::from_secs(7 * 24 * 60 * 60);
+        client.set_chat_item_ttl(1, Some(week)).await.unwrap();
+        assert_eq!(client.chat_item_ttl(1).await.unwrap(), Some(week));
+    }
+
+    #[tokio::test]
+    async fn test_chat_settings() {
+        let url = mock_server(|cmd| match cmd {
+            r#"/_settings @2 {"enableNtfs":"none","favorite":true}"#
+            | r#"/_settings #7 {"enableNtfs":"mentions","sendRcpts":false,"favorite":false}"#
+            | "/_unread chat @2 on"
+            | "/_read chat @2" => json!({ "type": "cmdOk" }),
+            "/_clear chat #7" => json!({
+                "type": "chatCleared",
+                "user": user(),
+                "chatInfo": { "type": "group", "groupInfo": group_info(7, "team") }
+            }),
+            "/_delete @2 notify=off" => json!({
+                "type": "contactDeleted",
+                "user": user(),
+                "contact": contact(2, "alice")
+            }),
+            "/_delete #7 notify=on" => json!({
+                "type": "groupDeletedUser",
+                "user": user(),
+                "groupInfo": group_info(7, "team")
+            }),
+            _ => json!({ "type": "chatCmdError", "chatError": { "type": "error" } }),
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let alice: Contact = serde_json::from_value(contact(2, "alice")).unwrap();
+        assert_eq!(alice.chat_settings.enable_ntfs, NotificationMode::All);
+        let muted = ChatSettings {
+            enable_ntfs: NotificationMode::None,
+            favorite: true,
+            ..alice.chat_settings
+        };
+        assert!(muted.is_muted());
+        client
+            .set_chat_settings(ChatInfoType::Direct, 2, &muted)
+            .await
+            .unwrap();
+        client
+            .set_chat_settings(
+                ChatInfoType::Group,
+                7,
+                &ChatSettings {
+                    enable_ntfs: NotificationMode::Mentions,
+                    send_rcpts: Some(false),
+                    favorite: false,
+                },
+            )
+            .await
+            .unwrap();
+
+        client
+            .mark_chat_unread(ChatInfoType::Direct, 2, true)
+            .await
+            .unwrap();
+        client
+            .mark_chat_read(ChatInfoType::Direct, 2)
+            .await
+            .unwrap();
+
+        let cleared = client.clear_chat(ChatInfoType::Group, 7).await.unwrap();
+        assert_eq!(cleared.chat_id(), 7);
+        client
+            .delete_chat(ChatInfoType::Direct, 2, false)
+            .await
+            .unwrap();
+        client
+            .delete_chat(ChatInfoType::Group, 7, true)
+            .await
+            .unwrap();
+    }
+}
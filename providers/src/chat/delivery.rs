@@ -0,0 +1,358 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Mutex,
+};
+
+use tokio::sync::watch;
+
+use super::response::{AChatItem, CIStatus, ChatInfo, ChatItemId, ChatResponse, DirectionType, User};
+
+/// How many sent items are remembered before the oldest ones are dropped.
+const MAX_TRACKED_ITEMS: usize = 4096;
+
+/// How far a sent message has to get before [`DeliveryTracker::wait_for`] returns.
+///
+/// simplex-chat only has delivery receipts, so there is no separate stage for a message being read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryStage {
+    /// Sent to every recipient's server.
+    Sent,
+    /// Sent to the server of at least one group member.
+    PartiallySent,
+    /// A delivery receipt arrived from every recipient.
+    Delivered,
+    /// A delivery receipt arrived from at least one group member.
+    PartiallyDelivered,
+}
+
+impl DeliveryStage {
+    fn needs_receipts(self) -> bool {
+        matches!(
+            self,
+            DeliveryStage::Delivered | DeliveryStage::PartiallyDelivered
+        )
+    }
+
+    fn reached_by(self, status: &CIStatus) -> bool {
+        match self {
+            DeliveryStage::Sent => status.is_sent(true),
+            DeliveryStage::PartiallySent => status.is_sent(false),
+            DeliveryStage::Delivered => status.is_delivered(true),
+            DeliveryStage::PartiallyDelivered => status.is_delivered(false),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DeliveryError {
+    /// The user has delivery receipts turned off for this kind of chat.
+    ReceiptsDisabled,
+    /// Sending failed with the given status.
+    Failed(CIStatus),
+    /// The item is not a sent message.
+    NotSent,
+    /// The item stopped being tracked before reaching the requested stage.
+    Untracked,
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::ReceiptsDisabled => write!(f, "delivery receipts are disabled"),
+            DeliveryError::Failed(status) => write!(f, "message was not sent: {:?}", status),
+            DeliveryError::NotSent => write!(f, "item is not a sent message"),
+            DeliveryError::Untracked => write!(f, "item is no longer tracked"),
+        }
+    }
+}
+
+impl std::error::Error for DeliveryError {}
+
+/// Follows the status of sent messages, so callers can wait for them to be sent or delivered.
+///
+/// Feed every response from the message stream to [`DeliveryTracker::observe`]; sent items are
+/// picked up from `NewChatItems` and updated from the status update responses.
+pub struct DeliveryTracker {
+    send_rcpts_contacts: bool,
+    send_rcpts_small_groups: bool,
+    items: Mutex<TrackedItems>,
+}
+
+#[derive(Default)]
+struct TrackedItems {
+    statuses: HashMap<ChatItemId, watch::Sender<CIStatus>>,
+    order: VecDeque<ChatItemId>,
+}
+
+impl DeliveryTracker {
+    pub fn new(user: &User) -> Self {
+        Self {
+            send_rcpts_contacts: user.send_rcpts_contacts,
+            send_rcpts_small_groups: user.send_rcpts_small_groups,
+            items: Mutex::new(TrackedItems::default()),
+        }
+    }
+
+    pub fn observe(&self, response: &ChatResponse) {
+        match response {
+            ChatResponse::NewChatItems { chat_items, .. }
+            | ChatResponse::ChatItemsStatusesUpdated { chat_items, .. } => {
+                for item in chat_items {
+                    self.update(item);
+                }
+            }
+            ChatResponse::ChatItemStatusUpdated { chat_item, .. } => self.update(chat_item),
+            _ => {}
+        }
+    }
+
+    /// The last known status of a sent item.
+    pub fn status(&self, item_id: ChatItemId) -> Option<CIStatus> {
+        let items = self.items.lock().unwrap();
+        items
+            .statuses
+            .get(&item_id)
+            .map(|status| status.borrow().clone())
+    }
+
+    /// Waits until `item` reaches `stage`, failing early if sending fails or the stage can never
+    /// be reached because the user has receipts disabled for this kind of chat.
+    ///
+    /// Receipts are only sent in groups with up to 20 members, so waiting for a group message to
+    /// be delivered in a larger group will not finish; callers should wrap this in a timeout.
+    pub async fn wait_for(
+        &self,
+        item: &AChatItem,
+        stage: DeliveryStage,
+    ) -> Result<CIStatus, DeliveryError> {
+        if !is_sent_item(item) {
+            return Err(DeliveryError::NotSent);
+        }
+
+        if stage.needs_receipts() && !self.receipts_enabled(&item.chat_info) {
+            return Err(DeliveryError::ReceiptsDisabled);
+        }
+
+        let mut receiver = self.track(item);
+        loop {
+            let status = receiver.borrow_and_update().clone();
+
+            if status.is_error() {
+                return Err(DeliveryError::Failed(status));
+            }
+
+            if stage.reached_by(&status) {
+                return Ok(status);
+            }
+
+            receiver
+                .changed()
+                .await
+                .map_err(|_| DeliveryError::Untracked)?;
+        }
+    }
+
+    /// Stops tracking an item, failing anyone still waiting on it.
+    pub fn forget(&self, item_id: ChatItemId) {
+        let mut items = self.items.lock().unwrap();
+        items.statuses.remove(&item_id);
+        items.order.retain(|id| *id != item_id);
+    }
+
+    fn receipts_enabled(&self, chat_info: &ChatInfo) -> bool {
+        match chat_info {
+            ChatInfo::Direct(_) => self.send_rcpts_contacts,
+            ChatInfo::Group(_) => self.send_rcpts_small_groups,
+            ChatInfo::ContactRequest(_) => false,
+        }
+    }
+
+    fn update(&self, item: &AChatItem) {
+        if is_sent_item(item) {
+            self.track(item);
+        }
+    }
+
+    fn track(&self, item: &AChatItem) -> watch::Receiver<CIStatus> {
+        let item_id = item.chat_item.meta.item_id;
+        let status = item.chat_item.meta.item_status.clone();
+        let mut items = self.items.lock().unwrap();
+
+        if let Some(sender) = items.statuses.get(&item_id) {
+            // Items can come back with an older status than the one already seen, for example
+            // when the caller passes the item from the original `NewChatItems` response.
+            if !is_older(&status, &sender.borrow()) {
+                sender.send_replace(status);
+            }
+            return sender.subscribe();
+        }
+
+        let (sender, receiver) = watch::channel(status);
+        items.statuses.insert(item_id, sender);
+        items.order.push_back(item_id);
+
+        while items.order.len() > MAX_TRACKED_ITEMS {
+            if let Some(oldest) = items.order.pop_front() {
+                items.statuses.remove(&oldest);
+            }
+        }
+
+        receiver
+    }
+}
+
+fn is_sent_item(item: &AChatItem) -> bool {
+    matches!(
+        item.chat_item.chat_dir.direction_type,
+        DirectionType::DirectSnd | DirectionType::GroupSnd
+    )
+}
+
+fn progress(status: &CIStatus) -> u8 {
+    match status {
+        CIStatus::SndNew(_) => 0,
+        CIStatus::SndWarning(_) => 1,
+        CIStatus::SndSent(_) if status.is_sent(true) => 2,
+        CIStatus::SndSent(_) => 1,
+        CIStatus::SndRcvd(_) if status.is_delivered(true) => 4,
+        CIStatus::SndRcvd(_) => 3,
+        _ => 5,
+    }
+}
+
+fn is_older(status: &CIStatus, current: &CIStatus) -> bool {
+    progress(status) < progress(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::fixtures::{a_chat_item, direct_item, group_item, response, user};
+
+    fn tracker() -> Arc<DeliveryTracker> {
+        let user: User = serde_json::from_value(user()).unwrap();
+        Arc::new(DeliveryTracker::new(&user))
+    }
+
+    fn status_update(item: Value) -> ChatResponse {
+        response(json!({
+            "type": "chatItemsStatusesUpdated",
+            "user": user(),
+            "chatItems": [item]
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_delivery() {
+        let tracker = tracker();
+        let sent = a_chat_item(direct_item(
+            2,
+            10,
+            "directSnd",
+            "hi",
+            json!({ "type": "sndNew" }),
+        ));
+
+        let waiter = {
+            let tracker = Arc::clone(&tracker);
+            let sent = sent.clone();
+            tokio::spawn(async move { tracker.wait_for(&sent, DeliveryStage::Delivered).await })
+        };
+
+        tracker.observe(&status_update(direct_item(
+            2,
+            10,
+            "directSnd",
+            "hi",
+            json!({ "type": "sndSent", "sndProgress": "complete" }),
+        )));
+        tracker.observe(&status_update(direct_item(
+            2,
+            10,
+            "directSnd",
+            "hi",
+            json!({ "type": "sndRcvd", "msgRcptStatus": "ok", "sndProgress": "complete" }),
+        )));
+
+        let status = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(status.is_delivered(true));
+
+        // A stale copy of the item must not move the status backwards.
+        let done = tracker.wait_for(&sent, DeliveryStage::Sent).await.unwrap();
+        assert!(matches!(done, CIStatus::SndRcvd(_)));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_errors() {
+        let tracker = tracker();
+
+        let failed = a_chat_item(direct_item(
+            2,
+            11,
+            "directSnd",
+            "hi",
+            json!({ "type": "sndError", "agentError": { "type": "other", "sndError": "BROKER" } }),
+        ));
+        assert!(matches!(
+            tracker.wait_for(&failed, DeliveryStage::Sent).await,
+            Err(DeliveryError::Failed(_))
+        ));
+
+        let group = a_chat_item(group_item(
+            3,
+            12,
+            "groupSnd",
+            "hi",
+            json!({ "type": "sndNew" }),
+        ));
+        assert!(matches!(
+            tracker.wait_for(&group, DeliveryStage::Delivered).await,
+            Err(DeliveryError::ReceiptsDisabled)
+        ));
+
+        let received = a_chat_item(direct_item(
+            2,
+            13,
+            "directRcv",
+            "hi",
+            json!({ "type": "rcvNew" }),
+        ));
+        assert!(matches!(
+            tracker.wait_for(&received, DeliveryStage::Sent).await,
+            Err(DeliveryError::NotSent)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_partial_group_delivery() {
+        let tracker = tracker();
+        let partial = a_chat_item(group_item(
+            3,
+            14,
+            "groupSnd",
+            "hi",
+            json!({ "type": "sndSent", "sndProgress": "partial" }),
+        ));
+
+        tracker.observe(&response(json!({
+            "type": "newChatItems",
+            "user": user(),
+            "chatItems": [serde_json::to_value(&partial).unwrap()]
+        })));
+
+        assert!(tracker
+            .wait_for(&partial, DeliveryStage::PartiallySent)
+            .await
+            .is_ok());
+        assert!(!tracker.status(14).unwrap().is_sent(true));
+    }
+}
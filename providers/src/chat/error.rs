@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Error as SerdeJsonError;
 use tokio_tungstenite::tungstenite::Error as TungsteniteError;
 
+use super::response::{ChatError as ChatErrorResponse, ChatResponse};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ChatError {
     Error(String),
@@ -18,6 +20,27 @@ pub enum TransportError {
     GenericError,
 }
 
+/// The ways a typed command can fail.
+#[derive(Debug)]
+pub enum CommandError {
+    Transport(TransportError),
+    /// simplex-chat rejected the command.
+    Chat(ChatErrorResponse),
+    UnexpectedResponse(String),
+}
+
+impl CommandError {
+    pub fn unexpected(response: ChatResponse) -> Self {
+        CommandError::UnexpectedResponse(format!("{:?}", response))
+    }
+}
+
+impl From<TransportError> for CommandError {
+    fn from(err: TransportError) -> Self {
+        CommandError::Transport(err)
+    }
+}
+
 impl From<TungsteniteError> for TransportError {
     fn from(err: TungsteniteError) -> Self {
         TransportError::WebSocket(err.to_string())
@@ -39,3 +62,33 @@ impl fmt::Display for ChatError {
 }
 
 impl std::error::Error for ChatError {}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::ConnectionClosed => write!(f, "TransportError: connection closed"),
+            TransportError::Timeout => write!(f, "TransportError: timed out"),
+            TransportError::WebSocket(msg) => write!(f, "TransportError: websocket error: {}", msg),
+            TransportError::InvalidFormat(msg) => {
+                write!(f, "TransportError: invalid format: {}", msg)
+            }
+            TransportError::GenericError => write!(f, "TransportError: unknown error"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Transport(err) => write!(f, "{}", err),
+            CommandError::Chat(err) => write!(f, "CommandError: chat error: {:?}", err),
+            CommandError::UnexpectedResponse(msg) => {
+                write!(f, "CommandError: unexpected response: {}", msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
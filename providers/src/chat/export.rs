@@ -0,0 +1,688 @@
+//! Exports a chat's history as JSON Lines, an HTML transcript or plain text.
+
+use std::{fmt, io::Write, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use super::{
+    client::ChatClient,
+    error::{CommandError, TransportError},
+    response::{CIDirection, CIFile, ChatInfo, ChatInfoType, ChatItem, ChatItemId, DirectionType},
+    utils::extract_text_content,
+};
+
+/// How many items are requested at a time while walking a chat's history.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One item per line as simplex-chat sent it, in the shape of an
+    /// [`AChatItem`](super::response::AChatItem), including the fields the client leaves out.
+    JsonLines,
+    /// A self-contained page, with quotes, edits, reactions and links to attachments.
+    Html,
+    Text,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Html => "html",
+            ExportFormat::Text => "txt",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" | "json" => Ok(ExportFormat::JsonLines),
+            "html" => Ok(ExportFormat::Html),
+            "text" | "txt" => Ok(ExportFormat::Text),
+            other => Err(ExportError::InvalidFormat(other.to_string())),
+        }
+    }
+}
+
+/// Which part of a chat's history to export. The default exports all of it.
+#[derive(Clone, Debug, Default)]
+pub struct ExportRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ExportRange {
+    pub fn contains(&self, ts: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| ts >= since) && self.until.is_none_or(|until| ts < until)
+    }
+}
+
+/// The items of a chat, oldest first.
+#[derive(Clone, Debug)]
+pub struct ChatHistory {
+    /// The display name of the user, shown as the sender of their messages.
+    pub user_name: String,
+    pub chat_info: ChatInfo,
+    pub chat_items: Vec<ChatItem>,
+    /// `chat_info` and `chat_items` as simplex-chat sent them, for lossless JSON Lines exports.
+    raw_chat_info: Value,
+    raw_items: Vec<Value>,
+}
+
+impl ChatHistory {
+    /// A history from the `chatInfo` and `chatItems` of a chat, oldest item first.
+    pub fn from_json(
+        user_name: String,
+        chat_info: Value,
+        chat_items: Vec<Value>,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(ChatHistory {
+            user_name,
+            chat_info: serde_json::from_value(chat_info.clone())?,
+            chat_items: chat_items
+                .iter()
+                .map(|item| serde_json::from_value(item.clone()))
+                .collect::<Result<_, _>>()?,
+            raw_chat_info: chat_info,
+            raw_items: chat_items,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Transport(String),
+    Command(String),
+    UnexpectedResponse(String),
+    InvalidFormat(String),
+    Io(String),
+}
+
+impl From<TransportError> for ExportError {
+    fn from(err: TransportError) -> Self {
+        ExportError::Transport(err.to_string())
+    }
+}
+
+impl From<CommandError> for ExportError {
+    fn from(err: CommandError) -> Self {
+        match err {
+            CommandError::Transport(err) => err.into(),
+            CommandError::Chat(chat_error) => ExportError::Command(format!("{:?}", chat_error)),
+            CommandError::UnexpectedResponse(msg) => ExportError::UnexpectedResponse(msg),
+        }
+    }
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(err: std::io::Error) -> Self {
+        ExportError::Io(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(err: serde_json::Error) -> Self {
+        ExportError::Io(err.to_string())
+    }
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Transport(msg) => write!(f, "ExportError: {}", msg),
+            ExportError::Command(msg) => write!(f, "ExportError: command failed: {}", msg),
+            ExportError::UnexpectedResponse(msg) => {
+                write!(f, "ExportError: unexpected response: {}", msg)
+            }
+            ExportError::InvalidFormat(format) => {
+                write!(f, "ExportError: unknown format {}", format)
+            }
+            ExportError::Io(msg) => write!(f, "ExportError: write failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// Walks a chat's history backwards from its latest item, until the start of `range`.
+///
+/// simplex-chat can only page backwards, so pages newer than the end of `range` are still
+/// fetched, but only the items in `range` are kept.
+pub async fn fetch_history(
+    client: &ChatClient,
+    chat_type: ChatInfoType,
+    chat_id: u64,
+    range: &ExportRange,
+) -> Result<ChatHistory, ExportError> {
+    let user = client.active_user().await?;
+    let mut pages = Vec::new();
+    let mut before: Option<ChatItemId> = None;
+
+    let chat_info = loop {
+        let mut chat = client
+            .get_chat_json(chat_type, chat_id, before, PAGE_SIZE)
+            .await?;
+        let raw_items: Vec<Value> =
+            serde_json::from_value(chat["chatItems"].take()).map_err(TransportError::from)?;
+        let chat_items: Vec<ChatItem> = raw_items
+            .iter()
+            .map(|item| serde_json::from_value(item.clone()))
+            .collect::<Result<_, _>>()
+            .map_err(TransportError::from)?;
+
+        let oldest = chat_items.first().map(|item| item.meta.item_id);
+        let reached_start = match (range.since, chat_items.first()) {
+            (Some(since), Some(item)) => item.meta.item_ts < since,
+            _ => false,
+        };
+        let last_page = chat_items.len() < PAGE_SIZE || reached_start;
+
+        pages.push(
+            chat_items
+                .into_iter()
+                .zip(raw_items)
+                .filter(|(item, _)| range.contains(item.meta.item_ts))
+                .collect::<Vec<_>>(),
+        );
+
+        if last_page || oldest.is_none() {
+            break chat["chatInfo"].take();
+        }
+        before = oldest;
+    };
+
+    let (chat_items, raw_items) = pages.into_iter().rev().flatten().unzip();
+
+    Ok(ChatHistory {
+        user_name: user.local_display_name,
+        chat_info: serde_json::from_value(chat_info.clone()).map_err(TransportError::from)?,
+        chat_items,
+        raw_chat_info: chat_info,
+        raw_items,
+    })
+}
+
+/// Fetches a chat's history and writes it to `writer`.
+pub async fn export_chat<W: Write>(
+    client: &ChatClient,
+    chat_type: ChatInfoType,
+    chat_id: u64,
+    range: &ExportRange,
+    format: ExportFormat,
+    writer: &mut W,
+) -> Result<(), ExportError> {
+    let history = fetch_history(client, chat_type, chat_id, range).await?;
+    write_history(&history, format, writer)
+}
+
+pub fn write_history<W: Write>(
+    history: &ChatHistory,
+    format: ExportFormat,
+    writer: &mut W,
+) -> Result<(), ExportError> {
+    match format {
+        ExportFormat::JsonLines => write_json_lines(history, writer),
+        ExportFormat::Html => write_html(history, writer),
+        ExportFormat::Text => write_text(history, writer),
+    }
+}
+
+fn write_json_lines<W: Write>(history: &ChatHistory, writer: &mut W) -> Result<(), ExportError> {
+    for chat_item in &history.raw_items {
+        let item = json!({ "chatInfo": history.raw_chat_info, "chatItem": chat_item });
+        serde_json::to_writer(&mut *writer, &item)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn write_text<W: Write>(history: &ChatHistory, writer: &mut W) -> Result<(), ExportError> {
+    writeln!(writer, "Chat with {}", history.chat_info.display_name())?;
+
+    for item in &history.chat_items {
+        writeln!(writer)?;
+
+        if let Some(quote) = &item.quoted_item {
+            let sender = quote
+                .chat_dir
+                .as_ref()
+                .map(|dir| sender_name(history, dir))
+                .unwrap_or_default();
+            for line in quote.content.text().lines() {
+                writeln!(writer, "> {}: {}", sender, line)?;
+            }
+        }
+
+        let mut marks = Vec::new();
+        if item.meta.item_edited {
+            marks.push("edited");
+        }
+        if is_deleted(item) {
+            marks.push("deleted");
+        }
+        let marks = if marks.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", marks.join(", "))
+        };
+
+        writeln!(
+            writer,
+            "[{}] {}{}: {}",
+            item.meta.item_ts.format("%Y-%m-%d %H:%M"),
+            sender_name(history, &item.chat_dir),
+            marks,
+            item_text(item)
+        )?;
+
+        if let Some(file) = &item.file {
+            writeln!(writer, "  [file: {}]", file_description(file))?;
+        }
+
+        if !item.reactions.is_empty() {
+            writeln!(writer, "  [{}]", reactions(item))?;
+        }
+    }
+
+    Ok(())
+}
+
+const HTML_STYLE: &str = "
+    body { font-family: sans-serif; max-width: 48rem; margin: 2rem auto; color: #222; }
+    .item { margin: 0.75rem 0; padding: 0.5rem 0.75rem; border-radius: 0.5rem; background: #f1f1f1; }
+    .item.sent { background: #dcebff; }
+    .item.deleted .text { color: #888; text-decoration: line-through; }
+    .meta { font-size: 0.8rem; color: #666; }
+    .sender { font-weight: bold; }
+    blockquote { margin: 0.25rem 0; padding-left: 0.5rem; border-left: 3px solid #aaa; color: #555; }
+    .text { margin: 0.25rem 0; white-space: pre-wrap; }
+    .reactions, .file { font-size: 0.9rem; margin: 0.25rem 0; }
+";
+
+fn write_html<W: Write>(history: &ChatHistory, writer: &mut W) -> Result<(), ExportError> {
+    let title = html_escape(history.chat_info.display_name());
+
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html>")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>Chat with {}</title>", title)?;
+    writeln!(writer, "<style>{}</style>", HTML_STYLE)?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>Chat with {}</h1>", title)?;
+
+    for item in &history.chat_items {
+        let mut classes = vec!["item", if is_sent(item) { "sent" } else { "received" }];
+        if is_deleted(item) {
+            classes.push("deleted");
+        }
+
+        writeln!(
+            writer,
+            "<div class=\"{}\" id=\"item-{}\">",
+            classes.join(" "),
+            item.meta.item_id
+        )?;
+        write!(
+            writer,
+            "<div class=\"meta\"><span class=\"sender\">{}</span> <time datetime=\"{}\">{}</time>",
+            html_escape(&sender_name(history, &item.chat_dir)),
+            item.meta.item_ts.to_rfc3339(),
+            item.meta.item_ts.format("%Y-%m-%d %H:%M")
+        )?;
+        if item.meta.item_edited {
+            write!(writer, " <span class=\"edited\">(edited)</span>")?;
+        }
+        writeln!(writer, "</div>")?;
+
+        if let Some(quote) = &item.quoted_item {
+            let sender = quote
+                .chat_dir
+                .as_ref()
+                .map(|dir| sender_name(history, dir))
+                .unwrap_or_default();
+            let text = html_escape(quote.content.text());
+            match quote.item_id {
+                Some(item_id) => writeln!(
+                    writer,
+                    "<blockquote><a href=\"#item-{}\"><span class=\"sender\">{}</span></a> {}</blockquote>",
+                    item_id,
+                    html_escape(&sender),
+                    text
+                )?,
+                None => writeln!(
+                    writer,
+                    "<blockquote><span class=\"sender\">{}</span> {}</blockquote>",
+                    html_escape(&sender),
+                    text
+                )?,
+            }
+        }
+
+        writeln!(
+            writer,
+            "<p class=\"text\">{}</p>",
+            html_escape(&item_text(item))
+        )?;
+
+        if let Some(file) = &item.file {
+            match &file.file_source {
+                Some(source) => writeln!(
+                    writer,
+                    "<p class=\"file\"><a href=\"{}\">{}</a></p>",
+                    html_escape(&file_url(&source.file_path)),
+                    html_escape(&file_description(file))
+                )?,
+                None => writeln!(
+                    writer,
+                    "<p class=\"file\">{}</p>",
+                    html_escape(&file_description(file))
+                )?,
+            }
+        }
+
+        if !item.reactions.is_empty() {
+            writeln!(
+                writer,
+                "<p class=\"reactions\">{}</p>",
+                html_escape(&reactions(item))
+            )?;
+        }
+
+        writeln!(writer, "</div>")?;
+    }
+
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    Ok(())
+}
+
+fn sender_name(history: &ChatHistory, dir: &CIDirection) -> String {
+    match dir.direction_type {
+        DirectionType::DirectSnd | DirectionType::GroupSnd => history.user_name.clone(),
+        DirectionType::DirectRcv => history.chat_info.display_name().to_string(),
+        DirectionType::GroupRcv => match &dir.group_member {
+            Some(member) => match &member.member_profile {
+                Some(profile) => profile.display_name.clone(),
+                None => member.local_display_name.clone(),
+            },
+            None => "unknown member".to_string(),
+        },
+    }
+}
+
+fn is_sent(item: &ChatItem) -> bool {
+    matches!(
+        item.chat_dir.direction_type,
+        DirectionType::DirectSnd | DirectionType::GroupSnd
+    )
+}
+
+fn is_deleted(item: &ChatItem) -> bool {
+    item.meta.item_deleted.unwrap_or(false)
+}
+
+fn item_text(item: &ChatItem) -> String {
+    extract_text_content(item.content.clone()).unwrap_or_else(|| item.meta.item_text.clone())
+}
+
+fn reactions(item: &ChatItem) -> String {
+    item.reactions
+        .iter()
+        .map(|count| {
+            format!(
+                "{} {}",
+                count.reaction.as_emoji().unwrap_or("?"),
+                count.total_reacted
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn file_description(file: &CIFile) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = file.file_size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} ({} B)", file.file_name, file.file_size)
+    } else {
+        format!("{} ({:.1} {})", file.file_name, size, UNITS[unit])
+    }
+}
+
+/// A `file://` URL for an absolute path, with everything but unreserved characters and
+/// separators percent-encoded.
+fn file_url(path: &str) -> String {
+    let encoded: String = path
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect();
+
+    format!("file://{}", encoded)
+}
+
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::chat::fixtures::{chat_item, contact, mock_server, text_content, user};
+    use crate::chat::response::AChatItem;
+
+    fn history_item(item_id: u64, dir: &str, text: &str, ts: &str) -> Value {
+        let mut item = chat_item(
+            item_id,
+            dir,
+            text_content(dir, text),
+            json!({ "type": "rcvRead" }),
+        );
+        item["meta"]["itemTs"] = json!(ts);
+        item
+    }
+
+    fn sample_history() -> ChatHistory {
+        let mut reply = history_item(
+            2,
+            "directSnd",
+            "<b>sure</b> & thanks",
+            "2024-12-20T10:31:00Z",
+        );
+        reply["meta"]["itemEdited"] = json!(true);
+        reply["quotedItem"] = json!({
+            "chatDir": { "type": "directRcv" },
+            "itemId": 1,
+            "sentAt": "2024-12-20T10:30:00Z",
+            "content": { "type": "text", "text": "can you help?" }
+        });
+        reply["reactions"] = json!([{
+            "reaction": { "type": "emoji", "emoji": "👍" },
+            "userReacted": false,
+            "totalReacted": 1
+        }]);
+        reply["file"] = json!({
+            "fileId": 1,
+            "fileName": "invoice.pdf",
+            "fileSize": 2048,
+            "fileSource": { "filePath": "/files/invoice #3.pdf" },
+            "fileStatus": { "type": "sndComplete" },
+            "fileProtocol": "xftp"
+        });
+
+        // Not typed by the client, but kept in JSON Lines exports.
+        reply["meta"]["showGroupAsSender"] = json!(false);
+
+        ChatHistory::from_json(
+            "bot".to_string(),
+            json!({ "type": "direct", "contact": contact(2, "alice") }),
+            vec![
+                history_item(1, "directRcv", "can you help?", "2024-12-20T10:30:00Z"),
+                reply,
+            ],
+        )
+        .unwrap()
+    }
+
+    fn render(format: ExportFormat) -> String {
+        let mut out = Vec::new();
+        write_history(&sample_history(), format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_text() {
+        assert_eq!(
+            render(ExportFormat::Text),
+            "Chat with alice\n\
+             \n\
+             [2024-12-20 10:30] alice: can you help?\n\
+             \n\
+             > alice: can you help?\n\
+             [2024-12-20 10:31] bot (edited): <b>sure</b> & thanks\n  \
+             [file: invoice.pdf (2.0 KB)]\n  \
+             [👍 1]\n"
+        );
+    }
+
+    #[test]
+    fn test_write_html() {
+        let html = render(ExportFormat::Html);
+
+        assert!(html.contains("<p class=\"text\">&lt;b&gt;sure&lt;/b&gt; &amp; thanks</p>"));
+        assert!(html.contains("<a href=\"#item-1\"><span class=\"sender\">alice</span></a>"));
+        assert!(html.contains("<span class=\"edited\">(edited)</span>"));
+        assert!(
+            html.contains("<a href=\"file:///files/invoice%20%233.pdf\">invoice.pdf (2.0 KB)</a>")
+        );
+        assert!(html.contains("<p class=\"reactions\">👍 1</p>"));
+    }
+
+    #[test]
+    fn test_write_json_lines() {
+        let lines: Vec<AChatItem> = render(ExportFormat::JsonLines)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].chat_item.file.as_ref().unwrap().file_size, 2048);
+        assert_eq!(lines[1].chat_info.chat_id(), 2);
+
+        let raw: Vec<Value> = render(ExportFormat::JsonLines)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(raw[1]["chatItem"]["meta"]["showGroupAsSender"], false);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_history_pages() {
+        // 250 items, one per minute, served newest page first.
+        let ts = |id: u64| {
+            (DateTime::parse_from_rfc3339("2024-12-20T00:00:00Z").unwrap()
+                + chrono::Duration::minutes(id as i64))
+            .to_rfc3339()
+        };
+        let url = mock_server(move |cmd| {
+            if cmd == "/u" {
+                return json!({ "type": "activeUser", "user": user() });
+            }
+
+            let end = cmd
+                .split_whitespace()
+                .find_map(|arg| arg.strip_prefix("before="))
+                .map(|id| id.parse::<u64>().unwrap())
+                .unwrap_or(251);
+            let start = end.saturating_sub(PAGE_SIZE as u64).max(1);
+            let items: Vec<Value> = (start..end)
+                .map(|id| history_item(id, "directRcv", &format!("message {}", id), &ts(id)))
+                .collect();
+
+            json!({
+                "type": "apiChat",
+                "user": user(),
+                "chat": {
+                    "chatInfo": { "type": "direct", "contact": contact(2, "alice") },
+                    "chatItems": items
+                }
+            })
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        let stream = Box::pin(stream.await);
+        tokio::spawn(stream.for_each(|_| async {}));
+
+        let all = fetch_history(&client, ChatInfoType::Direct, 2, &ExportRange::default())
+            .await
+            .unwrap();
+        let ids: Vec<u64> = all
+            .chat_items
+            .iter()
+            .map(|item| item.meta.item_id)
+            .collect();
+        assert_eq!(ids, (1..=250).collect::<Vec<_>>());
+        assert_eq!(all.user_name, "bot");
+
+        let range = ExportRange {
+            since: Some(ts(200).parse().unwrap()),
+            until: Some(ts(210).parse().unwrap()),
+        };
+        let some = fetch_history(&client, ChatInfoType::Direct, 2, &range)
+            .await
+            .unwrap();
+        let ids: Vec<u64> = some
+            .chat_items
+            .iter()
+            .map(|item| item.meta.item_id)
+            .collect();
+        assert_eq!(ids, (200..210).collect::<Vec<_>>());
+
+        let range = ExportRange {
+            since: None,
+            until: Some(ts(5).parse().unwrap()),
+        };
+        let start = fetch_history(&client, ChatInfoType::Direct, 2, &range)
+            .await
+            .unwrap();
+        let ids: Vec<u64> = start
+            .chat_items
+            .iter()
+            .map(|item| item.meta.item_id)
+            .collect();
+        assert_eq!(ids, (1..5).collect::<Vec<_>>());
+        assert_eq!(start.raw_items.len(), 4);
+    }
+}
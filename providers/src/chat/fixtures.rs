@@ -0,0 +1,178 @@
+//! Sample simplex-chat payloads shared by the unit tests, here and in dependent crates through
+//! the `test-fixtures` feature.
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+use super::{
+    commands::CommandPayload,
+    response::{AChatItem, ChatResponse},
+};
+
+pub fn user() -> Value {
+    let preference = json!({ "allow": "yes" });
+
+    json!({
+        "userId": 1,
+        "agentUserId": "1",
+        "userContactId": 1,
+        "localDisplayName": "bot",
+        "profile": {
+            "profileId": 1,
+            "displayName": "bot",
+            "fullName": "",
+            "localAlias": ""
+        },
+        "fullPreferences": {
+            "timedMessages": preference,
+            "fullDelete": preference,
+            "voice": preference,
+            "calls": preference,
+            "reactions": preference
+        },
+        "activeUser": true,
+        "showNtfs": true,
+        "sendRcptsContacts": true,
+        "sendRcptsSmallGroups": false
+    })
+}
+
+pub fn contact(contact_id: u64, name: &str) -> Value {
+    let setting = json!({
+        "enabled": { "forUser": true, "forContact": true },
+        "userPreference": { "type": "user", "preference": { "allow": "yes" } },
+        "contactPreference": { "allow": "yes" }
+    });
+
+    json!({
+        "contactId": contact_id,
+        "localDisplayName": name,
+        "profile": {
+            "profileId": contact_id + 100,
+            "displayName": name,
+            "fullName": "",
+            "localAlias": ""
+        },
+        "activeConn": { "connId": contact_id + 200 },
+        "contactUsed": true,
+        "contactStatus": "active",
+        "chatSettings": { "enableNtfs": "all", "favorite": false },
+        "userPreferences": {},
+        "mergedPreferences": {
+            "timedMessages": setting,
+            "fullDelete": setting,
+            "voice": setting,
+            "calls": setting,
+            "reactions": setting
+        },
+        "createdAt": "2024-12-20T10:00:00Z",
+        "updatedAt": "2024-12-20T10:00:00Z",
+        "contactGrpInvSent": false,
+        "chatDeleted": false
+    })
+}
+
+pub fn group_info(group_id: u64, name: &str) -> Value {
+    json!({
+        "groupId": group_id,
+        "localDisplayName": name,
+        "groupProfile": { "displayName": name, "fullName": "" },
+        "membership": {
+            "groupMemberId": 1,
+            "memberId": "bWVtYmVy",
+            "memberRole": "member"
+        },
+        "createdAt": "2024-12-20T10:00:00Z"
+    })
+}
+
+pub fn chat_item(item_id: u64, dir: &str, content: Value, status: Value) -> Value {
+    let text = content["msgContent"]["text"].clone();
+
+    json!({
+        "chatDir": { "type": dir },
+        "meta": {
+            "itemId": item_id,
+            "itemTs": "2024-12-20T10:30:00Z",
+            "itemText": text,
+            "itemStatus": status,
+            "createdAt": "2024-12-20T10:30:00Z",
+            "updatedAt": "2024-12-20T10:30:00Z",
+            "itemSharedMsgId": format!("msg-{}", item_id),
+            "itemEdited": false,
+            "editable": true,
+            "deletable": true
+        },
+        "content": content,
+        "formattedText": null,
+        "quotedItem": null,
+        "reactions": []
+    })
+}
+
+pub fn text_content(dir: &str, text: &str) -> Value {
+    let content_type = if dir.ends_with("Snd") {
+        "sndMsgContent"
+    } else {
+        "rcvMsgContent"
+    };
+
+    json!({ "type": content_type, "msgContent": { "type": "text", "text": text } })
+}
+
+pub fn direct_item(contact_id: u64, item_id: u64, dir: &str, text: &str, status: Value) -> Value {
+    json!({
+        "chatInfo": { "type": "direct", "contact": contact(contact_id, "alice") },
+        "chatItem": chat_item(item_id, dir, text_content(dir, text), status)
+    })
+}
+
+pub fn group_item(group_id: u64, item_id: u64, dir: &str, text: &str, status: Value) -> Value {
+    json!({
+        "chatInfo": { "type": "group", "groupInfo": group_info(group_id, "team") },
+        "chatItem": chat_item(item_id, dir, text_content(dir, text), status)
+    })
+}
+
+pub fn a_chat_item(value: Value) -> AChatItem {
+    serde_json::from_value(value).unwrap()
+}
+
+pub fn response(resp: Value) -> ChatResponse {
+    serde_json::from_value(resp).unwrap()
+}
+
+/// Starts a websocket server that answers every command with `handler(cmd)`, returning its url.
+/// A `null` answer closes the connection instead, like a simplex-chat that went away.
+pub async fn mock_server<F>(handler: F) -> String
+where
+    F: Fn(&str) -> Value + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = accept_async(stream).await.unwrap();
+
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            let command: CommandPayload = serde_json::from_str(&text).unwrap();
+            let resp = handler(&command.cmd);
+            if resp.is_null() {
+                break;
+            }
+            let reply = json!({ "corrId": command.corr_id, "resp": resp });
+            if ws
+                .send(Message::Text(reply.to_string().into()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    url
+}
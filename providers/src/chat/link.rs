@@ -0,0 +1,461 @@
+//! SimpleX connection links, the addresses and one-time invitations people share to connect.
+//!
+//! Links come in two forms that carry the same request: `simplex:/contact#/?v=2-7&smp=...` and
+//! `https://simplex.chat/contact#/?v=2-7&smp=...`, where the https form opens the app or a page
+//! explaining how to get it. The `smp` parameter lists the SMP queues, on the relays of the
+//! person who made the link, that the connection request is sent to.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::response::SimplexLinkType;
+
+/// The host of the https form of links made by the official apps.
+pub const DEFAULT_LINK_HOST: &str = "simplex.chat";
+
+/// A parsed contact address, group link or one-time invitation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionLink {
+    pub link_type: SimplexLinkType,
+    /// The versions of the SimpleX agent protocol the link works with.
+    pub version: VersionRange,
+    pub queues: Vec<SmpQueue>,
+    /// The key agreement parameters of an invitation, still encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e2e: Option<String>,
+    /// JSON attached to contact links, like `{"type":"group","groupLinkId":"..."}` for groups.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+impl ConnectionLink {
+    /// The hosts of every relay the link uses, without ports.
+    pub fn smp_hosts(&self) -> Vec<String> {
+        self.queues
+            .iter()
+            .flat_map(|queue| queue.server.hosts.iter().cloned())
+            .collect()
+    }
+
+    /// The link in its `simplex:` form, which the apps open directly.
+    pub fn to_simplex_uri(&self) -> String {
+        format!("simplex:{}", self.path_and_fragment())
+    }
+
+    /// The link in its `https://` form, for sharing with people who might not have an app yet.
+    pub fn to_https(&self, host: &str) -> String {
+        format!("https://{}{}", host, self.path_and_fragment())
+    }
+
+    fn path_and_fragment(&self) -> String {
+        let path = match self.link_type {
+            SimplexLinkType::Invitation => "invitation",
+            _ => "contact",
+        };
+        let smp = self
+            .queues
+            .iter()
+            .map(SmpQueue::to_string)
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let mut fragment = format!("v={}&smp={}", self.version, percent_encode(&smp));
+        if let Some(e2e) = &self.e2e {
+            fragment.push_str(&format!("&e2e={}", percent_encode(e2e)));
+        }
+        if let Some(data) = &self.data {
+            fragment.push_str(&format!("&data={}", percent_encode(data)));
+        }
+
+        format!("/{}#/?{}", path, fragment)
+    }
+}
+
+impl FromStr for ConnectionLink {
+    type Err = LinkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let path = match s.strip_prefix("simplex:") {
+            Some(path) => path,
+            None => {
+                let without_scheme = s
+                    .strip_prefix("https://")
+                    .ok_or_else(|| LinkError::new("links start with simplex:/ or https://"))?;
+                without_scheme
+                    .find('/')
+                    .map(|start| &without_scheme[start..])
+                    .ok_or_else(|| LinkError::new("the link has no path"))?
+            }
+        };
+
+        let (link_path, fragment) = path
+            .split_once('#')
+            .ok_or_else(|| LinkError::new("the link has no parameters"))?;
+        let params = parse_params(
+            fragment
+                .strip_prefix("/?")
+                .ok_or_else(|| LinkError::new("the link has no parameters"))?,
+        );
+
+        let version = required(&params, "v")?.parse()?;
+        let queues = required(&params, "smp")?
+            .split(';')
+            .map(str::parse)
+            .collect::<Result<Vec<SmpQueue>, _>>()?;
+        let e2e = optional(&params, "e2e");
+        let data = optional(&params, "data");
+
+        let link_type = match link_path {
+            "/invitation" => {
+                if e2e.is_none() {
+                    return Err(LinkError::new("invitations need e2e parameters"));
+                }
+                SimplexLinkType::Invitation
+            }
+            "/contact" => {
+                let is_group = data
+                    .as_deref()
+                    .and_then(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+                    .is_some_and(|data| data["type"] == "group");
+
+                if is_group {
+                    SimplexLinkType::Group
+                } else {
+                    SimplexLinkType::Contact
+                }
+            }
+            other => {
+                return Err(LinkError(format!(
+                    "{} is not a contact or invitation link",
+                    other
+                )))
+            }
+        };
+
+        Ok(ConnectionLink {
+            link_type,
+            version,
+            queues,
+            e2e,
+            data,
+        })
+    }
+}
+
+impl fmt::Display for ConnectionLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_simplex_uri())
+    }
+}
+
+/// A queue on an SMP relay: `smp://<key hash>@<hosts>[:port]/<queue id>#/?v=1-3&dh=<key>`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmpQueue {
+    pub server: SmpServer,
+    pub queue_id: String,
+    pub version: VersionRange,
+    /// The public key for the Diffie-Hellman exchange with the relay.
+    pub dh_public_key: String,
+    /// Parameters newer versions add, like the queue mode `q`, kept in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<(String, String)>,
+}
+
+impl FromStr for SmpQueue {
+    type Err = LinkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("smp://")
+            .ok_or_else(|| LinkError(format!("{} is not an SMP queue", s)))?;
+        let (address, fragment) = rest
+            .split_once('#')
+            .ok_or_else(|| LinkError(format!("the queue {} has no parameters", s)))?;
+        let (authority, queue_id) = address
+            .split_once('/')
+            .ok_or_else(|| LinkError(format!("the queue {} has no ID", s)))?;
+
+        if queue_id.is_empty() {
+            return Err(LinkError(format!("the queue {} has no ID", s)));
+        }
+
+        let mut params = parse_params(fragment.strip_prefix("/?").unwrap_or(fragment));
+        let version = take(&mut params, "v")
+            .ok_or_else(|| LinkError(format!("the queue {} has no version", s)))?
+            .parse()?;
+        let dh_public_key = take(&mut params, "dh")
+            .ok_or_else(|| LinkError(format!("the queue {} has no dh key", s)))?;
+
+        Ok(SmpQueue {
+            server: authority.parse()?,
+            queue_id: queue_id.to_string(),
+            version,
+            dh_public_key,
+            params,
+        })
+    }
+}
+
+impl fmt::Display for SmpQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "smp://{}/{}#/?v={}&dh={}",
+            self.server,
+            self.queue_id,
+            self.version,
+            percent_encode(&self.dh_public_key)
+        )?;
+        for (key, value) in &self.params {
+            write!(f, "&{}={}", key, percent_encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// An SMP relay, identified by the hash of its certificate's key and reachable at any of its
+/// hosts, typically a domain and an onion address.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmpServer {
+    pub key_hash: String,
+    pub hosts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl FromStr for SmpServer {
+    type Err = LinkError;
+
+    /// Parses `<key hash>@<host>,<host>[:port]`, the part of a server address after `smp://`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key_hash, hosts) = s
+            .split_once('@')
+            .ok_or_else(|| LinkError(format!("the server {} has no key hash", s)))?;
+
+        let (hosts, port) = match hosts.rsplit_once(':') {
+            Some((hosts, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| LinkError(format!("{} is not a port", port)))?;
+                (hosts, Some(port))
+            }
+            None => (hosts, None),
+        };
+        let hosts: Vec<String> = hosts.split(',').map(str::to_string).collect();
+
+        if key_hash.is_empty() || hosts.iter().any(|host| host.is_empty()) {
+            return Err(LinkError(format!("{} is not a server address", s)));
+        }
+
+        Ok(SmpServer {
+            key_hash: key_hash.to_string(),
+            hosts,
+            port,
+        })
+    }
+}
+
+impl fmt::Display for SmpServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.key_hash, self.hosts.join(","))?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+/// Protocol versions, written `2-7`, or `2` when both ends are the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl FromStr for VersionRange {
+    type Err = LinkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LinkError(format!("{} is not a version range", s));
+        let (min, max) = s.split_once('-').unwrap_or((s, s));
+        let min: u32 = min.parse().map_err(|_| invalid())?;
+        let max: u32 = max.parse().map_err(|_| invalid())?;
+
+        if min > max {
+            return Err(invalid());
+        }
+        Ok(VersionRange { min, max })
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}-{}", self.min, self.max)
+        }
+    }
+}
+
+/// Why a link could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkError(pub String);
+
+impl LinkError {
+    fn new(msg: &str) -> Self {
+        LinkError(msg.to_string())
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LinkError: {}", self.0)
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+fn parse_params(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_string(), percent_decode(value)))
+        .collect()
+}
+
+fn optional(params: &[(String, String)], name: &str) -> Option<String> {
+    params
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+}
+
+fn required(params: &[(String, String)], name: &str) -> Result<String, LinkError> {
+    optional(params, name).ok_or_else(|| LinkError(format!("the link has no {} parameter", name)))
+}
+
+fn take(params: &mut Vec<(String, String)>, name: &str) -> Option<String> {
+    let index = params.iter().position(|(key, _)| key == name)?;
+    Some(params.remove(index).1)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Encodes everything but unreserved characters, as the apps do.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTACT: &str = "https://simplex.chat/contact#/?v=2-7&smp=smp%3A%2F%2Fu2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU%3D%40smp4.simplex.im%2Cabcdefghij.onion%2F8IgHkYp0G9w3V1Ga2k6l7fOGrtRrX7Ae%23%2F%3Fv%3D1-3%26dh%3DMCowBQYDK2VuAyEAjiswwI3O_NlS8Fk3HJUW870EY2bAwmttMBsvRB9eV3o%253D%26q%3Dc";
+    const INVITATION: &str = "simplex:/invitation#/?v=2-7&smp=smp%3A%2F%2Fhash%40smp8.simplex.im%3A5223%2Fq1%23%2F%3Fv%3D1-3%26dh%3DMCowBQYDK2VuAyEAtd8kuO9qEw3o3L5RvCR-7lEYqg4rYcUkjE3c6Xr8h0k%253D&e2e=v%3D2-3%26x3dh%3DMEIwBQYDK2VvAzkAWxH7RbzI6-8ep7ymJ6uXfGzFqu0eoUw%253D%2CMEIwBQYDK2VvAzkAHuFTC2w-dW1UQFzY3aWd2bhsqXyAAPQ%253D";
+
+    #[test]
+    fn test_parse() {
+        let link: ConnectionLink = CONTACT.parse().unwrap();
+
+        assert_eq!(link.link_type, SimplexLinkType::Contact);
+        assert_eq!(link.version, VersionRange { min: 2, max: 7 });
+        assert_eq!(
+            link.smp_hosts(),
+            vec!["smp4.simplex.im", "abcdefghij.onion"]
+        );
+        assert_eq!(link.queues[0].queue_id, "8IgHkYp0G9w3V1Ga2k6l7fOGrtRrX7Ae");
+        assert_eq!(
+            link.queues[0].dh_public_key,
+            "MCowBQYDK2VuAyEAjiswwI3O_NlS8Fk3HJUW870EY2bAwmttMBsvRB9eV3o="
+        );
+        assert_eq!(
+            link.queues[0].params,
+            vec![("q".to_string(), "c".to_string())]
+        );
+
+        let invitation: ConnectionLink = INVITATION.parse().unwrap();
+        assert_eq!(invitation.link_type, SimplexLinkType::Invitation);
+        assert_eq!(invitation.queues[0].server.port, Some(5223));
+        assert_eq!(
+            invitation.e2e.as_deref(),
+            Some("v=2-3&x3dh=MEIwBQYDK2VvAzkAWxH7RbzI6-8ep7ymJ6uXfGzFqu0eoUw%3D,MEIwBQYDK2VvAzkAHuFTC2w-dW1UQFzY3aWd2bhsqXyAAPQ%3D")
+        );
+
+        let group = CONTACT.to_string()
+            + "&data=%7B%22type%22%3A%22group%22%2C%22groupLinkId%22%3A%22abc%22%7D";
+        assert_eq!(
+            group.parse::<ConnectionLink>().unwrap().link_type,
+            SimplexLinkType::Group
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        // Links render as the apps write them, with padded keys encoded inside the queue URI.
+        let link: ConnectionLink = CONTACT.parse().unwrap();
+        assert_eq!(link.to_https(DEFAULT_LINK_HOST), CONTACT);
+        assert_eq!(
+            link.to_simplex_uri(),
+            CONTACT.replace("https://simplex.chat", "simplex:")
+        );
+
+        let invitation: ConnectionLink = INVITATION.parse().unwrap();
+        assert_eq!(invitation.to_string(), INVITATION);
+    }
+
+    #[test]
+    fn test_invalid_links() {
+        for link in [
+            "https://simplex.chat",
+            "simplex:/contact",
+            "simplex:/contact#/?v=2-7",
+            "simplex:/chat#/?v=2-7&smp=smp%3A%2F%2Fh%40host%2Fq%23%2F%3Fv%3D1%26dh%3Dk",
+            "simplex:/contact#/?v=7-2&smp=smp%3A%2F%2Fh%40host%2Fq%23%2F%3Fv%3D1%26dh%3Dk",
+            "simplex:/contact#/?v=2-7&smp=smp%3A%2F%2Fhost%2Fq%23%2F%3Fv%3D1%26dh%3Dk",
+            "simplex:/contact#/?v=2-7&smp=smp%3A%2F%2Fh%40host%2Fq%23%2F%3Fv%3D1",
+            // Invitations cannot be accepted without their key agreement parameters.
+            "simplex:/invitation#/?v=2-7&smp=smp%3A%2F%2Fh%40host%2Fq%23%2F%3Fv%3D1%26dh%3Dk",
+            "ftp://simplex.chat/contact#/?v=2-7",
+        ] {
+            assert!(link.parse::<ConnectionLink>().is_err(), "{}", link);
+        }
+    }
+}
@@ -0,0 +1,579 @@
+//! Offline implementation of simplex-chat's markdown, equivalent to what `apiParseMarkdown` returns.
+//!
+//! The rules follow the Haskell parser in `Simplex.Chat.Markdown`: markers only apply when the
+//! enclosed text does not start or end with a space, unmatched markers are kept as plain text, and
+//! trailing punctuation is never part of a link, email or mention.
+
+use super::{
+    link::ConnectionLink,
+    response::{Format, FormatColor, FormattedText},
+};
+
+/// Parses `text` into formatted fragments, merging adjacent fragments with the same format.
+pub fn parse_markdown(text: &str) -> Vec<FormattedText> {
+    let mut parser = Parser::default();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let consumed = if c.is_whitespace() {
+            let len = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            parser.push(&rest[..len], None);
+            len
+        } else {
+            match c {
+                '*' => parser.formatted(rest, '*', Format::Bold),
+                '_' => parser.formatted(rest, '_', Format::Italic),
+                '~' => parser.formatted(rest, '~', Format::StrikeThrough),
+                '`' => parser.formatted(rest, '`', Format::Snippet),
+                '#' => parser.secret(rest),
+                '!' => parser.colored(rest).unwrap_or_else(|| parser.word(rest)),
+                '@' => parser.mention(rest).unwrap_or_else(|| parser.word(rest)),
+                '+' => parser.phone(rest).unwrap_or_else(|| parser.word(rest)),
+                c if c.is_ascii_digit() => parser.phone(rest).unwrap_or_else(|| parser.word(rest)),
+                _ => parser.word(rest),
+            }
+        };
+
+        rest = &rest[consumed..];
+    }
+
+    parser.items
+}
+
+/// Like [`parse_markdown`], but returns `None` when the text has no formatting at all, matching
+/// how simplex-chat fills `ChatItem::formatted_text`.
+pub fn parse_maybe_markdown_list(text: &str) -> Option<Vec<FormattedText>> {
+    let items = parse_markdown(text);
+
+    if items.iter().all(|item| item.format.is_none()) {
+        None
+    } else {
+        Some(items)
+    }
+}
+
+/// Renders formatted fragments back to markdown source, so that messages can be composed from
+/// [`FormattedText`] values and sent as plain text.
+pub fn to_markdown(items: &[FormattedText]) -> String {
+    items
+        .iter()
+        .map(|item| match &item.format {
+            Some(Format::Bold) => format!("*{}*", item.text),
+            Some(Format::Italic) => format!("_{}_", item.text),
+            Some(Format::StrikeThrough) => format!("~{}~", item.text),
+            Some(Format::Snippet) => format!("`{}`", item.text),
+            Some(Format::Secret) => format!("#{}#", item.text),
+            Some(Format::Colored { color }) => match color_code(*color) {
+                Some(code) => format!("!{} {}!", code, item.text),
+                None => item.text.clone(),
+            },
+            _ => item.text.clone(),
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Parser {
+    items: Vec<FormattedText>,
+}
+
+impl Parser {
+    fn push(&mut self, text: &str, format: Option<Format>) {
+        if text.is_empty() {
+            return;
+        }
+
+        match self.items.last_mut() {
+            Some(last) if last.format == format => last.text.push_str(text),
+            _ => self.items.push(FormattedText {
+                text: text.to_string(),
+                format,
+            }),
+        }
+    }
+
+    fn formatted(&mut self, rest: &str, marker: char, format: Format) -> usize {
+        let body = &rest[marker.len_utf8()..];
+
+        match body.find(marker) {
+            Some(end) => {
+                let inner = &body[..end];
+                let consumed = end + 2 * marker.len_utf8();
+
+                if is_trimmed(inner) {
+                    self.push(inner, Some(format));
+                } else {
+                    self.push(&rest[..consumed], None);
+                }
+
+                consumed
+            }
+            None => {
+                self.push(rest, None);
+                rest.len()
+            }
+        }
+    }
+
+    fn secret(&mut self, rest: &str) -> usize {
+        let body = &rest[1..];
+        let before = leading(body, '#');
+        let inner_end = body[before..].find('#').map_or(body.len(), |i| before + i);
+        let after = leading(&body[inner_end..], '#');
+        let inner = &body[before..inner_end];
+        let all = &body[..inner_end + after];
+
+        if after == 0 || !is_trimmed(inner) {
+            self.push(&rest[..1 + all.len()], None);
+        } else {
+            self.push(&all[..all.len() - 1], Some(Format::Secret));
+        }
+
+        1 + all.len()
+    }
+
+    fn colored(&mut self, rest: &str) -> Option<usize> {
+        let (color, len) = parse_color(&rest[1..])?;
+        let mut consumed = 1 + len;
+
+        let space = rest[consumed..]
+            .chars()
+            .next()
+            .filter(|c| c.is_whitespace())?;
+        consumed += space.len_utf8();
+
+        let body = &rest[consumed..];
+        let first = body.chars().next().filter(|c| *c != ' ' && *c != '!')?;
+        let end = first.len_utf8() + body[first.len_utf8()..].find('!')?;
+        let inner = &body[..end];
+
+        if inner.ends_with(' ') {
+            return None;
+        }
+
+        self.push(inner, Some(Format::Colored { color }));
+        Some(consumed + end + 1)
+    }
+
+    fn mention(&mut self, rest: &str) -> Option<usize> {
+        let body = &rest[1..];
+
+        if let Some(quoted) = body.strip_prefix('\'') {
+            let end = quoted.find('\'')?;
+            let name = &quoted[..end];
+            name.chars().next().filter(|c| is_name_char(*c))?;
+
+            let consumed = 1 + end + 2;
+            self.push(
+                &rest[..consumed],
+                Some(Format::Mention {
+                    member_name: name.to_string(),
+                }),
+            );
+            return Some(consumed);
+        }
+
+        body.chars().next().filter(|c| is_name_char(*c))?;
+        let word_end = body.find(char::is_whitespace).unwrap_or(body.len());
+        let (name, punct) = split_punctuation(&body[..word_end]);
+
+        if name.is_empty() {
+            return None;
+        }
+
+        self.push(
+            &rest[..1 + name.len()],
+            Some(Format::Mention {
+                member_name: name.to_string(),
+            }),
+        );
+        self.push(punct, None);
+        Some(1 + word_end)
+    }
+
+    fn phone(&mut self, rest: &str) -> Option<usize> {
+        let mut pos = 0;
+
+        if let Some(after_plus) = rest.strip_prefix('+') {
+            pos = 1 + leading_digits(after_plus).filter(|len| *len > 0)?;
+        }
+
+        if let Some(len) = phone_area_code(&rest[pos..]) {
+            pos += len;
+        }
+
+        while let Some(len) = phone_segment(&rest[pos..]) {
+            pos += len;
+        }
+
+        let phone = &rest[..pos];
+        let at_boundary = rest[pos..]
+            .chars()
+            .next()
+            .is_none_or(|c| c.is_whitespace() || is_punctuation(c));
+
+        if !(7..=22).contains(&phone.chars().count()) || !at_boundary {
+            return None;
+        }
+
+        self.push(phone, Some(Format::Phone));
+        Some(pos)
+    }
+
+    fn word(&mut self, rest: &str) -> usize {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let word = &rest[..end];
+        let (text, punct) = split_punctuation(word);
+
+        if is_uri(text) {
+            self.push(text, Some(uri_format(text)));
+            self.push(punct, None);
+        } else if is_email(text) {
+            self.push(text, Some(Format::Email));
+            self.push(punct, None);
+        } else {
+            self.push(word, None);
+        }
+
+        end
+    }
+}
+
+fn is_trimmed(text: &str) -> bool {
+    !text.is_empty() && !text.starts_with(char::is_whitespace) && !text.ends_with(char::is_whitespace)
+}
+
+fn leading(text: &str, c: char) -> usize {
+    text.len() - text.trim_start_matches(c).len()
+}
+
+fn leading_digits(text: &str) -> Option<usize> {
+    Some(text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len())
+}
+
+fn phone_separator(text: &str) -> usize {
+    match text.chars().next() {
+        Some(' ' | '-' | '.') => 1,
+        _ => 0,
+    }
+}
+
+fn phone_area_code(text: &str) -> Option<usize> {
+    let sep = phone_separator(text);
+    let body = text[sep..].strip_prefix('(')?;
+    let digits = leading_digits(body).filter(|len| *len > 0)?;
+    body[digits..].strip_prefix(')')?;
+    Some(sep + 1 + digits + 1)
+}
+
+fn phone_segment(text: &str) -> Option<usize> {
+    [phone_separator(text), 0].into_iter().find_map(|sep| {
+        leading_digits(&text[sep..])
+            .filter(|len| *len > 0)
+            .map(|len| sep + len)
+    })
+}
+
+fn parse_color(text: &str) -> Option<(FormatColor, usize)> {
+    let (color, full) = match text.chars().next()? {
+        '1' => return Some((FormatColor::Red, 1)),
+        '2' => return Some((FormatColor::Green, 1)),
+        '3' => return Some((FormatColor::Blue, 1)),
+        '4' => return Some((FormatColor::Yellow, 1)),
+        '5' => return Some((FormatColor::Cyan, 1)),
+        '6' => return Some((FormatColor::Magenta, 1)),
+        'r' => (FormatColor::Red, "red"),
+        'g' => (FormatColor::Green, "green"),
+        'b' => (FormatColor::Blue, "blue"),
+        'y' => (FormatColor::Yellow, "yellow"),
+        'c' => (FormatColor::Cyan, "cyan"),
+        'm' => (FormatColor::Magenta, "magenta"),
+        _ => return None,
+    };
+
+    let len = if text.starts_with(full) {
+        full.len()
+    } else {
+        1
+    };
+    Some((color, len))
+}
+
+fn color_code(color: FormatColor) -> Option<u8> {
+    match color {
+        FormatColor::Red => Some(1),
+        FormatColor::Green => Some(2),
+        FormatColor::Blue => Some(3),
+        FormatColor::Yellow => Some(4),
+        FormatColor::Cyan => Some(5),
+        FormatColor::Magenta => Some(6),
+        FormatColor::Black | FormatColor::White => None,
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c > ' ' && c != '#' && c != '@' && c != '\''
+}
+
+fn is_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '!' | '"'
+            | '#'
+            | '%'
+            | '&'
+            | '\''
+            | '('
+            | ')'
+            | '*'
+            | ','
+            | '-'
+            | '.'
+            | '/'
+            | ':'
+            | ';'
+            | '?'
+            | '@'
+            | '['
+            | '\\'
+            | ']'
+            | '_'
+            | '{'
+            | '}'
+            | '¡'
+            | '§'
+            | '«'
+            | '¶'
+            | '·'
+            | '»'
+            | '¿'
+            | '\u{2010}'..='\u{2027}'
+            | '\u{2030}'..='\u{205e}'
+            | '\u{3001}'..='\u{3003}'
+            | '\u{3008}'..='\u{3011}'
+    )
+}
+
+fn split_punctuation(word: &str) -> (&str, &str) {
+    let text = word.trim_end_matches(is_punctuation);
+    (text, &word[text.len()..])
+}
+
+fn is_uri(text: &str) -> bool {
+    text.len() >= 10
+        && ["http://", "https://", "simplex:/"]
+            .iter()
+            .any(|prefix| text.starts_with(prefix))
+}
+
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.rsplit_once('@') else {
+        return false;
+    };
+
+    let local_ok = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c));
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.chars().count() >= 2 && tld.chars().all(char::is_alphabetic));
+
+    local_ok && domain_ok
+}
+
+fn uri_format(uri: &str) -> Format {
+    simplex_link_format(uri).unwrap_or(Format::Uri)
+}
+
+/// Recognizes contact, group and invitation links, in both `simplex:/` and `https://` forms.
+fn simplex_link_format(uri: &str) -> Option<Format> {
+    let link: ConnectionLink = uri.parse().ok()?;
+
+    Some(Format::SimplexLink {
+        link_type: link.link_type,
+        simplex_uri: link.to_simplex_uri(),
+        smp_hosts: link.smp_hosts(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::response::SimplexLinkType;
+
+    fn plain(text: &str) -> FormattedText {
+        FormattedText::plain(text)
+    }
+
+    fn fmt(text: &str, format: Format) -> FormattedText {
+        FormattedText::formatted(text, format)
+    }
+
+    #[test]
+    fn test_plain_text_has_no_markdown_list() {
+        assert_eq!(parse_maybe_markdown_list("hello there, world"), None);
+        assert_eq!(parse_markdown("hello there"), vec![plain("hello there")]);
+    }
+
+    #[test]
+    fn test_simple_formats() {
+        assert_eq!(
+            parse_markdown("this is *bold* and _italic_, ~struck~ `code`"),
+            vec![
+                plain("this is "),
+                fmt("bold", Format::Bold),
+                plain(" and "),
+                fmt("italic", Format::Italic),
+                plain(", "),
+                fmt("struck", Format::StrikeThrough),
+                plain(" "),
+                fmt("code", Format::Snippet),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markers_around_spaces_are_plain() {
+        assert_eq!(parse_maybe_markdown_list("a * b * c"), None);
+        assert_eq!(parse_maybe_markdown_list("2 * 3"), None);
+        assert_eq!(parse_markdown("* not bold*"), vec![plain("* not bold*")]);
+    }
+
+    #[test]
+    fn test_secret() {
+        assert_eq!(
+            parse_markdown("pin: #1234#"),
+            vec![plain("pin: "), fmt("1234", Format::Secret)]
+        );
+        assert_eq!(parse_markdown("#hashtag"), vec![plain("#hashtag")]);
+        assert_eq!(parse_markdown("##x##"), vec![fmt("#x#", Format::Secret)]);
+    }
+
+    #[test]
+    fn test_colored() {
+        let red = Format::Colored {
+            color: FormatColor::Red,
+        };
+        assert_eq!(parse_markdown("!1 alert!"), vec![fmt("alert", red.clone())]);
+        assert_eq!(parse_markdown("!red alert!"), vec![fmt("alert", red)]);
+        assert_eq!(
+            parse_markdown("!5 a b!"),
+            vec![fmt(
+                "a b",
+                Format::Colored {
+                    color: FormatColor::Cyan
+                }
+            )]
+        );
+        assert_eq!(parse_markdown("wow!"), vec![plain("wow!")]);
+    }
+
+    #[test]
+    fn test_links_and_emails() {
+        assert_eq!(
+            parse_markdown("see https://simplex.chat. or mail me@example.com!"),
+            vec![
+                plain("see "),
+                fmt("https://simplex.chat", Format::Uri),
+                plain(". or mail "),
+                fmt("me@example.com", Format::Email),
+                plain("!"),
+            ]
+        );
+        assert_eq!(parse_maybe_markdown_list("http://x"), None);
+        assert_eq!(parse_maybe_markdown_list("me@localhost"), None);
+    }
+
+    #[test]
+    fn test_simplex_links() {
+        let link = "https://simplex.chat/contact#/?v=2-7&smp=smp%3A%2F%2Fu2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU%3D%40smp4.simplex.im%2Cabcdefghij.onion%2Fqueue%23%2F%3Fv%3D1-3%26dh%3DMCo";
+        let items = parse_markdown(link);
+
+        assert_eq!(
+            items,
+            vec![fmt(
+                link,
+                Format::SimplexLink {
+                    link_type: SimplexLinkType::Contact,
+                    simplex_uri: link.replace("https://simplex.chat", "simplex:"),
+                    smp_hosts: vec![
+                        "smp4.simplex.im".to_string(),
+                        "abcdefghij.onion".to_string()
+                    ],
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_mentions() {
+        assert_eq!(
+            parse_markdown("hi @alice, and @'Bob Smith'"),
+            vec![
+                plain("hi "),
+                fmt(
+                    "@alice",
+                    Format::Mention {
+                        member_name: "alice".to_string()
+                    }
+                ),
+                plain(", and "),
+                fmt(
+                    "@'Bob Smith'",
+                    Format::Mention {
+                        member_name: "Bob Smith".to_string()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(parse_maybe_markdown_list("@ alone"), None);
+    }
+
+    #[test]
+    fn test_phones() {
+        assert_eq!(
+            parse_markdown("call +1 (555) 123-4567."),
+            vec![
+                plain("call "),
+                fmt("+1 (555) 123-4567", Format::Phone),
+                plain("."),
+            ]
+        );
+        assert_eq!(parse_maybe_markdown_list("in 2024 we"), None);
+        assert_eq!(parse_maybe_markdown_list("1234567abc"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "*bold* _it_ ~s~ `c` #secret# !3 blue! plain https://example.com";
+        assert_eq!(to_markdown(&parse_markdown(source)), source);
+    }
+
+    #[test]
+    fn test_formatted_text_json() {
+        let json = r#"[{"text":"hi "},{"text":"there","format":{"type":"bold"}},{"text":"x","format":{"type":"colored","color":"green"}},{"text":"@bob","format":{"type":"mention","memberName":"bob"}},{"text":"?","format":{"type":"futureFormat"}}]"#;
+        let items: Vec<FormattedText> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(items[1].format, Some(Format::Bold));
+        assert_eq!(
+            items[2].format,
+            Some(Format::Colored {
+                color: FormatColor::Green
+            })
+        );
+        assert!(matches!(items[4].format, Some(Format::Unknown(_))));
+        assert_eq!(serde_json::to_string(&items).unwrap(), json);
+    }
+}
@@ -0,0 +1,28 @@
+use nucleo::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Config, Matcher,
+};
+
+/// Fuzzy matches `pattern` against `items`, returning the matching items best match first.
+pub fn fuzzy_match<T: AsRef<str>>(pattern: &str, items: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut matcher = Matcher::new(Config::DEFAULT);
+    let matches = Pattern::parse(pattern, CaseMatching::Ignore, Normalization::Smart)
+        .match_list(items, &mut matcher);
+
+    matches.into_iter().map(|(item, _score)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let names = ["alice", "bob", "Alicia Keys", "support team"];
+
+        assert_eq!(fuzzy_match("ali", names), vec!["alice", "Alicia Keys"]);
+        assert_eq!(fuzzy_match("spt", names), vec!["support team"]);
+        assert!(fuzzy_match("zzz", names).is_empty());
+        assert_eq!(fuzzy_match("", names).len(), names.len());
+    }
+}
@@ -4,27 +4,89 @@ use client::{ChatClient, StreamMessage};
 use commands::ChatCommand;
 use error::TransportError;
 use futures::{pin_mut, Stream, StreamExt};
-use response::{ChatInfo, ChatInfoType, ChatResponse, DirectionType};
+use response::{AChatItem, ChatInfo, ChatInfoType, ChatResponse, DirectionType};
 use tokio::{
     self,
     signal::{self},
 };
 
+pub mod archive;
+pub mod broadcast;
 pub mod client;
 pub mod commands;
+pub mod delivery;
 pub mod error;
+pub mod export;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
+pub mod link;
+pub mod markdown;
+pub mod matcher;
+pub mod network;
+pub mod notifications;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod reactions;
 pub mod response;
+pub mod scheduler;
+pub mod servers;
 pub mod utils;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+
+/// Which messages a bot answers.
+#[derive(Clone, Debug, Default)]
+pub struct BotPolicy {
+    /// Ignore messages from contacts and group members whose security code the user has not
+    /// verified, as bots that move money should.
+    pub verified_only: bool,
+}
+
+impl BotPolicy {
+    pub fn allows(&self, item: &AChatItem) -> bool {
+        if !self.verified_only {
+            return true;
+        }
+
+        match &item.chat_info {
+            ChatInfo::Direct(direct) => direct.contact.is_verified(),
+            ChatInfo::Group(_) => item
+                .chat_item
+                .chat_dir
+                .group_member
+                .as_ref()
+                .is_some_and(|member| member.is_verified()),
+            ChatInfo::ContactRequest(_) => false,
+        }
+    }
+}
 
 pub async fn process_message_stream<S>(client: Arc<ChatClient>, message_stream: S)
 where
     S: Stream<Item = StreamMessage> + Unpin,
+{
+    process_message_stream_with_policy(client, message_stream, BotPolicy::default()).await
+}
+
+/// Like [`process_message_stream`], but only answers the messages `policy` allows.
+pub async fn process_message_stream_with_policy<S>(
+    client: Arc<ChatClient>,
+    message_stream: S,
+    policy: BotPolicy,
+) where
+    S: Stream<Item = StreamMessage> + Unpin,
 {
     pin_mut!(message_stream);
     while let Some(response) = message_stream.next().await {
         if let Ok(message) = response {
             if let ChatResponse::NewChatItems { chat_items, .. } = message.resp {
                 for item in chat_items {
+                    if !policy.allows(&item) {
+                        continue;
+                    }
+
                     if let ChatInfo::Direct(c_info_direct) = item.chat_info {
                         if let DirectionType::DirectSnd = item.chat_item.chat_dir.direction_type {
                             continue;
@@ -74,7 +136,31 @@ pub async fn squaring_bot() -> Result<(), TransportError> {
 
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+
     use super::*;
+    use crate::chat::fixtures::{a_chat_item, direct_item, group_item};
+
+    #[test]
+    fn test_bot_policy() {
+        let policy = BotPolicy {
+            verified_only: true,
+        };
+        let mut item = direct_item(2, 1, "directRcv", "4", json!({ "type": "rcvNew" }));
+
+        assert!(BotPolicy::default().allows(&a_chat_item(item.clone())));
+        assert!(!policy.allows(&a_chat_item(item.clone())));
+
+        item["chatInfo"]["contact"]["activeConn"]["connectionCode"] = json!({
+            "securityCode": "12345 67890",
+            "verifiedAt": "2024-12-21T10:00:00Z"
+        });
+        assert!(policy.allows(&a_chat_item(item)));
+
+        // Group messages from members the user has no verified connection with are ignored.
+        let group = group_item(7, 2, "groupRcv", "4", json!({ "type": "rcvNew" }));
+        assert!(!policy.allows(&a_chat_item(group)));
+    }
 
     #[tokio::test]
     async fn test_command() {
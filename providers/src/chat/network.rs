@@ -0,0 +1,263 @@
+//! How simplex-chat reaches relays: the SOCKS proxy, onion hosts, timeouts, and the connection
+//! state of each host as reported by `hostConnected` and `hostDisconnected`.
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::response::ChatResponse;
+
+/// Whether to use the SOCKS proxy for every host or only for onion hosts.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SocksMode {
+    Always,
+    Onion,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// Which of a server's hosts to connect to.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HostMode {
+    /// Onion hosts when a SOCKS proxy is set, public ones otherwise.
+    OnionViaSocks,
+    Onion,
+    Public,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// How connections to the same server are shared.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TransportSessionMode {
+    /// One connection per user and server.
+    User,
+    /// One connection per contact or group and server, so the server cannot link them.
+    Entity,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// TCP keep-alive: idle time and probe interval in seconds, and how many probes to send.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeepAliveOpts {
+    pub keep_idle: u32,
+    pub keep_intvl: u32,
+    pub keep_cnt: u32,
+}
+
+/// The network settings of a running simplex-chat. Settings not typed here are kept in `other`,
+/// so a config read with [`ChatClient::network_config`] can be changed and set back as a whole.
+///
+/// [`ChatClient::network_config`]: super::client::ChatClient::network_config
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkConfig {
+    /// `host:port` of the SOCKS5 proxy, such as Tor's `127.0.0.1:9050`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socks_proxy: Option<String>,
+    pub socks_mode: SocksMode,
+    pub host_mode: HostMode,
+    /// Fail instead of falling back to other hosts when none match `host_mode`.
+    pub required_host_mode: bool,
+    pub session_mode: TransportSessionMode,
+    #[serde(with = "micros")]
+    pub tcp_connect_timeout: Duration,
+    #[serde(with = "micros")]
+    pub tcp_timeout: Duration,
+    /// Added to `tcp_timeout` for every kilobyte sent.
+    #[serde(with = "micros")]
+    pub tcp_timeout_per_kb: Duration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keep_alive: Option<KeepAliveOpts>,
+    #[serde(with = "micros")]
+    pub smp_ping_interval: Duration,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl NetworkConfig {
+    /// Routes every connection through Tor at `proxy`, using onion hosts where servers have them.
+    pub fn via_tor(self, proxy: impl Into<String>) -> Self {
+        NetworkConfig {
+            socks_proxy: Some(proxy.into()),
+            socks_mode: SocksMode::Always,
+            host_mode: HostMode::OnionViaSocks,
+            ..self
+        }
+    }
+}
+
+/// simplex-chat gives durations in microseconds.
+mod micros {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_micros() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_micros(u64::deserialize(deserializer)?))
+    }
+}
+
+/// The last known state of a relay host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostStatus {
+    pub connected: bool,
+    /// When the host last connected or disconnected.
+    pub since: DateTime<Utc>,
+    /// How many times the host disconnected since it was first seen.
+    pub disconnects: u32,
+}
+
+/// Follows the connection state of every relay host simplex-chat talks to.
+///
+/// Feed every response from the message stream to [`HostHealth::observe`]. Hosts only appear once
+/// simplex-chat reports them, so an empty view means nothing has connected yet.
+#[derive(Default)]
+pub struct HostHealth {
+    hosts: Mutex<HashMap<(String, String), HostStatus>>,
+}
+
+impl HostHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&self, response: &ChatResponse) {
+        let (protocol, transport_host, connected) = match response {
+            ChatResponse::HostConnected {
+                protocol,
+                transport_host,
+            } => (protocol, transport_host, true),
+            ChatResponse::HostDisconnected {
+                protocol,
+                transport_host,
+            } => (protocol, transport_host, false),
+            _ => return,
+        };
+
+        let mut hosts = self.hosts.lock().unwrap();
+        let status = hosts
+            .entry((protocol.clone(), transport_host.clone()))
+            .or_insert(HostStatus {
+                connected,
+                since: Utc::now(),
+                disconnects: 0,
+            });
+        if status.connected != connected {
+            status.since = Utc::now();
+        }
+        if !connected {
+            status.disconnects += 1;
+        }
+        status.connected = connected;
+    }
+
+    /// The state of a host, by protocol (`smp`, `xftp`) and host name.
+    pub fn status(&self, protocol: &str, host: &str) -> Option<HostStatus> {
+        let hosts = self.hosts.lock().unwrap();
+        hosts
+            .get(&(protocol.to_string(), host.to_string()))
+            .cloned()
+    }
+
+    /// Every host seen so far with its state, sorted by protocol and host.
+    pub fn snapshot(&self) -> Vec<(String, String, HostStatus)> {
+        let hosts = self.hosts.lock().unwrap();
+        let mut snapshot: Vec<_> = hosts
+            .iter()
+            .map(|((protocol, host), status)| (protocol.clone(), host.clone(), status.clone()))
+            .collect();
+        snapshot.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        snapshot
+    }
+
+    /// Hosts that are currently disconnected.
+    pub fn unreachable(&self) -> Vec<(String, String)> {
+        self.snapshot()
+            .into_iter()
+            .filter(|(_, _, status)| !status.connected)
+            .map(|(protocol, host, _)| (protocol, host))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_network_config() {
+        let json = json!({
+            "socksMode": "always",
+            "hostMode": "publicOnly",
+            "requiredHostMode": false,
+            "sessionMode": "user",
+            "smpProxyMode": "unknown",
+            "tcpConnectTimeout": 25000000,
+            "tcpTimeout": 15000000,
+            "tcpTimeoutPerKb": 10000,
+            "tcpKeepAlive": { "keepIdle": 30, "keepIntvl": 15, "keepCnt": 4 },
+            "smpPingInterval": 600000000,
+            "smpPingCount": 3,
+            "logTLSErrors": false
+        });
+
+        let config: NetworkConfig = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(config.socks_proxy, None);
+        assert_eq!(
+            config.host_mode,
+            HostMode::Unknown("publicOnly".to_string())
+        );
+        assert_eq!(config.tcp_connect_timeout, Duration::from_secs(25));
+        assert_eq!(serde_json::to_value(&config).unwrap(), json);
+
+        let config = config.via_tor("127.0.0.1:9050");
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["socksProxy"], "127.0.0.1:9050");
+        assert_eq!(json["hostMode"], "onionViaSocks");
+        assert_eq!(json["smpPingCount"], 3);
+    }
+
+    #[test]
+    fn test_host_health() {
+        let health = HostHealth::new();
+        let event = |kind: &str, host: &str| -> ChatResponse {
+            serde_json::from_value(json!({
+                "type": kind,
+                "protocol": "smp",
+                "transportHost": host
+            }))
+            .unwrap()
+        };
+
+        health.observe(&event("hostConnected", "smp4.simplex.im"));
+        health.observe(&event("hostConnected", "smp8.simplex.im"));
+        health.observe(&event("hostDisconnected", "smp8.simplex.im"));
+
+        assert!(health.status("smp", "smp4.simplex.im").unwrap().connected);
+        assert_eq!(
+            health.status("smp", "smp8.simplex.im").unwrap().disconnects,
+            1
+        );
+        assert_eq!(
+            health.unreachable(),
+            vec![("smp".to_string(), "smp8.simplex.im".to_string())]
+        );
+
+        health.observe(&event("hostConnected", "smp8.simplex.im"));
+        assert!(health.unreachable().is_empty());
+        assert_eq!(health.snapshot().len(), 2);
+    }
+}
@@ -0,0 +1,399 @@
+//! Which received messages deserve a desktop notification, and what it says.
+//!
+//! Showing the notification is left to a [`Notifier`], so the rules can be tested without a
+//! desktop. Messages that arrive in a chat shortly after one was shown are collapsed into a
+//! single "n more messages" notification instead of one each.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use futures::{pin_mut, Stream, StreamExt};
+
+use super::{
+    client::StreamMessage,
+    response::{
+        AChatItem, CIContent, ChatInfo, ChatInfoType, ChatResponse, ChatSettings, DirectionType,
+        NotificationMode, User,
+    },
+};
+
+/// How long after a notification further messages of the same chat are collapsed.
+pub const BURST_WINDOW: Duration = Duration::from_secs(10);
+
+/// How often [`Notifications::watch`] checks for ended bursts, to report them soon after.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Characters of a message shown in its notification.
+const PREVIEW_LEN: usize = 120;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub chat_type: ChatInfoType,
+    pub chat_id: u64,
+    /// The contact or group name.
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct NotifyError(pub String);
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NotifyError: {}", self.0)
+    }
+}
+
+impl std::error::Error for NotifyError {}
+
+/// Shows notifications, through the OS or anything else.
+pub trait Notifier {
+    fn notify(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+struct Burst {
+    title: String,
+    started: Instant,
+    collapsed: usize,
+}
+
+/// Turns responses from the message stream into notifications.
+///
+/// Call [`Notifications::observe`] with every response, and [`Notifications::flush`]
+/// regularly so collapsed messages are reported once their burst is over.
+pub struct Notifications {
+    window: Duration,
+    bursts: HashMap<(ChatInfoType, u64), Burst>,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self::new(BURST_WINDOW)
+    }
+}
+
+impl Notifications {
+    pub fn new(window: Duration) -> Self {
+        Notifications {
+            window,
+            bursts: HashMap::new(),
+        }
+    }
+
+    /// The notifications to show for a response received at `now`.
+    pub fn observe(&mut self, response: &ChatResponse, now: Instant) -> Vec<Notification> {
+        let mut notifications = self.flush(now);
+
+        let ChatResponse::NewChatItems { user, chat_items } = response else {
+            return notifications;
+        };
+
+        for item in chat_items.iter().filter(|item| should_notify(user, item)) {
+            let key = (item.chat_info.chat_type(), item.chat_info.chat_id());
+            if let Some(burst) = self.bursts.get_mut(&key) {
+                burst.collapsed += 1;
+                continue;
+            }
+
+            let notification = notification_for(item);
+            self.bursts.insert(
+                key,
+                Burst {
+                    title: notification.title.clone(),
+                    started: now,
+                    collapsed: 0,
+                },
+            );
+            notifications.push(notification);
+        }
+
+        notifications
+    }
+
+    /// Ends the bursts that started over a window before `now`, summarizing the messages they
+    /// collapsed.
+    pub fn flush(&mut self, now: Instant) -> Vec<Notification> {
+        let ended: Vec<_> = self
+            .bursts
+            .iter()
+            .filter(|(_, burst)| now.duration_since(burst.started) >= self.window)
+            .map(|(key, _)| *key)
+            .collect();
+
+        let mut notifications = Vec::new();
+        for (chat_type, chat_id) in ended {
+            let Some(burst) = self.bursts.remove(&(chat_type, chat_id)) else {
+                continue;
+            };
+            if burst.collapsed == 0 {
+                continue;
+            }
+
+            notifications.push(Notification {
+                chat_type,
+                chat_id,
+                title: burst.title,
+                body: match burst.collapsed {
+                    1 => "1 more message".to_string(),
+                    n => format!("{} more messages", n),
+                },
+            });
+        }
+
+        notifications
+    }
+
+    /// Shows the notifications for the message stream through `notifier`, until the stream
+    /// ends. Notifications that cannot be shown are passed to `on_error`.
+    pub async fn watch<S>(
+        &mut self,
+        stream: S,
+        notifier: &impl Notifier,
+        mut on_error: impl FnMut(NotifyError),
+    ) where
+        S: Stream<Item = StreamMessage>,
+    {
+        pin_mut!(stream);
+        let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            let shown = tokio::select! {
+                message = stream.next() => match message {
+                    Some(Ok(message)) => self.observe(&message.resp, Instant::now()),
+                    Some(Err(_)) => continue,
+                    None => break,
+                },
+                _ = flush.tick() => self.flush(Instant::now()),
+            };
+
+            for notification in shown {
+                if let Err(e) = notifier.notify(&notification) {
+                    on_error(e);
+                }
+            }
+        }
+    }
+}
+
+fn should_notify(user: &User, item: &AChatItem) -> bool {
+    if !user.show_ntfs {
+        return false;
+    }
+
+    let received = matches!(
+        item.chat_item.chat_dir.direction_type,
+        DirectionType::DirectRcv | DirectionType::GroupRcv
+    );
+    if !received || !matches!(item.chat_item.content, CIContent::RcvMsgContent(_)) {
+        return false;
+    }
+
+    let settings: Option<&ChatSettings> = match &item.chat_info {
+        ChatInfo::Direct(direct) => Some(&direct.contact.chat_settings),
+        ChatInfo::Group(group) => group.group_info.chat_settings.as_ref(),
+        ChatInfo::ContactRequest(_) => return false,
+    };
+
+    match settings.map(|settings| &settings.enable_ntfs) {
+        Some(NotificationMode::None) => false,
+        // Direct messages are all addressed to the user.
+        Some(NotificationMode::Mentions) => {
+            item.chat_info.chat_type() == ChatInfoType::Direct || item.chat_item.meta.user_mention
+        }
+        _ => true,
+    }
+}
+
+fn notification_for(item: &AChatItem) -> Notification {
+    let text = item.chat_item.content.summary_text();
+    let mut preview: String = text.chars().take(PREVIEW_LEN).collect();
+    if text.chars().count() > PREVIEW_LEN {
+        preview.push('…');
+    }
+
+    let sender = item
+        .chat_item
+        .chat_dir
+        .group_member
+        .as_ref()
+        .map(|member| member.local_display_name.as_str())
+        .filter(|name| !name.is_empty());
+
+    Notification {
+        chat_type: item.chat_info.chat_type(),
+        chat_id: item.chat_info.chat_id(),
+        title: item.chat_info.display_name().to_string(),
+        body: match sender {
+            Some(sender) => format!("{}: {}", sender, preview),
+            None => preview,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures::stream;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::{
+        fixtures::{direct_item, group_item, response, user},
+        response::ServerResponse,
+    };
+
+    /// Keeps the notifications it is asked to show, failing for the contact with ID 3.
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Notification>>);
+
+    impl Notifier for Recorder {
+        fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+            if notification.chat_id == 3 {
+                return Err(NotifyError("no desktop".to_string()));
+            }
+            self.0.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    fn new_items(items: Vec<Value>) -> ChatResponse {
+        response(json!({ "type": "newChatItems", "user": user(), "chatItems": items }))
+    }
+
+    fn received(contact_id: u64, item_id: u64, text: &str) -> Value {
+        direct_item(
+            contact_id,
+            item_id,
+            "directRcv",
+            text,
+            json!({ "type": "rcvNew" }),
+        )
+    }
+
+    #[test]
+    fn test_notify_and_collapse() {
+        let mut notifications = Notifications::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        let shown = notifications.observe(
+            &new_items(vec![
+                received(2, 1, "hi"),
+                received(2, 2, "are you there?"),
+                direct_item(2, 3, "directSnd", "yes", json!({ "type": "sndNew" })),
+            ]),
+            start,
+        );
+        assert_eq!(
+            shown,
+            vec![Notification {
+                chat_type: ChatInfoType::Direct,
+                chat_id: 2,
+                title: "alice".to_string(),
+                body: "hi".to_string(),
+            }]
+        );
+
+        let later = start + Duration::from_secs(3);
+        assert!(notifications
+            .observe(&new_items(vec![received(2, 4, "hello?")]), later)
+            .is_empty());
+        assert!(notifications.flush(later).is_empty());
+
+        let summary = notifications.flush(start + Duration::from_secs(10));
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].body, "2 more messages");
+
+        let long = "x".repeat(200);
+        let shown = notifications.observe(
+            &new_items(vec![received(2, 5, &long)]),
+            start + Duration::from_secs(11),
+        );
+        assert_eq!(shown[0].body.chars().count(), PREVIEW_LEN + 1);
+    }
+
+    #[test]
+    fn test_mute_settings() {
+        let mut notifications = Notifications::default();
+        let now = Instant::now();
+
+        let mut muted = received(2, 1, "hi");
+        muted["chatInfo"]["contact"]["chatSettings"]["enableNtfs"] = json!("none");
+        assert!(notifications
+            .observe(&new_items(vec![muted]), now)
+            .is_empty());
+
+        let mut quiet_user = user();
+        quiet_user["showNtfs"] = json!(false);
+        let quiet = response(json!({
+            "type": "newChatItems",
+            "user": quiet_user,
+            "chatItems": [received(3, 2, "hi")]
+        }));
+        assert!(notifications.observe(&quiet, now).is_empty());
+
+        let member_item = |item_id: u64, mention: bool| {
+            let mut item = group_item(
+                7,
+                item_id,
+                "groupRcv",
+                "lunch?",
+                json!({ "type": "rcvNew" }),
+            );
+            item["chatInfo"]["groupInfo"]["chatSettings"] =
+                json!({ "enableNtfs": "mentions", "favorite": false });
+            item["chatItem"]["chatDir"]["groupMember"] = json!({
+                "groupMemberId": 3,
+                "memberId": "Ym9i",
+                "memberRole": "member",
+                "localDisplayName": "bob"
+            });
+            item["chatItem"]["meta"]["userMention"] = json!(mention);
+            item
+        };
+        assert!(notifications
+            .observe(&new_items(vec![member_item(3, false)]), now)
+            .is_empty());
+
+        let shown = notifications.observe(&new_items(vec![member_item(4, true)]), now);
+        assert_eq!(shown[0].title, "team");
+        assert_eq!(shown[0].body, "bob: lunch?");
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let mut voice = received(2, 1, "");
+        voice["chatItem"]["content"]["msgContent"] =
+            json!({ "type": "voice", "text": "", "duration": 65 });
+        let messages = [
+            vec![voice],
+            vec![received(3, 2, "hi")],
+            vec![received(4, 3, "hello")],
+        ]
+        .map(|items| {
+            Ok(ServerResponse {
+                corr_id: None,
+                resp: new_items(items),
+            })
+        });
+
+        let recorder = Recorder::default();
+        let mut errors = Vec::new();
+        Notifications::default()
+            .watch(stream::iter(messages), &recorder, |e| errors.push(e))
+            .await;
+
+        let shown = recorder.0.into_inner().unwrap();
+        assert_eq!(
+            shown
+                .iter()
+                .map(|notification| notification.body.as_str())
+                .collect::<Vec<_>>(),
+            ["[voice message 1:05]", "hello"]
+        );
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+//! OpenAPI schemas for the simplex-chat types, for services that serve them.
+//!
+//! With the `openapi` feature, the types in [`response`](super::response) derive
+//! [`ToSchema`](utoipa::ToSchema). Their `Unknown` fallbacks keep values added by newer
+//! simplex-chat versions, but are `#[serde(untagged)]` variants that utoipa documents as tagged
+//! ones. Add [`UnknownVariants`] to the document's modifiers to describe them as they are sent.
+
+use serde_json::json;
+use utoipa::{
+    openapi::{
+        schema::{Object, Schema, Type},
+        OpenApi, RefOr,
+    },
+    Modify,
+};
+
+/// Rewrites the `Unknown` fallback of every enum schema: any other string for plain enums, and
+/// an object with any other `type` for tagged ones.
+pub struct UnknownVariants;
+
+impl Modify for UnknownVariants {
+    fn modify(&self, openapi: &mut OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.schemas.values_mut().for_each(rewrite_unknown);
+        }
+    }
+}
+
+fn rewrite_unknown(schema: &mut RefOr<Schema>) {
+    let RefOr::T(Schema::OneOf(one_of)) = schema else {
+        return;
+    };
+
+    for variant in &mut one_of.items {
+        match variant {
+            // `Unknown(String)`, documented as `{ "unknown": "..." }`.
+            RefOr::T(Schema::Object(object)) if object.required == ["unknown"] => {
+                *variant = Object::with_type(Type::String).into();
+            }
+            // `Unknown(serde_json::Value)`, documented with a `type` of `unknown`.
+            RefOr::T(Schema::Object(object)) => open_tag(object),
+            // `Unknown(MCUnknown)`, the same but combined with the fields it keeps.
+            RefOr::T(Schema::AllOf(all_of)) => {
+                for part in &mut all_of.items {
+                    if let RefOr::T(Schema::Object(object)) = part {
+                        open_tag(object);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Lets the `type` of a tagged variant documented as `unknown` be any string.
+fn open_tag(object: &mut Object) {
+    if let Some(RefOr::T(Schema::Object(tag))) = object.properties.get_mut("type") {
+        if tag.enum_values.as_deref() == Some(&[json!("unknown")]) {
+            tag.enum_values = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use utoipa::OpenApi;
+
+    use super::*;
+    use crate::chat::response::{CIContent, MsgContent, NotificationMode, SndError};
+
+    #[derive(OpenApi)]
+    #[openapi(
+        components(schemas(NotificationMode, CIContent, MsgContent, SndError)),
+        modifiers(&UnknownVariants)
+    )]
+    struct Doc;
+
+    #[test]
+    fn test_unknown_variants() {
+        let doc = serde_json::to_value(Doc::openapi()).unwrap();
+        let schemas = &doc["components"]["schemas"];
+
+        let modes = schemas["NotificationMode"]["oneOf"].as_array().unwrap();
+        assert_eq!(modes[0], json!({ "type": "string", "enum": ["all"] }));
+        assert_eq!(modes.last().unwrap(), &json!({ "type": "string" }));
+
+        let contents = schemas["CIContent"]["oneOf"].as_array().unwrap();
+        let unknown = contents.last().unwrap();
+        assert_eq!(unknown["properties"]["type"], json!({ "type": "string" }));
+        assert!(!doc.to_string().contains(r#"["unknown"]"#));
+    }
+}
@@ -0,0 +1,320 @@
+//! QR codes for contact addresses and invitations, so links can be shown on one screen and
+//! scanned from another. Everything happens offline.
+
+use std::{
+    fmt, io,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+
+use qrcode::{
+    render::{svg, unicode},
+    Color, EcLevel, QrCode,
+};
+
+use super::link::{ConnectionLink, LinkError};
+
+/// Light modules around the code, as the standard asks for so scanners can find it.
+const QUIET_ZONE: usize = 4;
+
+/// The shortest side images are enlarged to before decoding, as codes with modules a pixel or
+/// two wide cannot be measured.
+const MIN_DECODE_SIDE: usize = 400;
+
+#[derive(Debug)]
+pub enum QrError {
+    /// The text is too long for a QR code.
+    Encode(String),
+    Image(String),
+    /// There is no QR code in the image.
+    NotFound,
+    /// The image has a QR code that could not be read.
+    Decode(String),
+    /// The QR code does not hold a SimpleX link.
+    Link(LinkError),
+    Io(String),
+}
+
+impl From<qrcode::types::QrError> for QrError {
+    fn from(err: qrcode::types::QrError) -> Self {
+        QrError::Encode(err.to_string())
+    }
+}
+
+impl From<png::DecodingError> for QrError {
+    fn from(err: png::DecodingError) -> Self {
+        QrError::Image(err.to_string())
+    }
+}
+
+impl From<png::EncodingError> for QrError {
+    fn from(err: png::EncodingError) -> Self {
+        QrError::Image(err.to_string())
+    }
+}
+
+impl From<LinkError> for QrError {
+    fn from(err: LinkError) -> Self {
+        QrError::Link(err)
+    }
+}
+
+impl From<io::Error> for QrError {
+    fn from(err: io::Error) -> Self {
+        QrError::Io(err.to_string())
+    }
+}
+
+impl fmt::Display for QrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QrError::Encode(msg) => write!(f, "QrError: cannot encode: {}", msg),
+            QrError::Image(msg) => write!(f, "QrError: invalid image: {}", msg),
+            QrError::NotFound => write!(f, "QrError: no QR code found"),
+            QrError::Decode(msg) => write!(f, "QrError: cannot read the code: {}", msg),
+            QrError::Link(err) => write!(f, "QrError: {}", err),
+            QrError::Io(msg) => write!(f, "QrError: io error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for QrError {}
+
+fn encode(text: &str) -> Result<QrCode, QrError> {
+    Ok(QrCode::with_error_correction_level(
+        text.as_bytes(),
+        EcLevel::M,
+    )?)
+}
+
+/// An SVG image of the code, black on white.
+pub fn to_svg(text: &str) -> Result<String, QrError> {
+    Ok(encode(text)?
+        .render::<svg::Color>()
+        .min_dimensions(256, 256)
+        .build())
+}
+
+/// A grayscale PNG of the code, with each module `scale` pixels wide.
+pub fn to_png(text: &str, scale: u32) -> Result<Vec<u8>, QrError> {
+    let (pixels, side) = rasterize(&encode(text)?, scale.max(1) as usize);
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+
+    Ok(png)
+}
+
+/// The code drawn with half block characters, two modules per line. Light modules are the
+/// drawn ones, for terminals with dark backgrounds.
+pub fn to_terminal(text: &str) -> Result<String, QrError> {
+    Ok(encode(text)?
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
+
+/// Reads the text of the QR code in a PNG image. The code can be anywhere in it, as in a
+/// screenshot of an app showing it.
+pub fn decode_png(png: &[u8]) -> Result<String, QrError> {
+    let mut decoder = png::Decoder::new(png);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer)?;
+    let (width, height) = (frame.width as usize, frame.height as usize);
+
+    let channels = frame.color_type.samples();
+    let luma: Vec<u8> = (0..width * height)
+        .map(|i| {
+            let start = (i / width) * frame.line_size + (i % width) * channels;
+            to_luma(&buffer[start..start + channels])
+        })
+        .collect();
+
+    decode_luma(&luma, width, height)
+}
+
+/// Reads the SimpleX link in a PNG image of a QR code.
+pub fn scan_link(path: impl AsRef<Path>) -> Result<ConnectionLink, QrError> {
+    Ok(decode_png(&std::fs::read(path)?)?.parse()?)
+}
+
+fn decode_luma(luma: &[u8], width: usize, height: usize) -> Result<String, QrError> {
+    let factor = MIN_DECODE_SIDE.div_ceil(width.min(height).max(1));
+    let mut image =
+        rqrr::PreparedImage::prepare_from_greyscale(width * factor, height * factor, |x, y| {
+            luma[y / factor * width + x / factor]
+        });
+
+    // rqrr panics on some codes it cannot measure instead of returning an error.
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut error = QrError::NotFound;
+        for grid in image.detect_grids() {
+            match grid.decode() {
+                Ok((_, text)) => return Ok(text),
+                Err(e) => error = QrError::Decode(e.to_string()),
+            }
+        }
+        Err(error)
+    }))
+    .unwrap_or_else(|_| {
+        Err(QrError::Decode(
+            "the code could not be measured".to_string(),
+        ))
+    })
+}
+
+/// Converts a gray, gray and alpha, RGB or RGBA pixel to gray over a white background.
+fn to_luma(pixel: &[u8]) -> u8 {
+    let (gray, alpha) = match *pixel {
+        [gray] => (gray as u32, 255),
+        [gray, alpha] => (gray as u32, alpha as u32),
+        [r, g, b] => (
+            (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000,
+            255,
+        ),
+        [r, g, b, alpha, ..] => (
+            (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000,
+            alpha as u32,
+        ),
+        [] => (255, 255),
+    };
+
+    ((gray * alpha + 255 * (255 - alpha)) / 255) as u8
+}
+
+/// Grayscale pixels of the code with its quiet zone, and the side of the square they form.
+fn rasterize(code: &QrCode, scale: usize) -> (Vec<u8>, usize) {
+    let width = code.width();
+    let colors = code.to_colors();
+    let side = (width + 2 * QUIET_ZONE) * scale;
+
+    let pixels = (0..side * side)
+        .map(|i| {
+            let col = (i % side / scale).checked_sub(QUIET_ZONE);
+            let row = (i / side / scale).checked_sub(QUIET_ZONE);
+            match (row, col) {
+                (Some(row), Some(col))
+                    if row < width && col < width && colors[row * width + col] == Color::Dark =>
+                {
+                    0
+                }
+                _ => 255,
+            }
+        })
+        .collect();
+
+    (pixels, side)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK: &str = "simplex:/contact#/?v=2-7&smp=smp%3A%2F%2Fu2dS9sG8nMNURyZwqASV4yROM28Er0luVTx5X1CsMrU%3D%40smp4.simplex.im%2Cabcdefghij.onion%2Fqueue%23%2F%3Fv%3D1-3%26dh%3DMCo";
+
+    #[test]
+    fn test_png_round_trip() {
+        for scale in [1, 3, 8] {
+            let png = to_png(LINK, scale).unwrap();
+            assert_eq!(decode_png(&png).unwrap(), LINK);
+        }
+    }
+
+    #[test]
+    fn test_versions_and_levels() {
+        for level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            for length in [5, 40, 150, 400, 900, 2000] {
+                let text: String = LINK.chars().cycle().take(length).collect();
+                let Ok(code) = QrCode::with_error_correction_level(text.as_bytes(), level) else {
+                    continue;
+                };
+
+                let (pixels, side) = rasterize(&code, 2);
+                assert_eq!(
+                    decode_luma(&pixels, side, side).unwrap(),
+                    text,
+                    "{:?} {:?}",
+                    level,
+                    code.version()
+                );
+            }
+        }
+
+        // Numeric and alphanumeric segments.
+        for text in ["0123456789", "HTTPS://SIMPLEX.CHAT/CONTACT 42"] {
+            let (pixels, side) = rasterize(&encode(text).unwrap(), 2);
+            assert_eq!(decode_luma(&pixels, side, side).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_damaged_and_scaled() {
+        let code = encode(LINK).unwrap();
+        let (mut pixels, side) = rasterize(&code, 3);
+
+        // A smudge over a few modules of data is corrected.
+        let middle = side / 2;
+        for y in middle..middle + 6 {
+            for x in middle..middle + 6 {
+                pixels[y * side + x] = 255 - pixels[y * side + x];
+            }
+        }
+
+        // Screenshots are rarely scaled by whole pixels.
+        let scaled_side = side * 5 / 3;
+        let scaled: Vec<u8> = (0..scaled_side * scaled_side)
+            .map(|i| {
+                let (x, y) = (i % scaled_side * 3 / 5, i / scaled_side * 3 / 5);
+                pixels[y * side + x]
+            })
+            .collect();
+
+        assert_eq!(
+            decode_luma(&scaled, scaled_side, scaled_side).unwrap(),
+            LINK
+        );
+    }
+
+    #[test]
+    fn test_in_screenshot() {
+        let (code, side) = rasterize(&encode(LINK).unwrap(), 3);
+        let (width, height) = (side * 3, side * 2);
+        let mut pixels = vec![255; width * height];
+
+        // A dark frame, a title bar and lines of text around the code.
+        for y in 0..height {
+            for x in 0..width {
+                let frame = x < 6 || y < 6 || x >= width - 6 || y >= height - 6;
+                let title = y < side / 4;
+                let text = x > side * 2 && (y / 9) % 2 == 0 && (x / 5) % 4 != 0;
+                if frame || title || text {
+                    pixels[y * width + x] = 30;
+                }
+            }
+        }
+        let (left, top) = (side / 2, side / 2);
+        for y in 0..side {
+            pixels[(top + y) * width + left..][..side].copy_from_slice(&code[y * side..][..side]);
+        }
+
+        assert_eq!(decode_luma(&pixels, width, height).unwrap(), LINK);
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert!(matches!(
+            decode_luma(&[255; 100], 10, 10),
+            Err(QrError::NotFound)
+        ));
+        assert!(to_terminal(LINK).unwrap().lines().count() > 20);
+        assert!(to_svg(LINK).unwrap().starts_with("<?xml"));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use super::response::{CIReactionCount, ChatItem, ChatItemId, ChatResponse, MsgReaction};
+
+/// Keeps the current reaction counts of chat items, updated from the message stream.
+///
+/// simplex-chat sends the full, recounted reaction list of an item with every reaction change, so
+/// the view never drifts from what the chat database holds, even if events are missed.
+#[derive(Debug, Default)]
+pub struct ReactionView {
+    items: HashMap<ChatItemId, Vec<CIReactionCount>>,
+}
+
+impl ReactionView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the view from a response, returning the item whose reactions changed.
+    pub fn observe(&mut self, response: &ChatResponse) -> Option<ChatItemId> {
+        match response {
+            ChatResponse::ChatItemReaction { reaction, .. } => {
+                let chat_item = &reaction.chat_reaction.chat_item;
+                self.track(chat_item);
+                Some(chat_item.meta.item_id)
+            }
+            ChatResponse::NewChatItems { chat_items, .. } => {
+                for item in chat_items {
+                    if !item.chat_item.reactions.is_empty() {
+                        self.track(&item.chat_item);
+                    }
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Starts following an item, such as a poll the caller just sent, using its current counts.
+    pub fn track(&mut self, item: &ChatItem) {
+        self.items.insert(item.meta.item_id, item.reactions.clone());
+    }
+
+    pub fn forget(&mut self, item_id: ChatItemId) {
+        self.items.remove(&item_id);
+    }
+
+    pub fn reactions(&self, item_id: ChatItemId) -> &[CIReactionCount] {
+        self.items.get(&item_id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn count(&self, item_id: ChatItemId, reaction: &MsgReaction) -> u32 {
+        self.find(item_id, reaction)
+            .map_or(0, |count| count.total_reacted)
+    }
+
+    pub fn user_reacted(&self, item_id: ChatItemId, reaction: &MsgReaction) -> bool {
+        self.find(item_id, reaction)
+            .is_some_and(|count| count.user_reacted)
+    }
+
+    /// Emoji reactions of an item with their counts, most popular first. Ties keep the order in
+    /// which simplex-chat lists them.
+    pub fn tally(&self, item_id: ChatItemId) -> Vec<(&str, u32)> {
+        let mut tally: Vec<(&str, u32)> = self
+            .reactions(item_id)
+            .iter()
+            .filter_map(|count| Some((count.reaction.as_emoji()?, count.total_reacted)))
+            .filter(|(_, total)| *total > 0)
+            .collect();
+
+        tally.sort_by_key(|(_, total)| Reverse(*total));
+        tally
+    }
+
+    fn find(&self, item_id: ChatItemId, reaction: &MsgReaction) -> Option<&CIReactionCount> {
+        self.reactions(item_id)
+            .iter()
+            .find(|count| count.reaction == *reaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::fixtures::{chat_item, contact, response, text_content, user};
+
+    fn reaction_event(item_id: u64, added: bool, emoji: &str, reactions: Value) -> ChatResponse {
+        let mut item = chat_item(
+            item_id,
+            "directSnd",
+            text_content("directSnd", "lunch at noon?"),
+            json!({ "type": "sndSent", "sndProgress": "complete" }),
+        );
+        item["reactions"] = reactions;
+
+        response(json!({
+            "type": "chatItemReaction",
+            "user": user(),
+            "added": added,
+            "reaction": {
+                "chatInfo": { "type": "direct", "contact": contact(2, "alice") },
+                "chatReaction": {
+                    "chatDir": { "type": "directRcv" },
+                    "chatItem": item,
+                    "sentAt": "2024-12-20T10:31:00Z",
+                    "reaction": { "type": "emoji", "emoji": emoji }
+                }
+            }
+        }))
+    }
+
+    #[test]
+    fn test_reaction_events_replace_counts() {
+        let mut view = ReactionView::new();
+        let thumbs_up = MsgReaction::emoji("👍");
+
+        let changed = view.observe(&reaction_event(
+            5,
+            true,
+            "👍",
+            json!([
+                { "reaction": { "type": "emoji", "emoji": "👍" }, "userReacted": false, "totalReacted": 1 }
+            ]),
+        ));
+        assert_eq!(changed, Some(5));
+        assert_eq!(view.count(5, &thumbs_up), 1);
+
+        view.observe(&reaction_event(
+            5,
+            true,
+            "🚀",
+            json!([
+                { "reaction": { "type": "emoji", "emoji": "👍" }, "userReacted": true, "totalReacted": 2 },
+                { "reaction": { "type": "emoji", "emoji": "🚀" }, "userReacted": false, "totalReacted": 3 },
+                { "reaction": { "type": "custom", "id": 1 }, "userReacted": false, "totalReacted": 1 }
+            ]),
+        ));
+        assert!(view.user_reacted(5, &thumbs_up));
+        assert_eq!(view.tally(5), vec![("🚀", 3), ("👍", 2)]);
+
+        view.observe(&reaction_event(5, false, "🚀", json!([])));
+        assert!(view.tally(5).is_empty());
+        assert_eq!(view.count(6, &thumbs_up), 0);
+    }
+}
@@ -1,7 +1,13 @@
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::network::NetworkConfig;
+use super::servers::{ProtocolTestFailure, ServerAddress, UserProtoServers};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct LocalProfile {
     #[serde(rename = "profileId")]
     pub profile_id: u64,
@@ -9,10 +15,33 @@ pub struct LocalProfile {
     pub display_name: String,
     #[serde(rename = "fullName")]
     pub full_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(
+        rename = "contactLink",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub contact_link: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferences: Option<Box<Preferences>>,
     #[serde(rename = "localAlias")]
     pub local_alias: String,
 }
 
+impl LocalProfile {
+    /// The profile as contacts see it, without the local alias.
+    pub fn to_profile(&self) -> Profile {
+        Profile {
+            display_name: self.display_name.clone(),
+            full_name: self.full_name.clone(),
+            image: self.image.clone(),
+            contact_link: self.contact_link.clone(),
+            preferences: self.preferences.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     #[serde(rename = "userId")]
@@ -135,6 +164,12 @@ pub enum ChatResponse {
     #[serde(rename = "acceptingContactRequest", rename_all = "camelCase")]
     AcceptingContactRequest { user: User, contact: Contact },
 
+    #[serde(rename = "contactRequestRejected", rename_all = "camelCase")]
+    ContactRequestRejected {
+        user: User,
+        contact_request: UserContactRequest,
+    },
+
     #[serde(rename = "contactSndReady", rename_all = "camelCase")]
     ContactSndReady { user: User, contact: Contact },
 
@@ -146,6 +181,236 @@ pub enum ChatResponse {
         user: User,
         chat_items: Vec<AChatItem>,
     },
+
+    #[serde(rename = "chatItemsStatusesUpdated", rename_all = "camelCase")]
+    ChatItemsStatusesUpdated {
+        user: User,
+        chat_items: Vec<AChatItem>,
+    },
+
+    #[serde(rename = "chatItemStatusUpdated", rename_all = "camelCase")]
+    ChatItemStatusUpdated {
+        user: User,
+        chat_item: Box<AChatItem>,
+    },
+
+    /// An edit that left the item as it was.
+    #[serde(rename = "chatItemNotChanged", rename_all = "camelCase")]
+    ChatItemNotChanged {
+        user: User,
+        chat_item: Box<AChatItem>,
+    },
+
+    #[serde(rename = "chatItemUpdated", rename_all = "camelCase")]
+    ChatItemUpdated {
+        user: User,
+        chat_item: Box<AChatItem>,
+    },
+
+    /// A received file finished downloading.
+    #[serde(rename = "rcvFileComplete", rename_all = "camelCase")]
+    RcvFileComplete {
+        user: User,
+        chat_item: Box<AChatItem>,
+    },
+
+    #[serde(rename = "chatItemsDeleted", rename_all = "camelCase")]
+    ChatItemsDeleted {
+        user: User,
+        chat_item_deletions: Vec<ChatItemDeletion>,
+        by_user: bool,
+        #[serde(default)]
+        timed: bool,
+    },
+
+    #[serde(rename = "chatItemDeleted", rename_all = "camelCase")]
+    ChatItemDeleted {
+        user: User,
+        deleted_chat_item: Box<AChatItem>,
+        to_chat_item: Option<Box<AChatItem>>,
+        by_user: bool,
+    },
+
+    #[serde(rename = "contactUpdated", rename_all = "camelCase")]
+    ContactUpdated {
+        user: User,
+        from_contact: Box<Contact>,
+        to_contact: Box<Contact>,
+    },
+
+    #[serde(rename = "contactDeletedByContact", rename_all = "camelCase")]
+    ContactDeletedByContact { user: User, contact: Contact },
+
+    #[serde(rename = "groupUpdated", rename_all = "camelCase")]
+    GroupUpdated {
+        user: User,
+        from_group: Box<GroupInfo>,
+        to_group: Box<GroupInfo>,
+    },
+
+    #[serde(rename = "chatItemReaction", rename_all = "camelCase")]
+    ChatItemReaction {
+        user: User,
+        added: bool,
+        reaction: Box<ACIReaction>,
+    },
+
+    #[serde(rename = "apiParsedMarkdown", rename_all = "camelCase")]
+    ApiParsedMarkdown {
+        formatted_text: Option<Vec<FormattedText>>,
+    },
+
+    #[serde(rename = "apiChats", rename_all = "camelCase")]
+    ApiChats { user: User, chats: Vec<Chat> },
+    #[serde(rename = "apiChat", rename_all = "camelCase")]
+    ApiChat { user: User, chat: Box<Chat> },
+
+    #[serde(rename = "chatStarted")]
+    ChatStarted,
+    #[serde(rename = "chatRunning")]
+    ChatRunning,
+    #[serde(rename = "chatStopped")]
+    ChatStopped,
+
+    #[serde(rename = "cmdOk", rename_all = "camelCase")]
+    CmdOk {
+        #[serde(rename = "user_", default)]
+        user: Option<User>,
+    },
+
+    #[serde(rename = "contactsList", rename_all = "camelCase")]
+    ContactsList { user: User, contacts: Vec<Contact> },
+    #[serde(rename = "groupsList", rename_all = "camelCase")]
+    GroupsList {
+        user: User,
+        groups: Vec<GroupInfoSummary>,
+    },
+
+    #[serde(rename = "groupMembers", rename_all = "camelCase")]
+    GroupMembers { user: User, group: Group },
+
+    #[serde(rename = "chatTags", rename_all = "camelCase")]
+    ChatTags { user: User, user_tags: Vec<ChatTag> },
+
+    #[serde(rename = "userProfileUpdated", rename_all = "camelCase")]
+    UserProfileUpdated {
+        user: User,
+        from_profile: Profile,
+        to_profile: Profile,
+    },
+    #[serde(rename = "userProfileNoChange", rename_all = "camelCase")]
+    UserProfileNoChange { user: User },
+    #[serde(rename = "contactPrefsUpdated", rename_all = "camelCase")]
+    ContactPrefsUpdated { user: User, to_contact: Contact },
+    #[serde(rename = "contactAliasUpdated", rename_all = "camelCase")]
+    ContactAliasUpdated { user: User, to_contact: Contact },
+
+    #[serde(rename = "chatCleared", rename_all = "camelCase")]
+    ChatCleared { user: User, chat_info: ChatInfo },
+    #[serde(rename = "contactDeleted", rename_all = "camelCase")]
+    ContactDeleted { user: User, contact: Contact },
+    #[serde(rename = "groupDeletedUser", rename_all = "camelCase")]
+    GroupDeletedUser { user: User, group_info: GroupInfo },
+
+    #[serde(rename = "chatItemTTL", rename_all = "camelCase")]
+    ChatItemTTL {
+        user: User,
+        #[serde(rename = "chatItemTTL", default)]
+        chat_item_ttl: Option<u64>,
+    },
+
+    #[serde(rename = "invitation", rename_all = "camelCase")]
+    Invitation {
+        user: User,
+        conn_req_invitation: String,
+        connection: PendingContactConnection,
+    },
+    #[serde(rename = "connectionIncognitoUpdated", rename_all = "camelCase")]
+    ConnectionIncognitoUpdated {
+        user: User,
+        to_connection: PendingContactConnection,
+    },
+    #[serde(rename = "contactInfo", rename_all = "camelCase")]
+    ContactInfo {
+        user: User,
+        contact: Contact,
+        #[serde(default)]
+        custom_user_profile: Option<Profile>,
+    },
+    #[serde(rename = "sentConfirmation", rename_all = "camelCase")]
+    SentConfirmation {
+        user: User,
+        connection: PendingContactConnection,
+    },
+    #[serde(rename = "sentInvitation", rename_all = "camelCase")]
+    SentInvitation {
+        user: User,
+        connection: PendingContactConnection,
+    },
+    #[serde(rename = "contactAlreadyExists", rename_all = "camelCase")]
+    ContactAlreadyExists { user: User, contact: Contact },
+
+    #[serde(rename = "contactCode", rename_all = "camelCase")]
+    ContactCode {
+        user: User,
+        contact: Contact,
+        connection_code: String,
+    },
+    #[serde(rename = "groupMemberCode", rename_all = "camelCase")]
+    GroupMemberCode {
+        user: User,
+        group_info: GroupInfo,
+        member: GroupMember,
+        connection_code: String,
+    },
+    #[serde(rename = "connectionVerified", rename_all = "camelCase")]
+    ConnectionVerified {
+        user: User,
+        verified: bool,
+        expected_code: String,
+    },
+
+    #[serde(rename = "userProtoServers", rename_all = "camelCase")]
+    UserProtoServers {
+        user: User,
+        servers: UserProtoServers,
+    },
+    #[serde(rename = "serverTestResult", rename_all = "camelCase")]
+    ServerTestResult {
+        user: User,
+        test_server: ServerAddress,
+        test_failure: Option<ProtocolTestFailure>,
+    },
+
+    #[serde(rename = "networkConfig", rename_all = "camelCase")]
+    NetworkConfig { network_config: NetworkConfig },
+    #[serde(rename = "hostConnected", rename_all = "camelCase")]
+    HostConnected {
+        protocol: String,
+        transport_host: String,
+    },
+    #[serde(rename = "hostDisconnected", rename_all = "camelCase")]
+    HostDisconnected {
+        protocol: String,
+        transport_host: String,
+    },
+
+    #[serde(rename = "archiveExported", rename_all = "camelCase")]
+    ArchiveExported { archive_errors: Vec<ArchiveError> },
+    #[serde(rename = "archiveImported", rename_all = "camelCase")]
+    ArchiveImported { archive_errors: Vec<ArchiveError> },
+}
+
+/// A problem with a single part of a database archive. The rest of the archive is still used.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ArchiveError {
+    #[serde(rename_all = "camelCase")]
+    Import { import_error: String },
+    #[serde(rename_all = "camelCase")]
+    ImportFile { file: String, file_error: String },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -155,18 +420,53 @@ pub struct PendingSubStatus {}
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct MemberSubStatus {}
 
+/// A group with its members, not including the user.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Group {
+    pub group_info: GroupInfo,
+    pub members: Vec<GroupMember>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct GroupMember {
-    group_member_id: u64,
-    member_id: String,
-    member_role: GroupMemberRole,
+    pub group_member_id: u64,
+    pub member_id: String,
+    pub member_role: GroupMemberRole,
+    #[serde(default)]
+    pub local_display_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_profile: Option<LocalProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_contact_id: Option<u64>,
+    /// Missing for members the user is not connected to directly, like those who joined after
+    /// them in a large group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_conn: Option<Connection>,
+}
+
+impl GroupMember {
+    /// Whether the user compared security codes with the member.
+    pub fn is_verified(&self) -> bool {
+        self.active_conn
+            .as_ref()
+            .is_some_and(Connection::is_verified)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum GroupMemberRole {
+    #[serde(rename = "observer")]
+    Observer,
+    #[serde(rename = "author")]
+    Author,
     #[serde(rename = "member")]
     Member,
+    #[serde(rename = "moderator")]
+    Moderator,
     #[serde(rename = "admin")]
     Admin,
     #[serde(rename = "owner")]
@@ -174,6 +474,7 @@ pub enum GroupMemberRole {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct FullPreferences {
     pub timed_messages: PreferenceSettings,
@@ -203,6 +504,7 @@ pub struct TimedMessages {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct UserPreference {
     #[serde(rename = "type")]
@@ -211,45 +513,75 @@ pub struct UserPreference {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct EnabledSettings {
     pub for_user: bool,
     pub for_contact: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FullDelete {
-    pub allow: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Reactions {
-    pub allow: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Voice {
-    pub allow: String,
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Preference {
+    pub allow: AllowPreference,
+    /// Seconds before messages disappear, for timed messages only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Calls {
-    pub allow: String,
+impl Preference {
+    pub fn new(allow: AllowPreference) -> Self {
+        Preference { allow, ttl: None }
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Preference {
-    pub allow: AllowPreference,
+/// Chat features the user allows, for every contact in the profile or for one contact as an
+/// override. Features left as `None` keep the profile's or simplex-chat's default.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Preferences {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timed_messages: Option<Preference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_delete: Option<Preference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reactions: Option<Preference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<Preference>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calls: Option<Preference>,
+    /// Preferences added by newer simplex-chat versions, kept so that updates send them back.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Preferences {
+    /// Allows disappearing messages, deleted `ttl` after they are sent.
+    pub fn with_timed_messages(self, allow: AllowPreference, ttl: Option<Duration>) -> Self {
+        Preferences {
+            timed_messages: Some(Preference {
+                allow,
+                ttl: ttl.map(|ttl| ttl.as_secs()),
+            }),
+            ..self
+        }
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Whether a feature is allowed. With `Always`, the user uses it even if the contact only
+/// allows it; with `Yes`, only if both sides allow it.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum AllowPreference {
+    Always,
     Yes,
     No,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct PreferenceSettings {
     pub enabled: EnabledSettings,
@@ -257,6 +589,19 @@ pub struct PreferenceSettings {
     pub contact_preference: Preference,
 }
 
+impl GroupMemberRole {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GroupMemberRole::Observer => "observer",
+            GroupMemberRole::Author => "author",
+            GroupMemberRole::Member => "member",
+            GroupMemberRole::Moderator => "moderator",
+            GroupMemberRole::Admin => "admin",
+            GroupMemberRole::Owner => "owner",
+        }
+    }
+}
+
 impl ChatResponse {
     pub fn as_user(&self) -> Option<&User> {
         match self {
@@ -312,6 +657,7 @@ pub struct AddressPayload {
 pub struct ChatError {
     #[serde(rename = "type")]
     pub error_type: String,
+    #[serde(rename = "storeError", alias = "store_error", default)]
     pub store_error: Option<StoreErrorType>,
 }
 
@@ -360,6 +706,7 @@ pub struct StoreErrorType {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct UserContactRequest {
     pub contact_request_id: u64,
@@ -384,6 +731,7 @@ pub struct AutoAccept {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Contact {
     pub contact_id: u64,
@@ -393,40 +741,160 @@ pub struct Contact {
     pub contact_used: bool,
     pub contact_status: String,
     pub chat_settings: ChatSettings,
-    pub user_preferences: UserPreferences,
+    /// The user's overrides of their profile's preferences for this contact.
+    pub user_preferences: Preferences,
     pub merged_preferences: FullPreferences,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub contact_grp_inv_sent: bool,
     pub chat_deleted: bool,
+    /// IDs of the user's [`ChatTag`]s on this contact's chat.
+    #[serde(default)]
+    pub chat_tags: Vec<u64>,
 }
 
+impl Contact {
+    /// Whether the user compared security codes with the contact.
+    pub fn is_verified(&self) -> bool {
+        self.active_conn.is_verified()
+    }
+
+    /// Whether the contact sees a random profile instead of the user's.
+    pub fn is_incognito(&self) -> bool {
+        self.active_conn.is_incognito()
+    }
+}
+
+/// A label the user gives to chats to sort them, like "work" or "family".
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct UserPreferences {}
+pub struct ChatTag {
+    pub chat_tag_id: u64,
+    pub chat_tag_text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_tag_emoji: Option<String>,
+}
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ChatSettings {
-    pub enable_ntfs: String,
+    pub enable_ntfs: NotificationMode,
+    /// Overrides the user's delivery receipt setting for this chat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_rcpts: Option<bool>,
     pub favorite: bool,
 }
 
+impl ChatSettings {
+    pub fn is_muted(&self) -> bool {
+        self.enable_ntfs == NotificationMode::None
+    }
+}
+
+/// Which messages of a chat show notifications.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationMode {
+    All,
+    /// Only messages that mention the user or reply to them, in groups.
+    Mentions,
+    None,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Connection {
     pub conn_id: u64,
+    /// Set once the user verified the security code of the connection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_code: Option<SecurityCode>,
+    /// The random profile the other side sees instead of the user's, if the connection is
+    /// incognito.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_user_profile_id: Option<u64>,
+}
+
+impl Connection {
+    pub fn is_verified(&self) -> bool {
+        self.connection_code.is_some()
+    }
+
+    pub fn is_incognito(&self) -> bool {
+        self.custom_user_profile_id.is_some()
+    }
+}
+
+/// A connection that is not a contact yet, waiting for the other side to accept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingContactConnection {
+    pub pcc_conn_id: u64,
+    pub via_contact_uri: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_user_profile_id: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PendingContactConnection {
+    pub fn is_incognito(&self) -> bool {
+        self.custom_user_profile_id.is_some()
+    }
+}
+
+/// A verified security code. Both sides see the same code only if no one is relaying the
+/// connection, so comparing it out of band rules out a man in the middle.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityCode {
+    pub security_code: String,
+    pub verified_at: DateTime<Utc>,
+}
+
+/// The outcome of checking a security code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Verification {
+    pub verified: bool,
+    /// The code of the connection, to show when the code given did not match.
+    pub expected_code: String,
 }
 
 // Chat Messages
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct AChatItem {
     pub chat_info: ChatInfo,
     pub chat_item: ChatItem,
 }
 
+/// A page of a chat's history, oldest item first.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Chat {
+    pub chat_info: ChatInfo,
+    pub chat_items: Vec<ChatItem>,
+    #[serde(default)]
+    pub chat_stats: ChatStats,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChatStats {
+    pub unread_count: u32,
+    pub min_unread_item_id: ChatItemId,
+    pub unread_chat: bool,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ChatItem {
     pub chat_dir: CIDirection,
@@ -434,10 +902,129 @@ pub struct ChatItem {
     pub content: CIContent,
     pub formatted_text: Option<Vec<FormattedText>>,
     pub quoted_item: Option<CIQuote>,
-    pub reactions: Vec<serde_json::Value>,
+    pub reactions: Vec<CIReactionCount>,
+    #[serde(default)]
+    pub file: Option<CIFile>,
+}
+
+/// A file attached to a chat item.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIFile {
+    pub file_id: i64,
+    pub file_name: String,
+    pub file_size: u64,
+    /// Where the file is stored locally, once it was sent or received.
+    pub file_source: Option<CryptoFile>,
+    pub file_status: CIFileStatus,
+    pub file_protocol: FileProtocol,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CryptoFile {
+    pub file_path: String,
+    pub crypto_args: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CIFileStatus {
+    SndStored,
+    #[serde(rename_all = "camelCase")]
+    SndTransfer {
+        snd_progress: u64,
+        snd_total: u64,
+    },
+    SndComplete,
+    SndCancelled,
+    RcvInvitation,
+    RcvAccepted,
+    #[serde(rename_all = "camelCase")]
+    RcvTransfer {
+        rcv_progress: u64,
+        rcv_total: u64,
+    },
+    RcvComplete,
+    RcvCancelled,
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum FileProtocol {
+    Smp,
+    Xftp,
+    Local,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIReactionCount {
+    pub reaction: MsgReaction,
+    pub user_reacted: bool,
+    pub total_reacted: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MsgReaction {
+    Emoji {
+        emoji: String,
+    },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+impl MsgReaction {
+    pub fn emoji(emoji: impl Into<String>) -> Self {
+        MsgReaction::Emoji {
+            emoji: emoji.into(),
+        }
+    }
+
+    pub fn as_emoji(&self) -> Option<&str> {
+        match self {
+            MsgReaction::Emoji { emoji } => Some(emoji),
+            MsgReaction::Unknown(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ACIReaction {
+    pub chat_info: ChatInfo,
+    pub chat_reaction: CIReaction,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CIReaction {
+    pub chat_dir: CIDirection,
+    pub chat_item: ChatItem,
+    pub sent_at: DateTime<Utc>,
+    pub reaction: MsgReaction,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatItemDeletion {
+    pub deleted_chat_item: AChatItem,
+    pub to_chat_item: Option<AChatItem>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum ChatInfo {
     #[serde(rename = "direct")]
@@ -451,24 +1038,27 @@ pub enum ChatInfo {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CInfoDirect {
     pub contact: Contact,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CInfoGroup {
     pub group_info: GroupInfo,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CInfoContactRequest {
     pub contact_request: UserContactRequest,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub enum ChatInfoType {
     Direct,
@@ -476,6 +1066,33 @@ pub enum ChatInfoType {
     ContactRequest,
 }
 
+impl ChatInfo {
+    pub fn chat_type(&self) -> ChatInfoType {
+        match self {
+            ChatInfo::Direct(_) => ChatInfoType::Direct,
+            ChatInfo::Group(_) => ChatInfoType::Group,
+            ChatInfo::ContactRequest(_) => ChatInfoType::ContactRequest,
+        }
+    }
+
+    /// The contact, group or contact request ID, depending on the chat type.
+    pub fn chat_id(&self) -> u64 {
+        match self {
+            ChatInfo::Direct(direct) => direct.contact.contact_id,
+            ChatInfo::Group(group) => group.group_info.group_id,
+            ChatInfo::ContactRequest(request) => request.contact_request.contact_request_id,
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        match self {
+            ChatInfo::Direct(direct) => &direct.contact.local_display_name,
+            ChatInfo::Group(group) => &group.group_info.local_display_name,
+            ChatInfo::ContactRequest(request) => &request.contact_request.local_display_name,
+        }
+    }
+}
+
 impl ChatInfoType {
     pub fn value(&self) -> &'static str {
         match self {
@@ -487,13 +1104,18 @@ impl ChatInfoType {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIDirection {
     #[serde(rename = "type")]
     pub direction_type: DirectionType,
+    /// The sender of a received group message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_member: Option<GroupMember>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub enum DirectionType {
     DirectSnd,
@@ -503,6 +1125,7 @@ pub enum DirectionType {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum CIContent {
     #[serde(rename = "sndMsgContent")]
@@ -522,33 +1145,394 @@ pub enum CIContent {
 
     #[serde(rename = "rcvFileInvitation")]
     RcvFileInvitation(CIRcvFileInvitation),
-}
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct CISndMsgContent {
-    pub msg_content: MsgContent,
-}
+    #[serde(rename = "sndCall")]
+    SndCall(CISndCall),
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(rename = "rcvCall")]
+    RcvCall(CIRcvCall),
+
+    #[serde(rename = "rcvIntegrityError")]
+    RcvIntegrityError(CIRcvIntegrityError),
+
+    #[serde(rename = "rcvDecryptionError")]
+    RcvDecryptionError(CIRcvDecryptionError),
+
+    #[serde(rename = "rcvGroupInvitation")]
+    RcvGroupInvitation(CIRcvGroupInvitation),
+
+    #[serde(rename = "sndGroupInvitation")]
+    SndGroupInvitation(CISndGroupInvitation),
+
+    #[serde(rename = "rcvDirectEvent")]
+    RcvDirectEvent(CIRcvDirectEvent),
+
+    #[serde(rename = "rcvGroupEvent")]
+    RcvGroupEvent(CIRcvGroupEvent),
+
+    #[serde(rename = "sndGroupEvent")]
+    SndGroupEvent(CISndGroupEvent),
+
+    #[serde(rename = "rcvConnEvent")]
+    RcvConnEvent(CIRcvConnEvent),
+
+    #[serde(rename = "sndConnEvent")]
+    SndConnEvent(CISndConnEvent),
+
+    #[serde(rename = "rcvChatFeature")]
+    RcvChatFeature(CIRcvChatFeature),
+
+    #[serde(rename = "sndChatFeature")]
+    SndChatFeature(CISndChatFeature),
+
+    #[serde(rename = "rcvChatPreference")]
+    RcvChatPreference(CIRcvChatPreference),
+
+    #[serde(rename = "sndChatPreference")]
+    SndChatPreference(CISndChatPreference),
+
+    #[serde(rename = "rcvGroupFeature")]
+    RcvGroupFeature(CIRcvGroupFeature),
+
+    #[serde(rename = "sndGroupFeature")]
+    SndGroupFeature(CISndGroupFeature),
+
+    #[serde(rename = "rcvChatFeatureRejected")]
+    RcvChatFeatureRejected(CIRcvChatFeatureRejected),
+
+    #[serde(rename = "rcvGroupFeatureRejected")]
+    RcvGroupFeatureRejected(CIRcvGroupFeatureRejected),
+
+    #[serde(rename = "sndModerated")]
+    SndModerated,
+
+    #[serde(rename = "rcvModerated")]
+    RcvModerated,
+
+    #[serde(rename = "rcvBlocked")]
+    RcvBlocked,
+
+    #[serde(rename = "sndDirectE2EEInfo")]
+    SndDirectE2EEInfo(CISndE2EEInfo),
+
+    #[serde(rename = "rcvDirectE2EEInfo")]
+    RcvDirectE2EEInfo(CIRcvE2EEInfo),
+
+    #[serde(rename = "sndGroupE2EEInfo")]
+    SndGroupE2EEInfo(CISndE2EEInfo),
+
+    #[serde(rename = "rcvGroupE2EEInfo")]
+    RcvGroupE2EEInfo(CIRcvE2EEInfo),
+
+    #[serde(rename = "chatBanner")]
+    ChatBanner,
+
+    #[serde(rename = "invalidJSON")]
+    InvalidJson(CIInvalidJson),
+
+    /// Item types introduced by newer simplex-chat versions, kept as raw JSON so that a single
+    /// unsupported item does not fail the whole batch it arrives in.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+impl CIContent {
+    /// Whether the item is something a person sent (a message, call, file or group invitation),
+    /// as opposed to an event, preference change or metadata item generated by the client.
+    pub fn is_user_visible(&self) -> bool {
+        matches!(
+            self,
+            CIContent::SndMsgContent(_)
+                | CIContent::RcvMsgContent(_)
+                | CIContent::SndFileInvitation(_)
+                | CIContent::RcvFileInvitation(_)
+                | CIContent::SndCall(_)
+                | CIContent::RcvCall(_)
+                | CIContent::SndGroupInvitation(_)
+                | CIContent::RcvGroupInvitation(_)
+        )
+    }
+
+    /// A short, single-line description of the item, suitable for notifications and chat list
+    /// previews.
+    pub fn summary_text(&self) -> String {
+        match self {
+            CIContent::SndMsgContent(CISndMsgContent { msg_content })
+            | CIContent::RcvMsgContent(CIRcvMsgContent { msg_content }) => {
+                msg_content_summary(msg_content)
+            }
+            CIContent::SndDeleted(_) | CIContent::RcvDeleted(_) => "message deleted".to_string(),
+            CIContent::SndFileInvitation(_) | CIContent::RcvFileInvitation(_) => "file".to_string(),
+            CIContent::SndCall(CISndCall { status, duration }) => {
+                call_summary("outgoing", status, *duration)
+            }
+            CIContent::RcvCall(CIRcvCall { status, duration }) => {
+                call_summary("incoming", status, *duration)
+            }
+            CIContent::RcvIntegrityError(CIRcvIntegrityError { msg_error }) => match msg_error {
+                MsgErrorType::MsgSkipped {
+                    from_msg_id,
+                    to_msg_id,
+                } => format!(
+                    "{} messages skipped",
+                    to_msg_id.saturating_sub(*from_msg_id) + 1
+                ),
+                MsgErrorType::MsgBadId { .. } => "unexpected message ID".to_string(),
+                MsgErrorType::MsgBadHash => "incorrect message hash".to_string(),
+                MsgErrorType::MsgDuplicate => "duplicate message".to_string(),
+                MsgErrorType::Unknown(_) => "message integrity error".to_string(),
+            },
+            CIContent::RcvDecryptionError(CIRcvDecryptionError { msg_count, .. }) => {
+                format!("{} messages failed to decrypt", msg_count)
+            }
+            CIContent::RcvGroupInvitation(CIRcvGroupInvitation {
+                group_invitation, ..
+            })
+            | CIContent::SndGroupInvitation(CISndGroupInvitation {
+                group_invitation, ..
+            }) => format!(
+                "invitation to join group {}",
+                group_invitation.group_profile.display_name
+            ),
+            CIContent::RcvDirectEvent(CIRcvDirectEvent { rcv_direct_event }) => {
+                match rcv_direct_event {
+                    RcvDirectEvent::ContactDeleted => "contact deleted".to_string(),
+                    RcvDirectEvent::ProfileUpdated { to_profile, .. } => {
+                        format!("updated profile: {}", to_profile.display_name)
+                    }
+                    RcvDirectEvent::GroupInvLinkReceived { group_profile } => {
+                        format!("group link received: {}", group_profile.display_name)
+                    }
+                    RcvDirectEvent::Unknown(_) => "contact event".to_string(),
+                }
+            }
+            CIContent::RcvGroupEvent(CIRcvGroupEvent { rcv_group_event }) => {
+                rcv_group_event_summary(rcv_group_event)
+            }
+            CIContent::SndGroupEvent(CISndGroupEvent { snd_group_event }) => {
+                snd_group_event_summary(snd_group_event)
+            }
+            CIContent::RcvConnEvent(CIRcvConnEvent { rcv_conn_event }) => match rcv_conn_event {
+                RcvConnEvent::SwitchQueue { .. } => "changed address for you".to_string(),
+                RcvConnEvent::RatchetSync { .. } => "encryption re-negotiated".to_string(),
+                RcvConnEvent::VerificationCodeReset => "security code changed".to_string(),
+                RcvConnEvent::PqEnabled { enabled } => pq_summary(*enabled),
+                RcvConnEvent::Unknown(_) => "connection event".to_string(),
+            },
+            CIContent::SndConnEvent(CISndConnEvent { snd_conn_event }) => match snd_conn_event {
+                SndConnEvent::SwitchQueue { .. } => "changing address".to_string(),
+                SndConnEvent::RatchetSync { .. } => "re-negotiating encryption".to_string(),
+                SndConnEvent::PqEnabled { enabled } => pq_summary(*enabled),
+                SndConnEvent::Unknown(_) => "connection event".to_string(),
+            },
+            CIContent::RcvChatFeature(CIRcvChatFeature {
+                feature, enabled, ..
+            })
+            | CIContent::SndChatFeature(CISndChatFeature {
+                feature, enabled, ..
+            }) => {
+                let state = if enabled.for_user && enabled.for_contact {
+                    "enabled"
+                } else if enabled.for_user {
+                    "enabled for you"
+                } else if enabled.for_contact {
+                    "enabled for contact"
+                } else {
+                    "off"
+                };
+                format!("{}: {}", feature.name(), state)
+            }
+            CIContent::RcvChatPreference(CIRcvChatPreference {
+                feature, allowed, ..
+            })
+            | CIContent::SndChatPreference(CISndChatPreference {
+                feature, allowed, ..
+            }) => {
+                let allowed = match allowed {
+                    AllowPreference::Always => "always",
+                    AllowPreference::Yes => "allowed",
+                    AllowPreference::No => "prohibited",
+                };
+                format!("{}: {}", feature.name(), allowed)
+            }
+            CIContent::RcvGroupFeature(CIRcvGroupFeature {
+                group_feature,
+                preference,
+                ..
+            })
+            | CIContent::SndGroupFeature(CISndGroupFeature {
+                group_feature,
+                preference,
+                ..
+            }) => {
+                let state = match preference.enable {
+                    GroupFeatureEnabled::On => "on",
+                    GroupFeatureEnabled::Off => "off",
+                };
+                format!("{}: {}", group_feature.name(), state)
+            }
+            CIContent::RcvChatFeatureRejected(CIRcvChatFeatureRejected { feature }) => {
+                format!("{}: received, prohibited", feature.name())
+            }
+            CIContent::RcvGroupFeatureRejected(CIRcvGroupFeatureRejected { group_feature }) => {
+                format!("{}: received, prohibited", group_feature.name())
+            }
+            CIContent::SndModerated | CIContent::RcvModerated => "moderated".to_string(),
+            CIContent::RcvBlocked => "blocked by admin".to_string(),
+            CIContent::SndDirectE2EEInfo(CISndE2EEInfo { e2ee_info })
+            | CIContent::SndGroupE2EEInfo(CISndE2EEInfo { e2ee_info })
+            | CIContent::RcvDirectE2EEInfo(CIRcvE2EEInfo { e2ee_info })
+            | CIContent::RcvGroupE2EEInfo(CIRcvE2EEInfo { e2ee_info }) => {
+                if e2ee_info.pq_enabled == Some(true) {
+                    "messages are end-to-end encrypted with quantum resistant encryption".to_string()
+                } else {
+                    "messages are end-to-end encrypted".to_string()
+                }
+            }
+            CIContent::ChatBanner => String::new(),
+            CIContent::InvalidJson(_) => "invalid data".to_string(),
+            CIContent::Unknown(_) => "unsupported item".to_string(),
+        }
+    }
+}
+
+fn msg_content_summary(msg_content: &MsgContent) -> String {
+    let text = msg_content.text();
+    if !text.is_empty() {
+        return text.to_string();
+    }
+
+    match msg_content {
+        MsgContent::Image(_) => "[image]".to_string(),
+        MsgContent::Video(_) => "[video]".to_string(),
+        MsgContent::Voice(MCVoice { duration, .. }) => {
+            format!("[voice message {}]", format_duration(*duration))
+        }
+        MsgContent::File(_) => "[file]".to_string(),
+        MsgContent::Chat(_) => "[chat link]".to_string(),
+        _ => String::new(),
+    }
+}
+
+fn call_summary(direction: &str, status: &CICallStatus, duration: u64) -> String {
+    match status {
+        CICallStatus::Ended => format!("{} call ended ({})", direction, format_duration(duration)),
+        CICallStatus::Pending => format!("{} call", direction),
+        CICallStatus::Missed => "missed call".to_string(),
+        CICallStatus::Rejected => "rejected call".to_string(),
+        CICallStatus::Accepted | CICallStatus::Negotiated => "call connecting".to_string(),
+        CICallStatus::Progress => "call in progress".to_string(),
+        CICallStatus::Error => "call error".to_string(),
+        CICallStatus::Unknown(status) => format!("{} call: {}", direction, status),
+    }
+}
+
+fn rcv_group_event_summary(event: &RcvGroupEvent) -> String {
+    match event {
+        RcvGroupEvent::MemberAdded { profile, .. } => format!("invited {}", profile.display_name),
+        RcvGroupEvent::MemberConnected => "member connected".to_string(),
+        RcvGroupEvent::MemberAccepted { profile, .. } => {
+            format!("accepted {}", profile.display_name)
+        }
+        RcvGroupEvent::UserAccepted => "accepted you".to_string(),
+        RcvGroupEvent::MemberLeft => "left".to_string(),
+        RcvGroupEvent::MemberRole { profile, role, .. } => format!(
+            "changed role of {} to {}",
+            profile.display_name,
+            role.name()
+        ),
+        RcvGroupEvent::MemberBlocked {
+            profile, blocked, ..
+        } => {
+            let action = if *blocked { "blocked" } else { "unblocked" };
+            format!("{} {}", action, profile.display_name)
+        }
+        RcvGroupEvent::UserRole { role } => format!("changed your role to {}", role.name()),
+        RcvGroupEvent::MemberDeleted { profile, .. } => {
+            format!("removed {}", profile.display_name)
+        }
+        RcvGroupEvent::UserDeleted => "removed you".to_string(),
+        RcvGroupEvent::GroupDeleted => "deleted group".to_string(),
+        RcvGroupEvent::GroupUpdated { .. } => "group profile updated".to_string(),
+        RcvGroupEvent::InvitedViaGroupLink => "invited via your group link".to_string(),
+        RcvGroupEvent::MemberCreatedContact => "connected directly".to_string(),
+        RcvGroupEvent::MemberProfileUpdated { to_profile, .. } => {
+            format!("updated profile: {}", to_profile.display_name)
+        }
+        RcvGroupEvent::NewMemberPendingReview => "new member wants to join".to_string(),
+        RcvGroupEvent::Unknown(_) => "group event".to_string(),
+    }
+}
+
+fn snd_group_event_summary(event: &SndGroupEvent) -> String {
+    match event {
+        SndGroupEvent::MemberRole { profile, role, .. } => format!(
+            "you changed role of {} to {}",
+            profile.display_name,
+            role.name()
+        ),
+        SndGroupEvent::MemberBlocked {
+            profile, blocked, ..
+        } => {
+            let action = if *blocked { "blocked" } else { "unblocked" };
+            format!("you {} {}", action, profile.display_name)
+        }
+        SndGroupEvent::UserRole { role } => format!("you changed your role to {}", role.name()),
+        SndGroupEvent::MemberDeleted { profile, .. } => {
+            format!("you removed {}", profile.display_name)
+        }
+        SndGroupEvent::UserLeft => "you left".to_string(),
+        SndGroupEvent::GroupUpdated { .. } => "group profile updated".to_string(),
+        SndGroupEvent::MemberAccepted { profile, .. } => {
+            format!("you accepted {}", profile.display_name)
+        }
+        SndGroupEvent::UserPendingReview => "please wait for the group moderators".to_string(),
+        SndGroupEvent::Unknown(_) => "group event".to_string(),
+    }
+}
+
+fn pq_summary(enabled: bool) -> String {
+    if enabled {
+        "quantum resistant e2e encryption".to_string()
+    } else {
+        "standard end-to-end encryption".to_string()
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndMsgContent {
+    pub msg_content: MsgContent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIRcvMsgContent {
     pub msg_content: MsgContent,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CISndDeleted {
     pub delete_mode: DeleteMode,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIRcvDeleted {
     pub delete_mode: DeleteMode,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CISndFileInvitation {
     pub file_id: u64,
@@ -556,16 +1540,621 @@ pub struct CISndFileInvitation {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIRcvFileInvitation {
     pub rcv_file_transfer: RcvFileTransfer,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndCall {
+    pub status: CICallStatus,
+    pub duration: u64, // Seconds
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvCall {
+    pub status: CICallStatus,
+    pub duration: u64, // Seconds
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum CICallStatus {
+    Pending,
+    Missed,
+    Rejected,
+    Accepted,
+    Negotiated,
+    Progress,
+    Ended,
+    Error,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvIntegrityError {
+    pub msg_error: MsgErrorType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MsgErrorType {
+    #[serde(rename_all = "camelCase")]
+    MsgSkipped {
+        from_msg_id: u64,
+        to_msg_id: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    MsgBadId {
+        msg_id: u64,
+    },
+    MsgBadHash,
+    MsgDuplicate,
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvDecryptionError {
+    pub msg_decrypt_error: MsgDecryptError,
+    pub msg_count: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum MsgDecryptError {
+    RatchetHeader,
+    TooManySkipped,
+    RatchetEarlier,
+    Other,
+    RatchetSync,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvGroupInvitation {
+    pub group_invitation: CIGroupInvitation,
+    pub member_role: GroupMemberRole,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
-pub struct FormattedText {}
+pub struct CISndGroupInvitation {
+    pub group_invitation: CIGroupInvitation,
+    pub member_role: GroupMemberRole,
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIGroupInvitation {
+    pub group_id: u64,
+    pub group_member_id: u64,
+    pub local_display_name: String,
+    pub group_profile: GroupProfile,
+    pub status: CIGroupInvitationStatus,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum CIGroupInvitationStatus {
+    Pending,
+    Accepted,
+    Rejected,
+    Expired,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvDirectEvent {
+    pub rcv_direct_event: RcvDirectEvent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RcvDirectEvent {
+    ContactDeleted,
+    #[serde(rename_all = "camelCase")]
+    ProfileUpdated {
+        from_profile: Profile,
+        to_profile: Profile,
+    },
+    #[serde(rename_all = "camelCase")]
+    GroupInvLinkReceived {
+        group_profile: GroupProfile,
+    },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvGroupEvent {
+    pub rcv_group_event: RcvGroupEvent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RcvGroupEvent {
+    #[serde(rename_all = "camelCase")]
+    MemberAdded {
+        group_member_id: u64,
+        profile: Profile,
+    },
+    MemberConnected,
+    #[serde(rename_all = "camelCase")]
+    MemberAccepted {
+        group_member_id: u64,
+        profile: Profile,
+    },
+    UserAccepted,
+    MemberLeft,
+    #[serde(rename_all = "camelCase")]
+    MemberRole {
+        group_member_id: u64,
+        profile: Profile,
+        role: GroupMemberRole,
+    },
+    #[serde(rename_all = "camelCase")]
+    MemberBlocked {
+        group_member_id: u64,
+        profile: Profile,
+        blocked: bool,
+    },
+    UserRole {
+        role: GroupMemberRole,
+    },
+    #[serde(rename_all = "camelCase")]
+    MemberDeleted {
+        group_member_id: u64,
+        profile: Profile,
+    },
+    UserDeleted,
+    GroupDeleted,
+    #[serde(rename_all = "camelCase")]
+    GroupUpdated {
+        group_profile: GroupProfile,
+    },
+    InvitedViaGroupLink,
+    MemberCreatedContact,
+    #[serde(rename_all = "camelCase")]
+    MemberProfileUpdated {
+        from_profile: Profile,
+        to_profile: Profile,
+    },
+    NewMemberPendingReview,
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndGroupEvent {
+    pub snd_group_event: SndGroupEvent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SndGroupEvent {
+    #[serde(rename_all = "camelCase")]
+    MemberRole {
+        group_member_id: u64,
+        profile: Profile,
+        role: GroupMemberRole,
+    },
+    #[serde(rename_all = "camelCase")]
+    MemberBlocked {
+        group_member_id: u64,
+        profile: Profile,
+        blocked: bool,
+    },
+    UserRole {
+        role: GroupMemberRole,
+    },
+    #[serde(rename_all = "camelCase")]
+    MemberDeleted {
+        group_member_id: u64,
+        profile: Profile,
+    },
+    UserLeft,
+    #[serde(rename_all = "camelCase")]
+    GroupUpdated {
+        group_profile: GroupProfile,
+    },
+    #[serde(rename_all = "camelCase")]
+    MemberAccepted {
+        group_member_id: u64,
+        profile: Profile,
+    },
+    UserPendingReview,
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvConnEvent {
+    pub rcv_conn_event: RcvConnEvent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RcvConnEvent {
+    SwitchQueue {
+        phase: SwitchPhase,
+    },
+    #[serde(rename_all = "camelCase")]
+    RatchetSync {
+        sync_status: RatchetSyncState,
+    },
+    VerificationCodeReset,
+    PqEnabled {
+        enabled: bool,
+    },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndConnEvent {
+    pub snd_conn_event: SndConnEvent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SndConnEvent {
+    SwitchQueue {
+        phase: SwitchPhase,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        member: Option<GroupMemberRef>,
+    },
+    #[serde(rename_all = "camelCase")]
+    RatchetSync {
+        sync_status: RatchetSyncState,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        member: Option<GroupMemberRef>,
+    },
+    PqEnabled {
+        enabled: bool,
+    },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMemberRef {
+    pub group_member_id: u64,
+    pub profile: Profile,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum SwitchPhase {
+    Started,
+    Confirmed,
+    Secured,
+    Completed,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum RatchetSyncState {
+    Ok,
+    Allowed,
+    Required,
+    Started,
+    Agreed,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvChatFeature {
+    pub feature: ChatFeature,
+    pub enabled: EnabledSettings,
+    pub param: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndChatFeature {
+    pub feature: ChatFeature,
+    pub enabled: EnabledSettings,
+    pub param: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvChatPreference {
+    pub feature: ChatFeature,
+    pub allowed: AllowPreference,
+    pub param: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndChatPreference {
+    pub feature: ChatFeature,
+    pub allowed: AllowPreference,
+    pub param: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvGroupFeature {
+    pub group_feature: GroupFeature,
+    pub preference: GroupPreference,
+    pub param: Option<u64>,
+    #[serde(rename = "memberRole_")]
+    pub member_role: Option<GroupMemberRole>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndGroupFeature {
+    pub group_feature: GroupFeature,
+    pub preference: GroupPreference,
+    pub param: Option<u64>,
+    #[serde(rename = "memberRole_")]
+    pub member_role: Option<GroupMemberRole>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvChatFeatureRejected {
+    pub feature: ChatFeature,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvGroupFeatureRejected {
+    pub group_feature: GroupFeature,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum ChatFeature {
+    TimedMessages,
+    FullDelete,
+    Reactions,
+    Voice,
+    Calls,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl ChatFeature {
+    pub fn name(&self) -> &str {
+        match self {
+            ChatFeature::TimedMessages => "disappearing messages",
+            ChatFeature::FullDelete => "delete for everyone",
+            ChatFeature::Reactions => "message reactions",
+            ChatFeature::Voice => "voice messages",
+            ChatFeature::Calls => "audio/video calls",
+            ChatFeature::Unknown(feature) => feature,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum GroupFeature {
+    TimedMessages,
+    DirectMessages,
+    FullDelete,
+    Reactions,
+    Voice,
+    Files,
+    SimplexLinks,
+    Reports,
+    History,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl GroupFeature {
+    pub fn name(&self) -> &str {
+        match self {
+            GroupFeature::TimedMessages => "disappearing messages",
+            GroupFeature::DirectMessages => "direct messages",
+            GroupFeature::FullDelete => "delete for everyone",
+            GroupFeature::Reactions => "message reactions",
+            GroupFeature::Voice => "voice messages",
+            GroupFeature::Files => "files and media",
+            GroupFeature::SimplexLinks => "SimpleX links",
+            GroupFeature::Reports => "member reports",
+            GroupFeature::History => "recent history",
+            GroupFeature::Unknown(feature) => feature,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPreference {
+    pub enable: GroupFeatureEnabled,
+    /// Seconds before messages disappear, for timed messages only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+/// The features a group's owners allow for its members.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPreferences {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timed_messages: Option<GroupPreference>,
+    /// The other group features, kept so that updates send them back.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum GroupFeatureEnabled {
+    On,
+    Off,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndE2EEInfo {
+    pub e2ee_info: E2EInfo,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIRcvE2EEInfo {
+    pub e2ee_info: E2EInfo,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct E2EInfo {
+    #[serde(default)]
+    pub pq_enabled: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIInvalidJson {
+    pub json: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedText {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+}
+
+impl FormattedText {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            format: None,
+        }
+    }
+
+    pub fn formatted(text: impl Into<String>, format: Format) -> Self {
+        Self {
+            text: text.into(),
+            format: Some(format),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Format {
+    Bold,
+    Italic,
+    StrikeThrough,
+    Snippet,
+    Secret,
+    Colored {
+        color: FormatColor,
+    },
+    Uri,
+    #[serde(rename_all = "camelCase")]
+    SimplexLink {
+        link_type: SimplexLinkType,
+        simplex_uri: String,
+        smp_hosts: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Mention {
+        member_name: String,
+    },
+    Email,
+    Phone,
+    /// Formats introduced by newer simplex-chat versions, kept as raw JSON.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum FormatColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Cyan,
+    Magenta,
+    Black,
+    White,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum SimplexLinkType {
+    Contact,
+    Invitation,
+    Group,
+    Channel,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct RcvFileTransfer {
     pub file_id: u64,
@@ -576,6 +2165,7 @@ pub struct RcvFileTransfer {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIQuote {
     pub chat_dir: Option<CIDirection>,
@@ -587,101 +2177,292 @@ pub struct CIQuote {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub enum DeleteMode {
+    #[serde(alias = "cidmBroadcast")]
     Broadcast,
+    #[serde(alias = "cidmInternal")]
     Internal,
+    #[serde(alias = "cidmInternalMark")]
+    InternalMark,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum CIStatus {
     #[serde(rename = "sndNew")]
     SndNew(CISndNew),
     #[serde(rename = "sndSent")]
     SndSent(CISndSent),
+    #[serde(rename = "sndRcvd")]
+    SndRcvd(CISndRcvd),
     #[serde(rename = "sndErrorAuth")]
     SndErrorAuth(CISndErrorAuth),
     #[serde(rename = "sndError")]
     SndError(CISndError),
+    #[serde(rename = "sndWarning")]
+    SndWarning(CISndWarning),
     #[serde(rename = "rcvNew")]
     RcvNew(CIRcvNew),
     #[serde(rename = "rcvRead")]
     RcvRead(CIRcvRead),
+    #[serde(rename = "invalid")]
+    Invalid(CIInvalid),
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+impl CIStatus {
+    /// The message left this device, for every recipient when `complete` is set.
+    pub fn is_sent(&self, complete: bool) -> bool {
+        match self {
+            CIStatus::SndSent(CISndSent { snd_progress }) => {
+                !complete || *snd_progress == SndCIStatusProgress::Complete
+            }
+            CIStatus::SndRcvd(_) => true,
+            _ => false,
+        }
+    }
+
+    /// A delivery receipt arrived, from every recipient when `complete` is set.
+    pub fn is_delivered(&self, complete: bool) -> bool {
+        match self {
+            CIStatus::SndRcvd(CISndRcvd { snd_progress, .. }) => {
+                !complete || *snd_progress == SndCIStatusProgress::Complete
+            }
+            _ => false,
+        }
+    }
+
+    /// Sending failed permanently. Warnings are not errors, as the message is still being retried.
+    pub fn is_error(&self) -> bool {
+        matches!(self, CIStatus::SndErrorAuth(_) | CIStatus::SndError(_))
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CISndNew {}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndSent {
+    #[serde(default)]
+    pub snd_progress: SndCIStatusProgress,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
-pub struct CISndSent {}
+pub struct CISndRcvd {
+    pub msg_rcpt_status: MsgReceiptStatus,
+    #[serde(default)]
+    pub snd_progress: SndCIStatusProgress,
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CISndErrorAuth {}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CISndError {
-    pub agent_error: AgentErrorType,
+    pub agent_error: SndError,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CISndWarning {
+    pub agent_error: SndError,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIRcvNew {}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIRcvRead {}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CIInvalid {
+    pub text: String,
+}
+
+/// Whether a status applies to all recipients of a group message, or only to some of them.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
-pub enum StatusType {
-    SndNew,
-    SndSent,
-    SndErrorAuth,
-    SndError,
-    RcvNew,
-    RcvRead,
+pub enum SndCIStatusProgress {
+    Partial,
+    #[default]
+    Complete,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum MsgReceiptStatus {
+    Ok,
+    BadMsgHash,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SndError {
+    Auth,
+    Quota,
+    Expired,
+    #[serde(rename_all = "camelCase")]
+    Relay {
+        srv_error: serde_json::Value,
+    },
+    #[serde(rename_all = "camelCase")]
+    Proxy {
+        proxy_server: String,
+        srv_error: serde_json::Value,
+    },
+    #[serde(rename_all = "camelCase")]
+    ProxyRelay {
+        proxy_server: String,
+        srv_error: serde_json::Value,
+    },
+    #[serde(rename_all = "camelCase")]
+    Other {
+        snd_error: String,
+    },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct GroupInfo {
     pub group_id: u64,
     pub local_display_name: String,
     pub group_profile: GroupProfile,
     pub membership: GroupMember,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_settings: Option<ChatSettings>,
     pub created_at: DateTime<Utc>,
 }
 
+/// A group and how many members it has.
+///
+/// Older versions of simplex-chat send these as `[groupInfo, groupSummary]` pairs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(from = "GroupInfoSummaryRepr")]
+#[serde(rename_all = "camelCase")]
+pub struct GroupInfoSummary {
+    pub group_info: GroupInfo,
+    pub group_summary: GroupSummary,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GroupInfoSummaryRepr {
+    #[serde(rename_all = "camelCase")]
+    Object {
+        group_info: GroupInfo,
+        group_summary: GroupSummary,
+    },
+    Pair(GroupInfo, GroupSummary),
+}
+
+impl From<GroupInfoSummaryRepr> for GroupInfoSummary {
+    fn from(repr: GroupInfoSummaryRepr) -> Self {
+        match repr {
+            GroupInfoSummaryRepr::Object {
+                group_info,
+                group_summary,
+            }
+            | GroupInfoSummaryRepr::Pair(group_info, group_summary) => GroupInfoSummary {
+                group_info,
+                group_summary,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase", default)]
+pub struct GroupSummary {
+    pub current_members: u32,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct GroupProfile {
     pub display_name: String,
     pub full_name: String,
     pub image: Option<String>, // Optional field for base64 image string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_preferences: Option<GroupPreferences>,
+    /// Fields this crate does not model, like the description, kept so that updates send them
+    /// back.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum MsgContent {
     Text(MCText),
     Link(MCLink),
     Image(MCImage),
+    Video(MCVideo),
+    Voice(MCVoice),
     File(MCFile),
+    Report(MCReport),
+    Chat(MCChat),
+    /// Content types this crate does not know about yet, kept with all of their fields.
+    #[serde(untagged)]
     Unknown(MCUnknown),
 }
 
+impl MsgContent {
+    /// The message text, which is the caption for media, file and link messages.
+    pub fn text(&self) -> &str {
+        match self {
+            MsgContent::Text(mc) => &mc.text,
+            MsgContent::Link(mc) => &mc.text,
+            MsgContent::Image(mc) => &mc.text,
+            MsgContent::Video(mc) => &mc.text,
+            MsgContent::Voice(mc) => &mc.text,
+            MsgContent::File(mc) => &mc.text,
+            MsgContent::Report(mc) => &mc.text,
+            MsgContent::Chat(mc) => &mc.text,
+            MsgContent::Unknown(mc) => &mc.text,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MCText {
     pub text: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MCLink {
     pub text: String,
@@ -689,37 +2470,149 @@ pub struct MCLink {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MCImage {
+    #[serde(default)]
+    pub text: String,
     pub image: String, // Base64-encoded image string
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MCVideo {
+    #[serde(default)]
+    pub text: String,
+    pub image: String, // Base64-encoded thumbnail
+    pub duration: u64, // Seconds
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MCVoice {
+    #[serde(default)]
+    pub text: String,
+    pub duration: u64, // Seconds
+}
+
+/// A file attachment. `text` is the optional caption, the file itself is described by the chat
+/// item's file transfer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MCFile {
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MCReport {
+    #[serde(default)]
+    pub text: String,
+    pub reason: ReportReason,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum ReportReason {
+    Spam,
+    Content,
+    Community,
+    Profile,
+    Other,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MCChat {
+    #[serde(default)]
     pub text: String,
+    pub chat_link: MsgChatLink,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MsgChatLink {
+    #[serde(rename_all = "camelCase")]
+    Contact {
+        conn_link: String,
+        profile: Profile,
+        business: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Invitation { inv_link: String, profile: Profile },
+    #[serde(rename_all = "camelCase")]
+    Group {
+        conn_link: String,
+        group_profile: GroupProfile,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub display_name: String,
+    pub full_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact_link: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferences: Option<Box<Preferences>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MCUnknown {
     #[serde(rename = "type")]
     pub unknown_type: String,
+    #[serde(default)]
     pub text: String,
+    #[serde(flatten)]
+    pub json: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LinkPreview {
     pub uri: String,
     pub title: String,
     pub description: String,
     pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<LinkContent>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LinkContent {
+    Page,
+    Image,
+    Video {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<u64>,
+    },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
 }
 
 pub type ChatItemId = u64;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct CIMeta {
     pub item_id: ChatItemId,
@@ -731,6 +2624,234 @@ pub struct CIMeta {
     pub item_shared_msg_id: String,
     pub item_deleted: Option<bool>,
     pub item_edited: bool,
+    /// Set on disappearing messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_timed: Option<CITimed>,
+    /// Whether a received group message mentions the user.
+    #[serde(default)]
+    pub user_mention: bool,
     pub editable: bool,
     pub deletable: bool,
 }
+
+impl CIMeta {
+    /// When the item disappears, once its timer started.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.item_timed.as_ref().and_then(|timed| timed.delete_at)
+    }
+}
+
+/// The timer of a disappearing message. It starts when the message is read, so received
+/// messages have no `delete_at` until then.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CITimed {
+    /// Seconds the message stays once read.
+    pub ttl: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn round_trip(payload: Value) -> MsgContent {
+        let content: MsgContent = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&content).unwrap(), payload);
+        content
+    }
+
+    #[test]
+    fn test_msg_content_media() {
+        let image = round_trip(json!({
+            "type": "image",
+            "text": "sunset",
+            "image": "data:image/jpg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD"
+        }));
+        assert!(matches!(&image, MsgContent::Image(mc) if mc.text == "sunset"));
+
+        let video = round_trip(json!({
+            "type": "video",
+            "text": "",
+            "image": "data:image/jpg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD",
+            "duration": 14
+        }));
+        assert!(matches!(
+            video,
+            MsgContent::Video(MCVideo { duration: 14, .. })
+        ));
+
+        let voice = round_trip(json!({ "type": "voice", "text": "", "duration": 5 }));
+        assert!(matches!(
+            voice,
+            MsgContent::Voice(MCVoice { duration: 5, .. })
+        ));
+
+        let file = round_trip(json!({ "type": "file", "text": "invoice" }));
+        assert_eq!(file.text(), "invoice");
+    }
+
+    #[test]
+    fn test_msg_content_links() {
+        let link = round_trip(json!({
+            "type": "link",
+            "text": "https://simplex.chat",
+            "preview": {
+                "uri": "https://simplex.chat",
+                "title": "SimpleX Chat",
+                "description": "The first messaging network operating without user identifiers",
+                "image": "data:image/jpg;base64,/9j/4AAQSkZJRgABAQAAAQABAAD",
+                "content": { "type": "page" }
+            }
+        }));
+        assert!(matches!(
+            link,
+            MsgContent::Link(MCLink {
+                preview: LinkPreview {
+                    content: Some(LinkContent::Page),
+                    ..
+                },
+                ..
+            })
+        ));
+
+        let chat = round_trip(json!({
+            "type": "chat",
+            "text": "",
+            "chatLink": {
+                "type": "contact",
+                "connLink": "https://smp6.simplex.im/a#lrdvu2d8A1GumSmoKb2krQmtKhWXq-tyGpHuM7aMwsw",
+                "profile": { "displayName": "support", "fullName": "Support Bot" },
+                "business": true
+            }
+        }));
+        assert!(matches!(
+            chat,
+            MsgContent::Chat(MCChat {
+                chat_link: MsgChatLink::Contact { business: true, .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_msg_content_report_and_unknown() {
+        let report = round_trip(json!({ "type": "report", "text": "ads", "reason": "spam" }));
+        assert!(matches!(
+            report,
+            MsgContent::Report(MCReport {
+                reason: ReportReason::Spam,
+                ..
+            })
+        ));
+
+        let future = round_trip(json!({ "type": "poll", "text": "lunch?", "options": ["a", "b"] }));
+        match future {
+            MsgContent::Unknown(mc) => {
+                assert_eq!(mc.unknown_type, "poll");
+                assert_eq!(mc.text, "lunch?");
+                assert_eq!(mc.json["options"], json!(["a", "b"]));
+            }
+            other => panic!("expected unknown content, got {:?}", other),
+        }
+
+        let reason: ReportReason = serde_json::from_value(json!("harassment")).unwrap();
+        assert_eq!(reason, ReportReason::Unknown("harassment".to_string()));
+    }
+
+    #[test]
+    fn test_ci_content_events() {
+        let decode = |payload| serde_json::from_value::<CIContent>(payload).unwrap();
+        let profile = json!({ "displayName": "alice", "fullName": "Alice" });
+
+        let role = decode(json!({
+            "type": "rcvGroupEvent",
+            "rcvGroupEvent": {
+                "type": "memberRole",
+                "groupMemberId": 7,
+                "profile": profile,
+                "role": "moderator"
+            }
+        }));
+        assert!(!role.is_user_visible());
+        assert_eq!(role.summary_text(), "changed role of alice to moderator");
+
+        let call = decode(json!({ "type": "rcvCall", "status": "ended", "duration": 83 }));
+        assert!(call.is_user_visible());
+        assert_eq!(call.summary_text(), "incoming call ended (1:23)");
+
+        let skipped = decode(json!({
+            "type": "rcvIntegrityError",
+            "msgError": { "type": "msgSkipped", "fromMsgId": 10, "toMsgId": 12 }
+        }));
+        assert_eq!(skipped.summary_text(), "3 messages skipped");
+
+        let feature = decode(json!({
+            "type": "rcvChatFeature",
+            "feature": "timedMessages",
+            "enabled": { "forUser": true, "forContact": true },
+            "param": 86400
+        }));
+        assert_eq!(feature.summary_text(), "disappearing messages: enabled");
+
+        let group_feature = decode(json!({
+            "type": "sndGroupFeature",
+            "groupFeature": "directMessages",
+            "preference": { "enable": "off" },
+            "param": null,
+            "memberRole_": null
+        }));
+        assert_eq!(group_feature.summary_text(), "direct messages: off");
+
+        let e2ee = decode(json!({ "type": "rcvDirectE2EEInfo", "e2eeInfo": { "pqEnabled": true } }));
+        assert!(!e2ee.is_user_visible());
+
+        let deleted = decode(json!({ "type": "sndDeleted", "deleteMode": "broadcast" }));
+        assert!(matches!(
+            deleted,
+            CIContent::SndDeleted(CISndDeleted {
+                delete_mode: DeleteMode::Broadcast
+            })
+        ));
+        let mark: DeleteMode = serde_json::from_value(json!("internalMark")).unwrap();
+        assert_eq!(serde_json::to_value(mark).unwrap(), json!("internalMark"));
+
+        assert!(matches!(
+            decode(json!({ "type": "rcvBlocked" })),
+            CIContent::RcvBlocked
+        ));
+        assert!(matches!(
+            decode(json!({ "type": "rcvFutureEvent", "x": 1 })),
+            CIContent::Unknown(_)
+        ));
+    }
+
+    #[test]
+    fn test_ci_content_summary_for_media() {
+        let content: CIContent = serde_json::from_value(json!({
+            "type": "rcvMsgContent",
+            "msgContent": { "type": "voice", "text": "", "duration": 65 }
+        }))
+        .unwrap();
+
+        assert_eq!(content.summary_text(), "[voice message 1:05]");
+    }
+
+    #[test]
+    fn test_preferences_keep_unknown() {
+        let payload = json!({
+            "timedMessages": { "allow": "yes", "ttl": 3600 },
+            "files": { "allow": "always" },
+            "simplexLinks": { "allow": "no" }
+        });
+        let preferences: Preferences = serde_json::from_value(payload.clone()).unwrap();
+
+        assert_eq!(preferences.timed_messages.as_ref().unwrap().ttl, Some(3600));
+        assert_eq!(preferences.other.len(), 2);
+        assert_eq!(serde_json::to_value(&preferences).unwrap(), payload);
+    }
+}
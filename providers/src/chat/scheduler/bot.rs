@@ -0,0 +1,290 @@
+//! Chat commands for managing scheduled messages:
+//!
+//! - `/remind 10m stand up` sends "stand up" back in 10 minutes. Delays combine `d`, `h`, `m`
+//!   and `s`, as in `1h30m`.
+//! - `/every 0 9 * * 1-5 good morning` sends "good morning" at 9:00 UTC on weekdays.
+//! - `/schedules` lists the chat's scheduled messages.
+//! - `/unschedule 3` cancels scheduled message 3.
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::{cron::CronSchedule, Recurrence, ScheduleId, ScheduledMessage, Scheduler};
+use crate::chat::{
+    commands::ComposedMessage,
+    response::{ChatInfoType, MCText, MsgContent},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleCommand {
+    Remind {
+        delay: Duration,
+        text: String,
+    },
+    Every {
+        schedule: CronSchedule,
+        text: String,
+    },
+    List,
+    Cancel(ScheduleId),
+}
+
+impl ScheduleCommand {
+    /// Parses a message. Returns `None` if it is not a schedule command at all, and an error
+    /// explaining the right form if it is one with the wrong arguments.
+    pub fn parse(text: &str) -> Option<Result<Self, String>> {
+        let text = text.trim();
+        let (command, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest = rest.trim();
+
+        let parsed = match command {
+            "/remind" => parse_remind(rest),
+            "/every" => parse_every(rest),
+            "/schedules" => Ok(ScheduleCommand::List),
+            "/unschedule" => rest
+                .parse()
+                .map(ScheduleCommand::Cancel)
+                .map_err(|_| "usage: /unschedule <id>".to_string()),
+            _ => return None,
+        };
+
+        Some(parsed)
+    }
+}
+
+fn parse_remind(rest: &str) -> Result<ScheduleCommand, String> {
+    let usage = || "usage: /remind <delay like 10m or 1h30m> <text>".to_string();
+    let (delay, text) = rest.split_once(char::is_whitespace).ok_or_else(usage)?;
+    let delay = parse_delay(delay).ok_or_else(usage)?;
+    let text = text.trim();
+
+    if text.is_empty() {
+        return Err(usage());
+    }
+
+    Ok(ScheduleCommand::Remind {
+        delay,
+        text: text.to_string(),
+    })
+}
+
+fn parse_every(rest: &str) -> Result<ScheduleCommand, String> {
+    let fields: Vec<&str> = rest.splitn(6, char::is_whitespace).collect();
+    let [minute, hour, day, month, weekday, text] = fields.as_slice() else {
+        return Err("usage: /every <minute hour day month weekday> <text>".to_string());
+    };
+
+    let schedule = [*minute, *hour, *day, *month, *weekday]
+        .join(" ")
+        .parse()
+        .map_err(|e| format!("invalid schedule: {}", e))?;
+    let text = text.trim();
+
+    if text.is_empty() {
+        return Err("usage: /every <minute hour day month weekday> <text>".to_string());
+    }
+
+    Ok(ScheduleCommand::Every {
+        schedule,
+        text: text.to_string(),
+    })
+}
+
+/// Parses delays like `45s`, `10m`, `2h` or `1d12h`.
+pub fn parse_delay(delay: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut number = String::new();
+
+    for c in delay.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let value: i64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'd' => Duration::try_days(value)?,
+            'h' => Duration::try_hours(value)?,
+            'm' => Duration::try_minutes(value)?,
+            's' => Duration::try_seconds(value)?,
+            _ => return None,
+        };
+    }
+
+    (number.is_empty() && total > Duration::zero()).then_some(total)
+}
+
+impl Scheduler {
+    /// Runs a schedule command sent in a chat, returning the reply to send back. Returns `None`
+    /// for messages that are not schedule commands.
+    ///
+    /// Listing and cancelling only see the messages scheduled for the same chat.
+    pub fn handle_command(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        text: &str,
+    ) -> Option<String> {
+        let command = match ScheduleCommand::parse(text)? {
+            Ok(command) => command,
+            Err(usage) => return Some(usage),
+        };
+
+        let reply = match command {
+            ScheduleCommand::Remind { delay, text } => {
+                match self.send_at(chat_type, chat_id, text_message(text), Utc::now() + delay) {
+                    Ok(scheduled) => format!(
+                        "Reminder {} set for {}",
+                        scheduled.id,
+                        format_time(scheduled.next_run)
+                    ),
+                    Err(e) => format!("Could not set the reminder: {}", e),
+                }
+            }
+            ScheduleCommand::Every { schedule, text } => {
+                match self.send_every(chat_type, chat_id, text_message(text), schedule) {
+                    Ok(scheduled) => format!(
+                        "Scheduled {}, next at {}",
+                        scheduled.id,
+                        format_time(scheduled.next_run)
+                    ),
+                    Err(e) => format!("Could not schedule the message: {}", e),
+                }
+            }
+            ScheduleCommand::List => {
+                let lines: Vec<String> = self
+                    .list()
+                    .iter()
+                    .filter(|message| message.chat_type == chat_type && message.chat_id == chat_id)
+                    .map(describe)
+                    .collect();
+
+                if lines.is_empty() {
+                    "Nothing is scheduled in this chat".to_string()
+                } else {
+                    lines.join("\n")
+                }
+            }
+            ScheduleCommand::Cancel(id) => {
+                let in_chat = self.list().iter().any(|message| {
+                    message.id == id && message.chat_type == chat_type && message.chat_id == chat_id
+                });
+
+                match in_chat.then(|| self.cancel(id)) {
+                    Some(Ok(_)) => format!("Cancelled {}", id),
+                    Some(Err(e)) => format!("Could not cancel {}: {}", id, e),
+                    None => format!("Nothing scheduled as {} in this chat", id),
+                }
+            }
+        };
+
+        Some(reply)
+    }
+}
+
+fn text_message(text: String) -> ComposedMessage {
+    ComposedMessage {
+        file_path: None,
+        quoted_item_id: None,
+        msg_content: MsgContent::Text(MCText { text }),
+    }
+}
+
+fn describe(message: &ScheduledMessage) -> String {
+    let when = match &message.recurrence {
+        Recurrence::Once => format_time(message.next_run),
+        Recurrence::Cron { schedule } => format!(
+            "every `{}`, next at {}",
+            schedule,
+            format_time(message.next_run)
+        ),
+    };
+
+    format!(
+        "{}: {}: {}",
+        message.id,
+        when,
+        message.message.msg_content.text()
+    )
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ScheduleCommand::parse("/remind 1h30m  stand up"),
+            Some(Ok(ScheduleCommand::Remind {
+                delay: Duration::minutes(90),
+                text: "stand up".to_string()
+            }))
+        );
+        assert_eq!(
+            ScheduleCommand::parse("/every 0 9 * * 1-5 good morning, team"),
+            Some(Ok(ScheduleCommand::Every {
+                schedule: "0 9 * * 1-5".parse().unwrap(),
+                text: "good morning, team".to_string()
+            }))
+        );
+        assert_eq!(
+            ScheduleCommand::parse("/unschedule 3"),
+            Some(Ok(ScheduleCommand::Cancel(3)))
+        );
+        assert!(matches!(
+            ScheduleCommand::parse("/remind soon hi"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            ScheduleCommand::parse("/every 0 9 * * good morning"),
+            Some(Err(_))
+        ));
+        assert_eq!(ScheduleCommand::parse("remind me /remind"), None);
+        assert_eq!(ScheduleCommand::parse("/reminders"), None);
+
+        assert_eq!(parse_delay("1d2h"), Some(Duration::hours(26)));
+        assert_eq!(parse_delay("10"), None);
+        assert_eq!(parse_delay("0m"), None);
+    }
+
+    #[test]
+    fn test_handle_command() {
+        let scheduler = Scheduler::in_memory();
+
+        let reply = scheduler
+            .handle_command(ChatInfoType::Direct, 2, "/remind 10m stretch")
+            .unwrap();
+        assert!(reply.starts_with("Reminder 1 set for "));
+        scheduler
+            .handle_command(ChatInfoType::Group, 7, "/every 0 9 * * * hello")
+            .unwrap();
+
+        let list = scheduler
+            .handle_command(ChatInfoType::Direct, 2, "/schedules")
+            .unwrap();
+        assert!(list.starts_with("1: ") && list.ends_with(": stretch"));
+
+        // Other chats' schedules cannot be cancelled.
+        assert_eq!(
+            scheduler.handle_command(ChatInfoType::Direct, 2, "/unschedule 2"),
+            Some("Nothing scheduled as 2 in this chat".to_string())
+        );
+        assert_eq!(
+            scheduler.handle_command(ChatInfoType::Group, 7, "/unschedule 2"),
+            Some("Cancelled 2".to_string())
+        );
+        assert_eq!(
+            scheduler.handle_command(ChatInfoType::Group, 7, "/schedules"),
+            Some("Nothing is scheduled in this chat".to_string())
+        );
+        assert_eq!(
+            scheduler.handle_command(ChatInfoType::Group, 7, "hello"),
+            None
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Cron expressions: `minute hour day-of-month month day-of-week`, evaluated in UTC.
+
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How far ahead [`CronSchedule::next_after`] looks before deciding an expression never fires,
+/// as with `0 0 31 2 *`.
+const SEARCH_DAYS: i64 = 366 * 5;
+
+/// A parsed cron expression.
+///
+/// Fields take `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`) and lists of those
+/// (`1,15,30`). Days of the week run from 0 (Sunday) to 6, and 7 is Sunday too. As in cron, when
+/// both day fields are restricted a day matching either one fires.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    source: String,
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// The first time the schedule fires strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after + Duration::minutes(1);
+        let start_time = NaiveTime::from_hms_opt(start.hour(), start.minute(), 0)?;
+        let mut date = start.date_naive();
+
+        for day in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                let from = if day == 0 { start_time } else { NaiveTime::MIN };
+                if let Some(time) = self.first_time_from(from) {
+                    return Some(Utc.from_utc_datetime(&date.and_time(time)));
+                }
+            }
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    fn matches_date(&self, date: chrono::NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    fn first_time_from(&self, from: NaiveTime) -> Option<NaiveTime> {
+        (from.hour()..24)
+            .filter(|hour| self.hours & (1 << hour) != 0)
+            .find_map(|hour| {
+                let first_minute = if hour == from.hour() {
+                    from.minute()
+                } else {
+                    0
+                };
+                (first_minute..60)
+                    .find(|minute| self.minutes & (1 << minute) != 0)
+                    .and_then(|minute| NaiveTime::from_hms_opt(hour, minute, 0))
+            })
+    }
+}
+
+/// Parses one field into a bitset of the values it allows. Returns whether it was `*` too.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<(u64, bool), String> {
+    let mut bits = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step in {}", part))?;
+                if step == 0 {
+                    return Err(format!("step must not be 0 in {}", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // `5/10` means every 10 starting at 5.
+            (value, if step > 1 { max } else { value })
+        };
+
+        if start > end {
+            return Err(format!("range {} is backwards", range));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok((bits, field == "*"))
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    match value.parse() {
+        Ok(n) if (min..=max).contains(&n) => Ok(n),
+        _ => Err(format!("{} is not between {} and {}", value, min, max)),
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+
+        let (minutes, _) = parse_field(minute, 0, 59)?;
+        let (hours, _) = parse_field(hour, 0, 23)?;
+        let (days, any_day) = parse_field(day, 1, 31)?;
+        let (months, _) = parse_field(month, 1, 12)?;
+        let (mut weekdays, any_weekday) = parse_field(weekday, 0, 7)?;
+        // Sunday can be written as 0 or 7.
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+
+        Ok(CronSchedule {
+            source: fields.join(" "),
+            minutes,
+            hours: hours as u32,
+            days: days as u32,
+            months: months as u16,
+            weekdays: (weekdays & 0x7f) as u8,
+            any_day,
+            any_weekday,
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> Option<DateTime<Utc>> {
+        expression
+            .parse::<CronSchedule>()
+            .unwrap()
+            .next_after(at(after))
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(
+            next("*/15 * * * *", "2025-01-01T10:07:30Z"),
+            Some(at("2025-01-01T10:15:00Z"))
+        );
+        assert_eq!(
+            next("0 9 * * 1-5", "2025-01-03T09:00:00Z"),
+            // The 3rd is a Friday, so the next weekday is Monday the 6th.
+            Some(at("2025-01-06T09:00:00Z"))
+        );
+        assert_eq!(
+            next("30 23 31 * *", "2025-02-01T00:00:00Z"),
+            Some(at("2025-03-31T23:30:00Z"))
+        );
+        // Either day field matching is enough when both are restricted.
+        assert_eq!(
+            next("0 0 13 * 5", "2025-06-01T00:00:00Z"),
+            Some(at("2025-06-06T00:00:00Z"))
+        );
+        assert_eq!(
+            next("0 12 * * 7", "2025-01-01T00:00:00Z"),
+            Some(at("2025-01-05T12:00:00Z"))
+        );
+        assert_eq!(next("0 0 30 2 *", "2025-01-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!("* * * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("5-1 * * * *".parse::<CronSchedule>().is_err());
+        assert!("* * 0 * *".parse::<CronSchedule>().is_err());
+    }
+}
@@ -0,0 +1,521 @@
+//! Sending messages later: once at a given time, or repeatedly on a cron schedule.
+//!
+//! The schedule is kept in a JSON file so it survives restarts. Run [`Scheduler::run`] next to
+//! the message stream, and pass incoming messages to [`Scheduler::handle_command`] to let chat
+//! members manage their own reminders.
+
+use std::{fmt, fs, io, path::PathBuf, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use super::{
+    client::ChatClient, commands::ComposedMessage, error::CommandError, response::ChatInfoType,
+    utils::write_atomically,
+};
+
+pub mod bot;
+pub mod cron;
+
+use cron::CronSchedule;
+
+pub type ScheduleId = u64;
+
+/// The longest [`Scheduler::run`] sleeps before checking the schedule again, so that changes in
+/// the system clock are noticed.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Recurrence {
+    Once,
+    Cron { schedule: CronSchedule },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledMessage {
+    pub id: ScheduleId,
+    pub chat_type: ChatInfoType,
+    pub chat_id: u64,
+    pub message: ComposedMessage,
+    pub recurrence: Recurrence,
+    pub next_run: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The outcome of sending a scheduled message.
+#[derive(Debug)]
+pub struct Dispatch {
+    pub id: ScheduleId,
+    pub chat_type: ChatInfoType,
+    pub chat_id: u64,
+    pub result: Result<(), CommandError>,
+}
+
+#[derive(Debug)]
+pub enum ScheduleError {
+    InvalidSchedule(String),
+    Io(String),
+    InvalidFormat(String),
+}
+
+impl From<io::Error> for ScheduleError {
+    fn from(err: io::Error) -> Self {
+        ScheduleError::Io(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ScheduleError {
+    fn from(err: serde_json::Error) -> Self {
+        ScheduleError::InvalidFormat(err.to_string())
+    }
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::InvalidSchedule(msg) => {
+                write!(f, "ScheduleError: invalid schedule: {}", msg)
+            }
+            ScheduleError::Io(msg) => write!(f, "ScheduleError: io error: {}", msg),
+            ScheduleError::InvalidFormat(msg) => {
+                write!(f, "ScheduleError: invalid format: {}", msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Schedule {
+    next_id: ScheduleId,
+    messages: Vec<ScheduledMessage>,
+}
+
+pub struct Scheduler {
+    path: Option<PathBuf>,
+    schedule: Mutex<Schedule>,
+    changed: Notify,
+}
+
+impl Scheduler {
+    /// Loads the schedule saved at `path`, starting an empty one if the file does not exist.
+    ///
+    /// One-off messages that came due while nothing was running are sent on the next dispatch.
+    /// Recurring messages skip the runs they missed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ScheduleError> {
+        let path = path.into();
+        let mut schedule: Schedule = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Schedule::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let now = Utc::now();
+        schedule
+            .messages
+            .retain_mut(|message| match &message.recurrence {
+                Recurrence::Once => true,
+                Recurrence::Cron { schedule } if message.next_run < now => {
+                    match schedule.next_after(now) {
+                        Some(next_run) => {
+                            message.next_run = next_run;
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                Recurrence::Cron { .. } => true,
+            });
+
+        Ok(Self {
+            path: Some(path),
+            schedule: Mutex::new(schedule),
+            changed: Notify::new(),
+        })
+    }
+
+    /// A schedule that is lost when the scheduler is dropped.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            schedule: Mutex::new(Schedule::default()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Sends `message` once, at `at`. Times in the past send on the next dispatch.
+    pub fn send_at(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        message: ComposedMessage,
+        at: DateTime<Utc>,
+    ) -> Result<ScheduledMessage, ScheduleError> {
+        self.add(chat_type, chat_id, message, Recurrence::Once, at)
+    }
+
+    /// Sends `message` every time `schedule` fires, from now on.
+    pub fn send_every(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        message: ComposedMessage,
+        schedule: CronSchedule,
+    ) -> Result<ScheduledMessage, ScheduleError> {
+        let next_run = schedule
+            .next_after(Utc::now())
+            .ok_or_else(|| ScheduleError::InvalidSchedule(format!("{} never fires", schedule)))?;
+
+        self.add(
+            chat_type,
+            chat_id,
+            message,
+            Recurrence::Cron { schedule },
+            next_run,
+        )
+    }
+
+    /// Removes a scheduled message, returning it if it existed.
+    pub fn cancel(&self, id: ScheduleId) -> Result<Option<ScheduledMessage>, ScheduleError> {
+        let mut schedule = self.schedule.lock().unwrap();
+        let Some(index) = schedule
+            .messages
+            .iter()
+            .position(|message| message.id == id)
+        else {
+            return Ok(None);
+        };
+
+        let mut updated = schedule.clone();
+        let removed = updated.messages.remove(index);
+        self.save(&updated)?;
+        *schedule = updated;
+
+        self.changed.notify_one();
+        Ok(Some(removed))
+    }
+
+    /// Every scheduled message, the next to be sent first.
+    pub fn list(&self) -> Vec<ScheduledMessage> {
+        let mut messages = self.schedule.lock().unwrap().messages.clone();
+        messages.sort_by_key(|message| (message.next_run, message.id));
+        messages
+    }
+
+    /// Sends the messages due at `now`.
+    ///
+    /// The schedule is advanced and saved before sending, so a crash while sending loses the
+    /// message instead of sending it twice after a restart.
+    pub async fn dispatch_due(
+        &self,
+        client: &ChatClient,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Dispatch>, ScheduleError> {
+        let due = self.take_due(now)?;
+        let mut dispatches = Vec::with_capacity(due.len());
+
+        for message in due {
+            let result = client
+                .send_messages(message.chat_type, message.chat_id, &[message.message])
+                .await
+                .map(|_| ());
+
+            dispatches.push(Dispatch {
+                id: message.id,
+                chat_type: message.chat_type,
+                chat_id: message.chat_id,
+                result,
+            });
+        }
+
+        Ok(dispatches)
+    }
+
+    /// Sends scheduled messages as they come due, until the schedule cannot be saved.
+    ///
+    /// Every message sent is passed to `on_dispatch`, so that failed sends can be reported; they
+    /// are not retried. Sending goes through requests, so the client's message stream must be
+    /// read elsewhere.
+    pub async fn run(
+        &self,
+        client: &ChatClient,
+        mut on_dispatch: impl FnMut(Dispatch),
+    ) -> Result<(), ScheduleError> {
+        loop {
+            let now = Utc::now();
+            for dispatch in self.dispatch_due(client, now).await? {
+                on_dispatch(dispatch);
+            }
+
+            let wait = self
+                .next_run()
+                .map(|next_run| (next_run - now).to_std().unwrap_or_default())
+                .unwrap_or(MAX_SLEEP)
+                .min(MAX_SLEEP);
+
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.changed.notified() => {}
+            }
+        }
+    }
+
+    fn next_run(&self) -> Option<DateTime<Utc>> {
+        let schedule = self.schedule.lock().unwrap();
+        schedule
+            .messages
+            .iter()
+            .map(|message| message.next_run)
+            .min()
+    }
+
+    fn add(
+        &self,
+        chat_type: ChatInfoType,
+        chat_id: u64,
+        message: ComposedMessage,
+        recurrence: Recurrence,
+        next_run: DateTime<Utc>,
+    ) -> Result<ScheduledMessage, ScheduleError> {
+        let mut schedule = self.schedule.lock().unwrap();
+        let mut updated = schedule.clone();
+        updated.next_id += 1;
+
+        let scheduled = ScheduledMessage {
+            id: updated.next_id,
+            chat_type,
+            chat_id,
+            message,
+            recurrence,
+            next_run,
+            created_at: Utc::now(),
+        };
+        updated.messages.push(scheduled.clone());
+
+        self.save(&updated)?;
+        *schedule = updated;
+
+        self.changed.notify_one();
+        Ok(scheduled)
+    }
+
+    fn take_due(&self, now: DateTime<Utc>) -> Result<Vec<ScheduledMessage>, ScheduleError> {
+        let mut schedule = self.schedule.lock().unwrap();
+        let mut updated = schedule.clone();
+        let mut due = Vec::new();
+
+        updated.messages.retain_mut(|message| {
+            if message.next_run > now {
+                return true;
+            }
+            due.push(message.clone());
+
+            match &message.recurrence {
+                Recurrence::Once => false,
+                Recurrence::Cron { schedule } => match schedule.next_after(now) {
+                    Some(next_run) => {
+                        message.next_run = next_run;
+                        true
+                    }
+                    None => false,
+                },
+            }
+        });
+
+        if !due.is_empty() {
+            self.save(&updated)?;
+            *schedule = updated;
+        }
+        due.sort_by_key(|message| (message.next_run, message.id));
+        Ok(due)
+    }
+
+    fn save(&self, schedule: &Schedule) -> Result<(), ScheduleError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        write_atomically(path, &serde_json::to_vec_pretty(schedule)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Duration as ChronoDuration;
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::{
+        fixtures::{direct_item, mock_server, user},
+        response::{MCText, MsgContent},
+    };
+
+    fn text(text: &str) -> ComposedMessage {
+        ComposedMessage {
+            file_path: None,
+            quoted_item_id: None,
+            msg_content: MsgContent::Text(MCText {
+                text: text.to_string(),
+            }),
+        }
+    }
+
+    async fn client() -> (ChatClient, Arc<Mutex<Vec<String>>>) {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&commands);
+        let url = mock_server(move |cmd: &str| -> Value {
+            log.lock().unwrap().push(cmd.to_string());
+            json!({
+                "type": "newChatItems",
+                "user": user(),
+                "chatItems": [direct_item(2, 1, "directSnd", "sent", json!({ "type": "sndNew" }))]
+            })
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+        (client, commands)
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_due() {
+        let (client, commands) = client().await;
+        let scheduler = Scheduler::in_memory();
+        let now = Utc::now();
+
+        let later = scheduler
+            .send_at(
+                ChatInfoType::Direct,
+                2,
+                text("later"),
+                now + ChronoDuration::hours(1),
+            )
+            .unwrap();
+        scheduler
+            .send_at(
+                ChatInfoType::Group,
+                7,
+                text("now"),
+                now - ChronoDuration::seconds(1),
+            )
+            .unwrap();
+        let every = scheduler
+            .send_every(
+                ChatInfoType::Direct,
+                2,
+                text("tick"),
+                "* * * * *".parse().unwrap(),
+            )
+            .unwrap();
+
+        let dispatches = scheduler
+            .dispatch_due(&client, now + ChronoDuration::minutes(2))
+            .await
+            .unwrap();
+        assert_eq!(dispatches.len(), 2);
+        assert!(dispatches.iter().all(|dispatch| dispatch.result.is_ok()));
+        assert_eq!(
+            commands.lock().unwrap().as_slice(),
+            [
+                r#"/_send #7 json [{"msgContent":{"type":"text","text":"now"}}]"#,
+                r#"/_send @2 json [{"msgContent":{"type":"text","text":"tick"}}]"#,
+            ]
+        );
+
+        // The one-off message is gone and the recurring one moved on.
+        let remaining = scheduler.list();
+        assert_eq!(
+            remaining
+                .iter()
+                .map(|message| message.id)
+                .collect::<Vec<_>>(),
+            vec![every.id, later.id]
+        );
+        assert!(remaining[0].next_run > now + ChronoDuration::minutes(2));
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_failed_sends() {
+        let url = mock_server(|_: &str| -> Value {
+            json!({ "type": "chatCmdError", "chatError": { "type": "error" } })
+        })
+        .await;
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+
+        let scheduler = Scheduler::in_memory();
+        let message = scheduler
+            .send_at(ChatInfoType::Direct, 2, text("hello"), Utc::now())
+            .unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::select! {
+            result = scheduler.run(&client, |dispatch| tx.send(dispatch).unwrap()) => {
+                panic!("run stopped: {:?}", result)
+            }
+            dispatch = rx.recv() => {
+                let dispatch = dispatch.unwrap();
+                assert_eq!(dispatch.id, message.id);
+                assert!(dispatch.result.is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_save_keeps_schedule() {
+        let path = std::env::temp_dir()
+            .join(format!("muchat-missing-{}", std::process::id()))
+            .join("schedule.json");
+
+        let scheduler = Scheduler::open(&path).unwrap();
+        let result = scheduler.send_at(ChatInfoType::Direct, 2, text("lost"), Utc::now());
+        assert!(matches!(result, Err(ScheduleError::Io(_))));
+        assert!(scheduler.list().is_empty());
+    }
+
+    #[test]
+    fn test_persists_across_restarts() {
+        let path = std::env::temp_dir().join(format!("muchat-schedule-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let scheduler = Scheduler::open(&path).unwrap();
+        let at = Utc::now() + ChronoDuration::days(1);
+        let first = scheduler
+            .send_at(ChatInfoType::Direct, 2, text("first"), at)
+            .unwrap();
+        let second = scheduler
+            .send_every(
+                ChatInfoType::Group,
+                7,
+                text("weekly"),
+                "0 9 * * 1".parse().unwrap(),
+            )
+            .unwrap();
+        scheduler.cancel(first.id).unwrap();
+        drop(scheduler);
+
+        let reopened = Scheduler::open(&path).unwrap();
+        let messages = reopened.list();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, second.id);
+        assert_eq!(messages[0].message.msg_content.text(), "weekly");
+
+        // Ids keep counting up, so a cancelled id is never reused.
+        let third = reopened
+            .send_at(ChatInfoType::Direct, 2, text("third"), at)
+            .unwrap();
+        assert_eq!(third.id, 3);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,5 +1,24 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
 use super::response::{CIContent, MsgContent};
 
+/// Writes a new file next to `path` and moves it into place, so a crash never leaves half of
+/// one.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
 pub fn extract_text_content(content: CIContent) -> Option<String> {
     match content {
         CIContent::SndMsgContent(msg) => extract_text_from_msg_content(msg.msg_content),
@@ -8,12 +27,43 @@ pub fn extract_text_content(content: CIContent) -> Option<String> {
     }
 }
 
+/// Returns the text of a message, or its caption for media and files. Media and files sent
+/// without a caption have no text.
 pub fn extract_text_from_msg_content(msg_content: MsgContent) -> Option<String> {
     match msg_content {
         MsgContent::Text(mc_text) => Some(mc_text.text),
         MsgContent::Link(mc_link) => Some(mc_link.text),
-        MsgContent::File(mc_file) => Some(mc_file.text),
         MsgContent::Unknown(mc_unknown) => Some(mc_unknown.text),
-        MsgContent::Image(_) => None,
+        other => Some(other.text().to_string()).filter(|caption| !caption.is_empty()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_captions() {
+        let caption =
+            |payload| extract_text_from_msg_content(serde_json::from_value(payload).unwrap());
+
+        assert_eq!(
+            caption(json!({ "type": "image", "text": "sunset", "image": "data:image/png;base64," })),
+            Some("sunset".to_string())
+        );
+        assert_eq!(
+            caption(json!({ "type": "voice", "text": "listen", "duration": 3 })),
+            Some("listen".to_string())
+        );
+        assert_eq!(
+            caption(json!({ "type": "voice", "text": "", "duration": 3 })),
+            None
+        );
+        assert_eq!(
+            caption(json!({ "type": "text", "text": "" })),
+            Some(String::new())
+        );
     }
 }
@@ -0,0 +1,805 @@
+//! Forwarding chat events to an HTTP endpoint, so bots can be written in any language.
+//!
+//! Each event is POSTed as a JSON [`WebhookPayload`] signed with HMAC-SHA256. Failed deliveries
+//! are retried with exponential backoff and then written to a dead-letter file, from which they
+//! can be replayed. A non-empty body in a successful response is sent back to the chat as a
+//! reply.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures::{pin_mut, Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::{mpsc, Mutex};
+
+use super::{
+    client::{ChatClient, StreamMessage},
+    response::{ChatInfoType, ChatResponse, DirectionType},
+    utils::write_atomically,
+};
+
+pub const EVENT_HEADER: &str = "x-muchat-event";
+pub const TIMESTAMP_HEADER: &str = "x-muchat-timestamp";
+/// `sha256=<hex>`, the HMAC-SHA256 of `<timestamp>.<body>` keyed with the webhook secret.
+pub const SIGNATURE_HEADER: &str = "x-muchat-signature";
+
+/// How many payloads can wait for delivery before reading the message stream pauses.
+const QUEUE_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookEvent {
+    /// Messages received in any chat. Messages sent by the user are not forwarded.
+    NewChatItems,
+    ReceivedContactRequest,
+    ContactConnected,
+    /// A received file finished downloading.
+    FileComplete,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 4] = [
+        WebhookEvent::NewChatItems,
+        WebhookEvent::ReceivedContactRequest,
+        WebhookEvent::ContactConnected,
+        WebhookEvent::FileComplete,
+    ];
+
+    pub fn of(response: &ChatResponse) -> Option<Self> {
+        match response {
+            ChatResponse::NewChatItems { .. } => Some(WebhookEvent::NewChatItems),
+            ChatResponse::ReceivedContactRequest { .. } => Some(WebhookEvent::ReceivedContactRequest),
+            ChatResponse::ContactConnected { .. } => Some(WebhookEvent::ContactConnected),
+            ChatResponse::RcvFileComplete { .. } => Some(WebhookEvent::FileComplete),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WebhookEvent::NewChatItems => "newChatItems",
+            WebhookEvent::ReceivedContactRequest => "receivedContactRequest",
+            WebhookEvent::ContactConnected => "contactConnected",
+            WebhookEvent::FileComplete => "fileComplete",
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WebhookEvent::ALL
+            .into_iter()
+            .find(|event| event.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown webhook event: {}", s))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key for the signature header. Receivers should reject payloads it does not verify.
+    pub secret: String,
+    pub events: HashSet<WebhookEvent>,
+    /// Deliveries are given up after this many attempts.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each retry after it.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub timeout: Duration,
+    /// JSON Lines file holding payloads that could not be delivered.
+    pub dead_letters: PathBuf,
+}
+
+impl WebhookConfig {
+    /// Forwards every event, making up to 5 attempts over about 15 seconds.
+    pub fn new(
+        url: impl Into<String>,
+        secret: impl Into<String>,
+        dead_letters: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            secret: secret.into(),
+            events: WebhookEvent::ALL.into_iter().collect(),
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            timeout: Duration::from_secs(10),
+            dead_letters: dead_letters.into(),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// The body of a webhook request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    /// Unique per forwarder, so receivers can drop payloads delivered twice.
+    pub id: String,
+    pub event: WebhookEvent,
+    pub created_at: DateTime<Utc>,
+    /// The simplex-chat response the event came from.
+    pub response: ChatResponse,
+}
+
+/// A payload that could not be delivered.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetter {
+    pub payload: WebhookPayload,
+    pub attempts: u32,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    pub delivered: usize,
+    /// Dead letters that failed again and were kept.
+    pub failed: usize,
+}
+
+#[derive(Debug)]
+pub enum WebhookError {
+    Http(String),
+    /// The endpoint answered with a status other than 2xx.
+    Status(u16),
+    Io(String),
+    InvalidFormat(String),
+}
+
+impl WebhookError {
+    /// Whether trying again later could succeed. Client errors other than timeouts and rate
+    /// limits mean the payload itself was refused.
+    fn is_retryable(&self) -> bool {
+        match self {
+            WebhookError::Http(_) => true,
+            WebhookError::Status(status) => *status >= 500 || *status == 408 || *status == 429,
+            WebhookError::Io(_) | WebhookError::InvalidFormat(_) => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for WebhookError {
+    fn from(err: reqwest::Error) -> Self {
+        WebhookError::Http(err.to_string())
+    }
+}
+
+impl From<io::Error> for WebhookError {
+    fn from(err: io::Error) -> Self {
+        WebhookError::Io(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for WebhookError {
+    fn from(err: serde_json::Error) -> Self {
+        WebhookError::InvalidFormat(err.to_string())
+    }
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookError::Http(msg) => write!(f, "WebhookError: request failed: {}", msg),
+            WebhookError::Status(status) => {
+                write!(f, "WebhookError: endpoint answered {}", status)
+            }
+            WebhookError::Io(msg) => write!(f, "WebhookError: io error: {}", msg),
+            WebhookError::InvalidFormat(msg) => {
+                write!(f, "WebhookError: invalid format: {}", msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+/// The signature header value for a body sent at `timestamp`, in unix seconds.
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        hex::encode(mac(secret, timestamp, body).finalize().into_bytes())
+    )
+}
+
+/// Checks a signature header value, in constant time.
+pub fn verify(secret: &[u8], timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let Some(Ok(expected)) = signature.strip_prefix("sha256=").map(hex::decode) else {
+        return false;
+    };
+
+    mac(secret, timestamp, body).verify_slice(&expected).is_ok()
+}
+
+fn mac(secret: &[u8], timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// The chat a reply to an event goes to. Contact requests have no chat until accepted, and
+/// messages from several chats have no single one; [`Forwarder::payloads`] splits those by chat.
+pub fn reply_target(response: &ChatResponse) -> Option<(ChatInfoType, u64)> {
+    match response {
+        ChatResponse::NewChatItems { chat_items, .. } => {
+            let mut chats = chat_items
+                .iter()
+                .map(|item| (item.chat_info.chat_type(), item.chat_info.chat_id()));
+            let first = chats.next()?;
+            chats.all(|chat| chat == first).then_some(first)
+        }
+        ChatResponse::ContactConnected { contact, .. } => {
+            Some((ChatInfoType::Direct, contact.contact_id))
+        }
+        ChatResponse::RcvFileComplete { chat_item, .. } => Some((
+            chat_item.chat_info.chat_type(),
+            chat_item.chat_info.chat_id(),
+        )),
+        _ => None,
+    }
+}
+
+pub struct Forwarder {
+    config: WebhookConfig,
+    http: reqwest::Client,
+    next_id: AtomicU64,
+    /// Serializes reads and writes of the dead-letter file.
+    dead_letters: Mutex<()>,
+}
+
+impl Forwarder {
+    pub fn new(config: WebhookConfig) -> Result<Self, WebhookError> {
+        let http = reqwest::Client::builder().timeout(config.timeout).build()?;
+
+        Ok(Self {
+            config,
+            http,
+            next_id: AtomicU64::new(0),
+            dead_letters: Mutex::new(()),
+        })
+    }
+
+    /// The payloads for a response, if it is one of the configured events. Messages received in
+    /// several chats at once get a payload per chat, so each reply goes to the right one.
+    pub fn payloads(&self, response: &ChatResponse) -> Vec<WebhookPayload> {
+        let Some(event) = WebhookEvent::of(response) else {
+            return vec![];
+        };
+        if !self.config.events.contains(&event) {
+            return vec![];
+        }
+
+        let responses = match response {
+            ChatResponse::NewChatItems { user, chat_items } => {
+                let mut chats: Vec<((ChatInfoType, u64), Vec<_>)> = Vec::new();
+                let received = chat_items.iter().filter(|item| {
+                    !matches!(
+                        item.chat_item.chat_dir.direction_type,
+                        DirectionType::DirectSnd | DirectionType::GroupSnd
+                    )
+                });
+                for item in received {
+                    let chat = (item.chat_info.chat_type(), item.chat_info.chat_id());
+                    match chats.iter_mut().find(|(key, _)| *key == chat) {
+                        Some((_, items)) => items.push(item.clone()),
+                        None => chats.push((chat, vec![item.clone()])),
+                    }
+                }
+
+                chats
+                    .into_iter()
+                    .map(|(_, chat_items)| ChatResponse::NewChatItems {
+                        user: user.clone(),
+                        chat_items,
+                    })
+                    .collect()
+            }
+            other => vec![other.clone()],
+        };
+
+        responses
+            .into_iter()
+            .map(|response| {
+                let created_at = Utc::now();
+                WebhookPayload {
+                    id: format!(
+                        "{}-{}",
+                        created_at.timestamp_millis(),
+                        self.next_id.fetch_add(1, Ordering::Relaxed)
+                    ),
+                    event,
+                    created_at,
+                    response,
+                }
+            })
+            .collect()
+    }
+
+    /// Delivers a payload, retrying failures. Returns the reply in the response body, if any.
+    pub async fn deliver(&self, payload: &WebhookPayload) -> Result<Option<String>, WebhookError> {
+        self.deliver_counting(payload)
+            .await
+            .map_err(|(error, _)| error)
+    }
+
+    /// Delivers a payload and sends the reply, if any, to the chat it came from. Payloads that
+    /// cannot be delivered are written to the dead-letter file.
+    pub async fn forward(
+        &self,
+        client: &ChatClient,
+        payload: WebhookPayload,
+    ) -> Result<(), WebhookError> {
+        match self.deliver_counting(&payload).await {
+            Ok(reply) => {
+                self.reply(client, &payload, reply).await;
+                Ok(())
+            }
+            Err((error, attempts)) => {
+                self.push_dead_letter(DeadLetter {
+                    payload,
+                    attempts,
+                    error: error.to_string(),
+                    failed_at: Utc::now(),
+                })
+                .await?;
+                Err(error)
+            }
+        }
+    }
+
+    pub async fn dead_letters(&self) -> Result<Vec<DeadLetter>, WebhookError> {
+        let _guard = self.dead_letters.lock().await;
+        self.read_dead_letters()
+    }
+
+    /// Tries to deliver every dead letter again, keeping the ones that still fail.
+    ///
+    /// Payloads that fail to be forwarded meanwhile wait for the replay to finish before they are
+    /// added to the file.
+    pub async fn replay_dead_letters(
+        &self,
+        client: &ChatClient,
+    ) -> Result<ReplayReport, WebhookError> {
+        let _guard = self.dead_letters.lock().await;
+        let letters = self.read_dead_letters()?;
+        let mut report = ReplayReport::default();
+        let mut failed = Vec::new();
+
+        for mut letter in letters {
+            match self.deliver_counting(&letter.payload).await {
+                Ok(reply) => {
+                    self.reply(client, &letter.payload, reply).await;
+                    report.delivered += 1;
+                }
+                Err((error, attempts)) => {
+                    letter.attempts += attempts;
+                    letter.error = error.to_string();
+                    letter.failed_at = Utc::now();
+                    failed.push(letter);
+                }
+            }
+        }
+        report.failed = failed.len();
+
+        let mut contents = Vec::new();
+        for letter in &failed {
+            serde_json::to_writer(&mut contents, letter)?;
+            contents.push(b'\n');
+        }
+        write_atomically(&self.config.dead_letters, &contents)?;
+
+        Ok(report)
+    }
+
+    async fn deliver_counting(
+        &self,
+        payload: &WebhookPayload,
+    ) -> Result<Option<String>, (WebhookError, u32)> {
+        let body = serde_json::to_vec(payload).map_err(|e| (e.into(), 0))?;
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            match self.attempt(payload.event, &body).await {
+                Ok(reply) => return Ok(reply),
+                Err(error) if error.is_retryable() && attempt < self.config.max_attempts => {
+                    tokio::time::sleep(self.config.backoff(attempt)).await;
+                }
+                Err(error) => return Err((error, attempt)),
+            }
+        }
+    }
+
+    async fn attempt(
+        &self,
+        event: WebhookEvent,
+        body: &[u8],
+    ) -> Result<Option<String>, WebhookError> {
+        let timestamp = Utc::now().timestamp();
+        let response = self
+            .http
+            .post(&self.config.url)
+            .header("content-type", "application/json")
+            .header(EVENT_HEADER, event.name())
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                sign(self.config.secret.as_bytes(), timestamp, body),
+            )
+            .body(body.to_vec())
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(WebhookError::Status(status.as_u16()));
+        }
+
+        let reply = response.text().await?;
+        let reply = reply.trim();
+        Ok((!reply.is_empty()).then(|| reply.to_string()))
+    }
+
+    async fn reply(&self, client: &ChatClient, payload: &WebhookPayload, reply: Option<String>) {
+        if let (Some(reply), Some((chat_type, chat_id))) = (reply, reply_target(&payload.response)) {
+            let _ = client.send_text(chat_type, chat_id, reply).await;
+        }
+    }
+
+    async fn push_dead_letter(&self, letter: DeadLetter) -> Result<(), WebhookError> {
+        let _guard = self.dead_letters.lock().await;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.dead_letters)?;
+
+        let mut line = serde_json::to_vec(&letter)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    fn read_dead_letters(&self) -> Result<Vec<DeadLetter>, WebhookError> {
+        let file = match fs::File::open(&self.config.dead_letters) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+
+        BufReader::new(file)
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+}
+
+/// Forwards events from the message stream until it ends.
+///
+/// Payloads are delivered one at a time, in order, while the stream keeps being read so that
+/// requests made on `client` still get their responses.
+pub async fn forward_events<S>(client: Arc<ChatClient>, message_stream: S, forwarder: Arc<Forwarder>)
+where
+    S: Stream<Item = StreamMessage>,
+{
+    let (sender, mut receiver) = mpsc::channel::<WebhookPayload>(QUEUE_SIZE);
+
+    let delivering = Arc::clone(&forwarder);
+    let worker = tokio::spawn(async move {
+        while let Some(payload) = receiver.recv().await {
+            let _ = delivering.forward(&client, payload).await;
+        }
+    });
+
+    pin_mut!(message_stream);
+    'stream: while let Some(message) = message_stream.next().await {
+        let Ok(message) = message else {
+            continue;
+        };
+
+        for payload in forwarder.payloads(&message.resp) {
+            if sender.send(payload).await.is_err() {
+                break 'stream;
+            }
+        }
+    }
+
+    drop(sender);
+    let _ = worker.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicUsize, Mutex};
+
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::chat::fixtures::{direct_item, mock_server, response, user};
+
+    /// An HTTP endpoint answering the n-th request with `replies[n]`, or the last reply after
+    /// that. Returns its url and the bodies it received.
+    async fn endpoint(replies: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&received);
+        let count = AtomicUsize::new(0);
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+
+                // Read the headers, then as much body as they announce.
+                let body = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length: usize = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|value| value.trim().parse().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        let signature = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("x-muchat-signature: "))
+                            .unwrap()
+                            .to_string();
+                        let timestamp: i64 = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("x-muchat-timestamp: "))
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        assert!(verify(b"secret", timestamp, body.as_bytes(), &signature));
+                        break body.to_string();
+                    }
+                };
+                log.lock()
+                    .unwrap()
+                    .push(serde_json::from_str(&body).unwrap());
+
+                let n = count.fetch_add(1, Ordering::SeqCst);
+                let (status, reply) = replies[n.min(replies.len() - 1)];
+                let answer = format!(
+                    "HTTP/1.1 {} X\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+                socket.write_all(answer.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, received)
+    }
+
+    async fn chat_client() -> (ChatClient, Arc<Mutex<Vec<String>>>) {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&commands);
+        let url = mock_server(move |cmd| {
+            log.lock().unwrap().push(cmd.to_string());
+            json!({ "type": "cmdOk" })
+        })
+        .await;
+
+        let (client, stream) = ChatClient::new(url).await.unwrap();
+        tokio::spawn(Box::pin(stream.await).for_each(|_| async {}));
+        (client, commands)
+    }
+
+    fn config(url: &str, dead_letters: PathBuf) -> WebhookConfig {
+        WebhookConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_attempts: 3,
+            ..WebhookConfig::new(url, "secret", dead_letters)
+        }
+    }
+
+    fn dead_letter_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "muchat-webhook-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn received_message(text: &str) -> ChatResponse {
+        response(json!({
+            "type": "newChatItems",
+            "user": user(),
+            "chatItems": [
+                direct_item(2, 1, "directSnd", "sent by the bot", json!({ "type": "sndNew" })),
+                direct_item(2, 2, "directRcv", text, json!({ "type": "rcvNew" })),
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_signature() {
+        let signature = sign(b"secret", 1700000000, b"{}");
+
+        assert!(signature.starts_with("sha256="));
+        assert!(verify(b"secret", 1700000000, b"{}", &signature));
+        assert!(!verify(b"secret", 1700000001, b"{}", &signature));
+        assert!(!verify(b"other", 1700000000, b"{}", &signature));
+        assert!(!verify(b"secret", 1700000000, b"{}", "sha256=zz"));
+    }
+
+    #[test]
+    fn test_payload_filters_events() {
+        let mut config = WebhookConfig::new("http://localhost", "secret", "/dev/null");
+        config.events = [WebhookEvent::NewChatItems].into_iter().collect();
+        let forwarder = Forwarder::new(config).unwrap();
+
+        let payload = forwarder.payloads(&received_message("hello")).remove(0);
+        let ChatResponse::NewChatItems { chat_items, .. } = &payload.response else {
+            panic!("unexpected response {:?}", payload.response);
+        };
+        assert_eq!(chat_items.len(), 1);
+        assert_eq!(chat_items[0].chat_item.meta.item_id, 2);
+
+        let only_sent = response(json!({
+            "type": "newChatItems",
+            "user": user(),
+            "chatItems": [direct_item(2, 1, "directSnd", "hi", json!({ "type": "sndNew" }))]
+        }));
+        assert!(forwarder.payloads(&only_sent).is_empty());
+
+        let connected = response(json!({ "type": "chatStopped" }));
+        assert!(forwarder.payloads(&connected).is_empty());
+    }
+
+    #[test]
+    fn test_payloads_split_by_chat() {
+        let forwarder = Forwarder::new(WebhookConfig::new(
+            "http://localhost",
+            "secret",
+            "/dev/null",
+        ))
+        .unwrap();
+        let mixed = response(json!({
+            "type": "newChatItems",
+            "user": user(),
+            "chatItems": [
+                direct_item(2, 1, "directRcv", "hi", json!({ "type": "rcvNew" })),
+                direct_item(3, 2, "directRcv", "hello", json!({ "type": "rcvNew" })),
+                direct_item(2, 3, "directRcv", "anyone?", json!({ "type": "rcvNew" })),
+            ]
+        }));
+        assert_eq!(reply_target(&mixed), None);
+
+        let payloads = forwarder.payloads(&mixed);
+        let targets: Vec<_> = payloads
+            .iter()
+            .map(|payload| reply_target(&payload.response))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                Some((ChatInfoType::Direct, 2)),
+                Some((ChatInfoType::Direct, 3))
+            ]
+        );
+        let ChatResponse::NewChatItems { chat_items, .. } = &payloads[0].response else {
+            panic!("unexpected response {:?}", payloads[0].response);
+        };
+        assert_eq!(chat_items.len(), 2);
+        assert_ne!(payloads[0].id, payloads[1].id);
+    }
+
+    #[tokio::test]
+    async fn test_forward_retries_and_replies() {
+        let (url, received) = endpoint(vec![(503, ""), (200, "  pong  ")]).await;
+        let (client, commands) = chat_client().await;
+        let forwarder = Forwarder::new(config(&url, dead_letter_path("replies"))).unwrap();
+
+        let payload = forwarder.payloads(&received_message("ping")).remove(0);
+        forwarder.forward(&client, payload).await.unwrap();
+
+        {
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 2);
+            assert_eq!(received[0]["event"], "newChatItems");
+            assert_eq!(received[0]["id"], received[1]["id"]);
+        }
+
+        // The reply is sent without waiting for a response, so give it a moment to arrive.
+        for _ in 0..50 {
+            if !commands.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            commands.lock().unwrap().as_slice(),
+            [r#"/_send @2 json [{"msgContent":{"type":"text","text":"pong"}}]"#]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_and_replay() {
+        let (url, received) =
+            endpoint(vec![(500, ""), (500, ""), (500, ""), (400, ""), (204, "")]).await;
+        let (client, _) = chat_client().await;
+        let path = dead_letter_path("replay");
+        let forwarder = Forwarder::new(config(&url, path.clone())).unwrap();
+
+        // Three attempts fail with a server error.
+        let first = forwarder.payloads(&received_message("one")).remove(0);
+        assert!(matches!(
+            forwarder.forward(&client, first).await,
+            Err(WebhookError::Status(500))
+        ));
+        // A client error is not retried.
+        let second = forwarder.payloads(&received_message("two")).remove(0);
+        assert!(matches!(
+            forwarder.forward(&client, second).await,
+            Err(WebhookError::Status(400))
+        ));
+        assert_eq!(received.lock().unwrap().len(), 4);
+
+        let letters = forwarder.dead_letters().await.unwrap();
+        assert_eq!(
+            letters
+                .iter()
+                .map(|letter| letter.attempts)
+                .collect::<Vec<_>>(),
+            vec![3, 1]
+        );
+
+        let report = forwarder.replay_dead_letters(&client).await.unwrap();
+        assert_eq!(
+            report,
+            ReplayReport {
+                delivered: 2,
+                failed: 0
+            }
+        );
+        assert!(forwarder.dead_letters().await.unwrap().is_empty());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1 +1,3 @@
 pub mod chat;
+#[cfg(feature = "store")]
+pub mod store;
@@ -0,0 +1,33 @@
+use std::fmt;
+
+use rusqlite::Error as SqliteError;
+use serde_json::Error as SerdeJsonError;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Database(String),
+    InvalidData(String),
+}
+
+impl From<SqliteError> for StoreError {
+    fn from(err: SqliteError) -> Self {
+        StoreError::Database(err.to_string())
+    }
+}
+
+impl From<SerdeJsonError> for StoreError {
+    fn from(err: SerdeJsonError) -> Self {
+        StoreError::InvalidData(err.to_string())
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Database(msg) => write!(f, "StoreError: database error: {}", msg),
+            StoreError::InvalidData(msg) => write!(f, "StoreError: invalid data: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
@@ -0,0 +1,552 @@
+//! Local persistence of chat history, contacts and groups, fed from the message stream.
+//!
+//! simplex-chat keeps its own database, but bots have no way to query it. [`MessageStore`] keeps a
+//! copy of everything that passes through [`MessageStore::apply`] in a SQLite file, so that
+//! history survives restarts and can be queried by chat, direction and time.
+
+use std::{path::Path, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, types::Value, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::chat::response::{
+    AChatItem, ChatInfo, ChatInfoType, ChatItem, ChatItemId, ChatResponse, Contact, DirectionType,
+    GroupInfo,
+};
+use crate::chat::utils::extract_text_content;
+
+mod error;
+mod search;
+
+pub use error::StoreError;
+pub use search::{SearchHit, SearchQuery};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS contacts (
+        contact_id INTEGER PRIMARY KEY,
+        local_display_name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS groups (
+        group_id INTEGER PRIMARY KEY,
+        local_display_name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS chat_items (
+        item_id INTEGER PRIMARY KEY,
+        chat_type TEXT NOT NULL,
+        chat_id INTEGER NOT NULL,
+        sent INTEGER NOT NULL,
+        group_member_id INTEGER,
+        item_ts INTEGER NOT NULL,
+        item_text TEXT NOT NULL,
+        deleted INTEGER NOT NULL DEFAULT 0,
+        data TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_chat_items_chat ON chat_items (chat_type, chat_id, item_ts);
+    CREATE INDEX IF NOT EXISTS idx_chat_items_ts ON chat_items (item_ts);
+
+    CREATE VIRTUAL TABLE IF NOT EXISTS chat_items_fts USING fts5(
+        item_text,
+        content = 'chat_items',
+        content_rowid = 'item_id',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+
+    CREATE TRIGGER IF NOT EXISTS chat_items_fts_insert AFTER INSERT ON chat_items BEGIN
+        INSERT INTO chat_items_fts (rowid, item_text) VALUES (new.item_id, new.item_text);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS chat_items_fts_delete AFTER DELETE ON chat_items BEGIN
+        INSERT INTO chat_items_fts (chat_items_fts, rowid, item_text)
+        VALUES ('delete', old.item_id, old.item_text);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS chat_items_fts_update AFTER UPDATE OF item_text ON chat_items BEGIN
+        INSERT INTO chat_items_fts (chat_items_fts, rowid, item_text)
+        VALUES ('delete', old.item_id, old.item_text);
+        INSERT INTO chat_items_fts (rowid, item_text) VALUES (new.item_id, new.item_text);
+    END;
+";
+
+/// A chat item together with the chat it belongs to.
+#[derive(Clone, Debug)]
+pub struct StoredItem {
+    pub chat_type: ChatInfoType,
+    pub chat_id: u64,
+    pub chat_item: ChatItem,
+    /// The item was deleted in simplex-chat. Its last known content is kept.
+    pub deleted: bool,
+}
+
+/// Filters for [`MessageStore::items`]. The default matches every item that was not deleted.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ItemQuery {
+    pub chat: Option<(ChatInfoType, u64)>,
+    /// Only items sent by the user (`true`) or received by them (`false`).
+    pub sent: Option<bool>,
+    /// Only items received from this group member.
+    pub group_member_id: Option<u64>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub include_deleted: bool,
+    /// Return only the most recent items.
+    pub limit: Option<usize>,
+}
+
+pub struct MessageStore {
+    conn: Mutex<Connection>,
+}
+
+impl MessageStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, StoreError> {
+        let has_index = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE name = 'chat_items_fts'",
+                [],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+
+        conn.execute_batch(SCHEMA)?;
+
+        // Stores created before the search index existed need their items indexed once.
+        if !has_index {
+            conn.execute(
+                "INSERT INTO chat_items_fts (chat_items_fts) VALUES ('rebuild')",
+                [],
+            )?;
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Records everything relevant in a response: new, edited and deleted items, status and
+    /// reaction changes, and the contacts and groups they belong to.
+    pub fn apply(&self, response: &ChatResponse) -> Result<(), StoreError> {
+        match response {
+            ChatResponse::NewChatItems { chat_items, .. }
+            | ChatResponse::ChatItemsStatusesUpdated { chat_items, .. } => {
+                for item in chat_items {
+                    self.save_item(item)?;
+                }
+            }
+            ChatResponse::ChatItemUpdated { chat_item, .. }
+            | ChatResponse::ChatItemStatusUpdated { chat_item, .. } => self.save_item(chat_item)?,
+            ChatResponse::ChatItemReaction { reaction, .. } => self.save_item(&AChatItem {
+                chat_info: reaction.chat_info.clone(),
+                chat_item: reaction.chat_reaction.chat_item.clone(),
+            })?,
+            ChatResponse::ChatItemsDeleted {
+                chat_item_deletions,
+                ..
+            } => {
+                for deletion in chat_item_deletions {
+                    self.mark_deleted(deletion.deleted_chat_item.chat_item.meta.item_id)?;
+                }
+            }
+            ChatResponse::ChatItemDeleted {
+                deleted_chat_item, ..
+            } => self.mark_deleted(deleted_chat_item.chat_item.meta.item_id)?,
+            ChatResponse::AcceptingContactRequest { contact, .. }
+            | ChatResponse::ContactSndReady { contact, .. }
+            | ChatResponse::ContactConnected { contact, .. }
+            | ChatResponse::ContactDeletedByContact { contact, .. } => self.save_contact(contact)?,
+            ChatResponse::ContactUpdated { to_contact, .. } => self.save_contact(to_contact)?,
+            ChatResponse::GroupUpdated { to_group, .. } => self.save_group(to_group)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Inserts or replaces an item, keeping its deleted flag if it was already deleted.
+    pub fn save_item(&self, item: &AChatItem) -> Result<(), StoreError> {
+        match &item.chat_info {
+            ChatInfo::Direct(direct) => self.save_contact(&direct.contact)?,
+            ChatInfo::Group(group) => self.save_group(&group.group_info)?,
+            ChatInfo::ContactRequest(_) => {}
+        }
+
+        let meta = &item.chat_item.meta;
+        let sent = matches!(
+            item.chat_item.chat_dir.direction_type,
+            DirectionType::DirectSnd | DirectionType::GroupSnd
+        );
+        let group_member_id = item
+            .chat_item
+            .chat_dir
+            .group_member
+            .as_ref()
+            .map(|member| member.group_member_id);
+        let item_text = extract_text_content(item.chat_item.content.clone())
+            .unwrap_or_else(|| meta.item_text.clone());
+
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO chat_items
+                (item_id, chat_type, chat_id, sent, group_member_id, item_ts, item_text, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (item_id) DO UPDATE SET
+                item_ts = excluded.item_ts,
+                item_text = excluded.item_text,
+                data = excluded.data",
+            params![
+                meta.item_id,
+                chat_type_name(item.chat_info.chat_type()),
+                item.chat_info.chat_id(),
+                sent,
+                group_member_id,
+                meta.item_ts.timestamp_millis(),
+                item_text,
+                serde_json::to_string(&item.chat_item)?,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn mark_deleted(&self, item_id: ChatItemId) -> Result<(), StoreError> {
+        self.conn.lock().unwrap().execute(
+            "UPDATE chat_items SET deleted = 1 WHERE item_id = ?1",
+            params![item_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn save_contact(&self, contact: &Contact) -> Result<(), StoreError> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO contacts (contact_id, local_display_name, data)
+             VALUES (?1, ?2, ?3)",
+            params![
+                contact.contact_id,
+                contact.local_display_name,
+                serde_json::to_string(contact)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn save_group(&self, group: &GroupInfo) -> Result<(), StoreError> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO groups (group_id, local_display_name, data)
+             VALUES (?1, ?2, ?3)",
+            params![
+                group.group_id,
+                group.local_display_name,
+                serde_json::to_string(group)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn contact(&self, contact_id: u64) -> Result<Option<Contact>, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let data: Option<String> = conn
+            .query_row(
+                "SELECT data FROM contacts WHERE contact_id = ?1",
+                params![contact_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+    }
+
+    pub fn contacts(&self) -> Result<Vec<Contact>, StoreError> {
+        self.load_all("SELECT data FROM contacts ORDER BY local_display_name")
+    }
+
+    pub fn group(&self, group_id: u64) -> Result<Option<GroupInfo>, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let data: Option<String> = conn
+            .query_row(
+                "SELECT data FROM groups WHERE group_id = ?1",
+                params![group_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+    }
+
+    pub fn groups(&self) -> Result<Vec<GroupInfo>, StoreError> {
+        self.load_all("SELECT data FROM groups ORDER BY local_display_name")
+    }
+
+    pub fn item(&self, item_id: ChatItemId) -> Result<Option<StoredItem>, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn
+            .query_row(
+                "SELECT chat_type, chat_id, deleted, data FROM chat_items WHERE item_id = ?1",
+                params![item_id],
+                read_item_row,
+            )
+            .optional()?;
+
+        row.map(stored_item).transpose()
+    }
+
+    /// Items matching `query`, oldest first.
+    pub fn items(&self, query: &ItemQuery) -> Result<Vec<StoredItem>, StoreError> {
+        let (conditions, values) = filters(query);
+
+        let mut sql = "SELECT chat_type, chat_id, deleted, data FROM chat_items".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY item_ts DESC, item_id DESC");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(&sql)?;
+        let rows = statement
+            .query_map(params_from_iter(values), read_item_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut items = rows
+            .into_iter()
+            .map(stored_item)
+            .collect::<Result<Vec<_>, _>>()?;
+        items.reverse();
+        Ok(items)
+    }
+
+    fn load_all<T: serde::de::DeserializeOwned>(&self, sql: &str) -> Result<Vec<T>, StoreError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(sql)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.iter()
+            .map(|data| Ok(serde_json::from_str(data)?))
+            .collect()
+    }
+}
+
+/// The SQL conditions selecting the items matched by `query`, and their parameters.
+fn filters(query: &ItemQuery) -> (Vec<&'static str>, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some((chat_type, chat_id)) = query.chat {
+        conditions.push("chat_type = ? AND chat_id = ?");
+        values.push(Value::Text(chat_type_name(chat_type).to_string()));
+        values.push(Value::Integer(chat_id as i64));
+    }
+    if let Some(sent) = query.sent {
+        conditions.push("sent = ?");
+        values.push(Value::Integer(sent as i64));
+    }
+    if let Some(group_member_id) = query.group_member_id {
+        conditions.push("group_member_id = ?");
+        values.push(Value::Integer(group_member_id as i64));
+    }
+    if let Some(since) = query.since {
+        conditions.push("item_ts >= ?");
+        values.push(Value::Integer(since.timestamp_millis()));
+    }
+    if let Some(until) = query.until {
+        conditions.push("item_ts < ?");
+        values.push(Value::Integer(until.timestamp_millis()));
+    }
+    if !query.include_deleted {
+        conditions.push("deleted = 0");
+    }
+
+    (conditions, values)
+}
+
+type ItemRow = (String, u64, bool, String);
+
+fn read_item_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ItemRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+}
+
+fn stored_item((chat_type, chat_id, deleted, data): ItemRow) -> Result<StoredItem, StoreError> {
+    Ok(StoredItem {
+        chat_type: parse_chat_type(&chat_type)?,
+        chat_id,
+        chat_item: serde_json::from_str(&data)?,
+        deleted,
+    })
+}
+
+fn chat_type_name(chat_type: ChatInfoType) -> &'static str {
+    match chat_type {
+        ChatInfoType::Direct => "direct",
+        ChatInfoType::Group => "group",
+        ChatInfoType::ContactRequest => "contactRequest",
+    }
+}
+
+fn parse_chat_type(name: &str) -> Result<ChatInfoType, StoreError> {
+    match name {
+        "direct" => Ok(ChatInfoType::Direct),
+        "group" => Ok(ChatInfoType::Group),
+        "contactRequest" => Ok(ChatInfoType::ContactRequest),
+        other => Err(StoreError::InvalidData(format!(
+            "unknown chat type {}",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::fixtures::{direct_item, group_item, response, user};
+
+    fn new_items(items: Vec<Value>) -> ChatResponse {
+        response(json!({ "type": "newChatItems", "user": user(), "chatItems": items }))
+    }
+
+    fn received(item_id: u64, text: &str, ts: &str) -> Value {
+        let mut item = direct_item(2, item_id, "directRcv", text, json!({ "type": "rcvNew" }));
+        item["chatItem"]["meta"]["itemTs"] = json!(ts);
+        item
+    }
+
+    #[test]
+    fn test_store_items_and_chats() {
+        let store = MessageStore::open_in_memory().unwrap();
+
+        store
+            .apply(&new_items(vec![
+                received(1, "old news", "2024-12-01T09:00:00Z"),
+                received(2, "hello", "2024-12-18T09:00:00Z"),
+                direct_item(2, 3, "directSnd", "hi!", json!({ "type": "sndNew" })),
+                group_item(7, 4, "groupRcv", "team update", json!({ "type": "rcvNew" })),
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            store.contact(2).unwrap().unwrap().local_display_name,
+            "alice"
+        );
+        assert_eq!(store.groups().unwrap()[0].group_id, 7);
+
+        let week_ago = "2024-12-20T10:00:00Z".parse::<DateTime<Utc>>().unwrap() - Duration::days(7);
+        let last_week = store
+            .items(&ItemQuery {
+                chat: Some((ChatInfoType::Direct, 2)),
+                sent: Some(false),
+                since: Some(week_ago),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(last_week.len(), 1);
+        assert_eq!(last_week[0].chat_item.meta.item_text, "hello");
+
+        let latest = store
+            .items(&ItemQuery {
+                limit: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        let ids: Vec<u64> = latest
+            .iter()
+            .map(|item| item.chat_item.meta.item_id)
+            .collect();
+        assert_eq!(ids, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_store_applies_updates_and_deletions() {
+        let store = MessageStore::open_in_memory().unwrap();
+        store
+            .apply(&new_items(vec![direct_item(
+                2,
+                10,
+                "directSnd",
+                "helo",
+                json!({ "type": "sndNew" }),
+            )]))
+            .unwrap();
+
+        let mut edited = direct_item(2, 10, "directSnd", "hello", json!({ "type": "sndNew" }));
+        edited["chatItem"]["meta"]["itemEdited"] = json!(true);
+        store
+            .apply(&response(json!({
+                "type": "chatItemUpdated",
+                "user": user(),
+                "chatItem": edited
+            })))
+            .unwrap();
+
+        let item = store.item(10).unwrap().unwrap();
+        assert_eq!(item.chat_item.meta.item_text, "hello");
+        assert!(item.chat_item.meta.item_edited);
+
+        store
+            .apply(&response(json!({
+                "type": "chatItemsStatusesUpdated",
+                "user": user(),
+                "chatItems": [direct_item(
+                    2,
+                    10,
+                    "directSnd",
+                    "hello",
+                    json!({ "type": "sndRcvd", "msgRcptStatus": "ok", "sndProgress": "complete" })
+                )]
+            })))
+            .unwrap();
+        assert!(store
+            .item(10)
+            .unwrap()
+            .unwrap()
+            .chat_item
+            .meta
+            .item_status
+            .is_delivered(true));
+
+        store
+            .apply(&response(json!({
+                "type": "chatItemsDeleted",
+                "user": user(),
+                "chatItemDeletions": [{
+                    "deletedChatItem": direct_item(2, 10, "directSnd", "hello", json!({ "type": "sndNew" })),
+                    "toChatItem": null
+                }],
+                "byUser": false,
+                "timed": false
+            })))
+            .unwrap();
+
+        assert!(store.item(10).unwrap().unwrap().deleted);
+        assert!(store.items(&ItemQuery::default()).unwrap().is_empty());
+        assert_eq!(
+            store
+                .items(&ItemQuery {
+                    include_deleted: true,
+                    ..Default::default()
+                })
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
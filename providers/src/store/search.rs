@@ -0,0 +1,302 @@
+use std::ops::Range;
+
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params_from_iter, types::Value};
+use serde::{Deserialize, Serialize};
+
+use super::{filters, parse_chat_type, ItemQuery, MessageStore, StoreError};
+use crate::chat::response::{ChatInfoType, ChatItemId};
+
+const DEFAULT_LIMIT: usize = 50;
+
+/// How many tokens of context a snippet has around the matched terms.
+const SNIPPET_TOKENS: usize = 12;
+
+const HIGHLIGHT_START: char = '\u{1}';
+const HIGHLIGHT_END: char = '\u{2}';
+
+/// A full-text search over stored messages.
+///
+/// `text` is split into terms that must all appear in a message. Quoted text matches a phrase,
+/// and a term ending in `*` matches any word starting with it: `"see you" tomorr*`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Restricts the search to matching items. Its `limit` caps the number of hits, 50 if unset.
+    pub filter: ItemQuery,
+}
+
+impl SearchQuery {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub item_id: ChatItemId,
+    pub chat_type: ChatInfoType,
+    pub chat_id: u64,
+    pub sent: bool,
+    pub item_ts: DateTime<Utc>,
+    /// The part of the message around the matched terms.
+    pub snippet: String,
+    /// Byte ranges of the matched terms within `snippet`.
+    pub highlights: Vec<Range<usize>>,
+    /// Relevance of the hit, higher is better.
+    pub score: f64,
+}
+
+impl MessageStore {
+    /// Messages matching `query`, most relevant first.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>, StoreError> {
+        let Some(expression) = match_expression(&query.text) else {
+            return Ok(vec![]);
+        };
+
+        let (mut conditions, filter_values) = filters(&query.filter);
+        conditions.insert(0, "chat_items_fts MATCH ?");
+
+        let mut values = vec![Value::Text(expression)];
+        values.extend(filter_values);
+        values.push(Value::Integer(
+            query.filter.limit.unwrap_or(DEFAULT_LIMIT) as i64
+        ));
+
+        let sql = format!(
+            "SELECT item_id, chat_type, chat_id, sent, item_ts,
+                    snippet(chat_items_fts, 0, '{}', '{}', '…', {}),
+                    bm25(chat_items_fts)
+             FROM chat_items_fts JOIN chat_items ON chat_items.item_id = chat_items_fts.rowid
+             WHERE {}
+             ORDER BY bm25(chat_items_fts), item_ts DESC
+             LIMIT ?",
+            HIGHLIGHT_START,
+            HIGHLIGHT_END,
+            SNIPPET_TOKENS,
+            conditions.join(" AND ")
+        );
+
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(&sql)?;
+        let rows = statement
+            .query_map(params_from_iter(values), |row| {
+                Ok((
+                    row.get::<_, ChatItemId>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u64>(2)?,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, f64>(6)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(
+                |(item_id, chat_type, chat_id, sent, item_ts, snippet, rank)| {
+                    let (snippet, highlights) = split_highlights(&snippet);
+
+                    Ok(SearchHit {
+                        item_id,
+                        chat_type: parse_chat_type(&chat_type)?,
+                        chat_id,
+                        sent,
+                        item_ts: Utc.timestamp_millis_opt(item_ts).single().ok_or_else(|| {
+                            StoreError::InvalidData(format!("invalid timestamp {}", item_ts))
+                        })?,
+                        snippet,
+                        highlights,
+                        // bm25 ranks better matches lower.
+                        score: -rank,
+                    })
+                },
+            )
+            .collect()
+    }
+}
+
+/// Builds an FTS5 match expression out of the user's search text, quoting every term so that
+/// characters with a special meaning in FTS5 are matched literally.
+fn match_expression(text: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|c| *c != '"').collect();
+            if !phrase.trim().is_empty() {
+                terms.push(quote(phrase.trim()));
+            }
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+
+            let prefix = word.ends_with('*');
+            let word = word.trim_end_matches('*');
+            if !word.is_empty() {
+                terms.push(if prefix {
+                    format!("{}*", quote(word))
+                } else {
+                    quote(word)
+                });
+            }
+        }
+    }
+
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn quote(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
+
+fn split_highlights(marked: &str) -> (String, Vec<Range<usize>>) {
+    let mut snippet = String::with_capacity(marked.len());
+    let mut highlights = Vec::new();
+    let mut start = None;
+
+    for c in marked.chars() {
+        match c {
+            HIGHLIGHT_START => start = Some(snippet.len()),
+            HIGHLIGHT_END => {
+                if let Some(start) = start.take() {
+                    highlights.push(start..snippet.len());
+                }
+            }
+            c => snippet.push(c),
+        }
+    }
+
+    (snippet, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::chat::{
+        fixtures::{direct_item, group_item, response, user},
+        response::ChatResponse,
+    };
+
+    fn store_with(items: Vec<Value>) -> MessageStore {
+        let store = MessageStore::open_in_memory().unwrap();
+        store
+            .apply(&response(json!({
+                "type": "newChatItems",
+                "user": user(),
+                "chatItems": items
+            })))
+            .unwrap();
+        store
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<ChatItemId> {
+        hits.iter().map(|hit| hit.item_id).collect()
+    }
+
+    #[test]
+    fn test_match_expression() {
+        assert_eq!(
+            match_expression(r#""see you" tomorr* a"b"#).as_deref(),
+            Some(r#""see you" "tomorr"* "a" "b""#)
+        );
+        assert_eq!(
+            match_expression("NEAR(x) OR -y").as_deref(),
+            Some(r#""NEAR(x)" "OR" "-y""#)
+        );
+        assert_eq!(match_expression("  * \"\" "), None);
+    }
+
+    #[test]
+    fn test_search_phrases_and_prefixes() {
+        let rcv = json!({ "type": "rcvNew" });
+        let store = store_with(vec![
+            direct_item(
+                2,
+                1,
+                "directRcv",
+                "See you tomorrow at the café",
+                rcv.clone(),
+            ),
+            direct_item(2, 2, "directRcv", "you will see tomorrow", rcv.clone()),
+            group_item(7, 3, "groupRcv", "tomorrowland tickets", rcv.clone()),
+        ]);
+
+        assert_eq!(
+            ids(&store.search(&SearchQuery::new("\"see you\"")).unwrap()),
+            vec![1]
+        );
+
+        let mut prefix = ids(&store.search(&SearchQuery::new("tomorr*")).unwrap());
+        prefix.sort();
+        assert_eq!(prefix, vec![1, 2, 3]);
+
+        // Diacritics are ignored.
+        let hits = store.search(&SearchQuery::new("cafe")).unwrap();
+        assert_eq!(ids(&hits), vec![1]);
+        let hit = &hits[0];
+        assert_eq!(&hit.snippet[hit.highlights[0].clone()], "café");
+
+        let in_group = store
+            .search(&SearchQuery {
+                text: "tomorr*".to_string(),
+                filter: ItemQuery {
+                    chat: Some((ChatInfoType::Group, 7)),
+                    ..Default::default()
+                },
+            })
+            .unwrap();
+        assert_eq!(ids(&in_group), vec![3]);
+    }
+
+    #[test]
+    fn test_search_follows_edits_and_deletions() {
+        let store = store_with(vec![direct_item(
+            2,
+            1,
+            "directSnd",
+            "meeting on monday",
+            json!({ "type": "sndNew" }),
+        )]);
+
+        let edited: ChatResponse = response(json!({
+            "type": "chatItemUpdated",
+            "user": user(),
+            "chatItem": direct_item(2, 1, "directSnd", "meeting on friday", json!({ "type": "sndNew" }))
+        }));
+        store.apply(&edited).unwrap();
+
+        assert!(store
+            .search(&SearchQuery::new("monday"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            ids(&store.search(&SearchQuery::new("friday")).unwrap()),
+            vec![1]
+        );
+
+        store.mark_deleted(1).unwrap();
+        assert!(store
+            .search(&SearchQuery::new("friday"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,646 @@
+//! The state of the terminal client, and how it changes with key presses and chat events.
+
+use chrono::{DateTime, Utc};
+use muchat_providers::chat::{
+    matcher::fuzzy_match,
+    response::{
+        AChatItem, Chat, ChatInfo, ChatInfoType, ChatItem, ChatItemId, ChatResponse, DirectionType,
+        MsgReaction,
+    },
+    utils::extract_text_content,
+};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Reactions offered by the reaction picker, the same set simplex-chat accepts.
+pub const REACTIONS: [&str; 8] = ["👍", "👎", "😀", "😂", "😢", "❤️", "🚀", "✅"];
+
+pub type ChatKey = (ChatInfoType, u64);
+
+pub struct ChatEntry {
+    pub chat_info: ChatInfo,
+    pub unread: u32,
+    pub last_ts: Option<DateTime<Utc>>,
+    pub preview: String,
+}
+
+impl ChatEntry {
+    pub fn key(&self) -> ChatKey {
+        (self.chat_info.chat_type(), self.chat_info.chat_id())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Mode {
+    Normal,
+    Compose,
+    Reply(ChatItemId),
+    Edit(ChatItemId),
+    React(ChatItemId),
+    /// Picking a chat by name. `matches` are indexes into [`App::chats`].
+    Switch {
+        query: String,
+        matches: Vec<usize>,
+        selected: usize,
+    },
+}
+
+/// Something the client has to ask simplex-chat to do.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    OpenChat(ChatKey),
+    Send {
+        chat: ChatKey,
+        text: String,
+        quoted_item_id: Option<ChatItemId>,
+    },
+    Edit {
+        chat: ChatKey,
+        item_id: ChatItemId,
+        text: String,
+    },
+    React {
+        chat: ChatKey,
+        item_id: ChatItemId,
+        add: bool,
+        reaction: MsgReaction,
+    },
+    Quit,
+}
+
+pub struct App {
+    /// The display name of the user, shown as the sender of their messages.
+    pub user_name: String,
+    /// Chats, most recently active first.
+    pub chats: Vec<ChatEntry>,
+    pub current: Option<ChatKey>,
+    /// Items of the current chat, oldest first.
+    pub items: Vec<ChatItem>,
+    /// The item under the cursor. When unset the view follows the latest item.
+    pub selected: Option<usize>,
+    pub mode: Mode,
+    pub input: String,
+    pub status: String,
+}
+
+impl App {
+    pub fn new(user_name: String, chats: Vec<Chat>) -> Self {
+        let mut app = App {
+            user_name,
+            chats: chats
+                .into_iter()
+                .map(|chat| {
+                    let last = chat.chat_items.last();
+                    ChatEntry {
+                        unread: chat.chat_stats.unread_count,
+                        last_ts: last.map(|item| item.meta.item_ts),
+                        preview: last.map(item_text).unwrap_or_default(),
+                        chat_info: chat.chat_info,
+                    }
+                })
+                .collect(),
+            current: None,
+            items: vec![],
+            selected: None,
+            mode: Mode::Normal,
+            input: String::new(),
+            status: String::new(),
+        };
+        app.sort_chats();
+        app
+    }
+
+    pub fn current_entry(&self) -> Option<&ChatEntry> {
+        let current = self.current?;
+        self.chats.iter().find(|entry| entry.key() == current)
+    }
+
+    /// Shows the history of a chat that was just opened.
+    pub fn open(&mut self, chat: Chat) {
+        let key = (chat.chat_info.chat_type(), chat.chat_info.chat_id());
+        self.current = Some(key);
+        self.items = chat.chat_items;
+        self.selected = None;
+        self.mode = Mode::Normal;
+        self.input.clear();
+
+        if let Some(entry) = self.chats.iter_mut().find(|entry| entry.key() == key) {
+            entry.unread = 0;
+        }
+    }
+
+    pub fn apply(&mut self, response: &ChatResponse) {
+        match response {
+            ChatResponse::NewChatItems { chat_items, .. } => {
+                for item in chat_items {
+                    self.add_item(item);
+                }
+            }
+            ChatResponse::ChatItemsStatusesUpdated { chat_items, .. } => {
+                for item in chat_items {
+                    self.replace_item(item);
+                }
+            }
+            ChatResponse::ChatItemStatusUpdated { chat_item, .. }
+            | ChatResponse::ChatItemUpdated { chat_item, .. } => self.replace_item(chat_item),
+            ChatResponse::ChatItemReaction { reaction, .. } => self.replace_item(&AChatItem {
+                chat_info: reaction.chat_info.clone(),
+                chat_item: reaction.chat_reaction.chat_item.clone(),
+            }),
+            ChatResponse::ChatItemsDeleted {
+                chat_item_deletions,
+                ..
+            } => {
+                for deletion in chat_item_deletions {
+                    match &deletion.to_chat_item {
+                        Some(item) => self.replace_item(item),
+                        None => self.remove_item(&deletion.deleted_chat_item),
+                    }
+                }
+            }
+            ChatResponse::ChatItemDeleted {
+                deleted_chat_item,
+                to_chat_item,
+                ..
+            } => match to_chat_item {
+                Some(item) => self.replace_item(item),
+                None => self.remove_item(deleted_chat_item),
+            },
+            _ => {}
+        }
+    }
+
+    fn add_item(&mut self, item: &AChatItem) {
+        let key = (item.chat_info.chat_type(), item.chat_info.chat_id());
+        let is_current = self.current == Some(key);
+
+        match self.chats.iter_mut().find(|entry| entry.key() == key) {
+            Some(entry) => {
+                entry.chat_info = item.chat_info.clone();
+                entry.last_ts = Some(item.chat_item.meta.item_ts);
+                entry.preview = item_text(&item.chat_item);
+                if !is_current && !is_sent(&item.chat_item) {
+                    entry.unread += 1;
+                }
+            }
+            None => self.chats.push(ChatEntry {
+                chat_info: item.chat_info.clone(),
+                unread: if is_current || is_sent(&item.chat_item) {
+                    0
+                } else {
+                    1
+                },
+                last_ts: Some(item.chat_item.meta.item_ts),
+                preview: item_text(&item.chat_item),
+            }),
+        }
+        self.sort_chats();
+
+        if is_current {
+            self.items.push(item.chat_item.clone());
+        }
+    }
+
+    fn replace_item(&mut self, item: &AChatItem) {
+        if self.current != Some((item.chat_info.chat_type(), item.chat_info.chat_id())) {
+            return;
+        }
+
+        let item_id = item.chat_item.meta.item_id;
+        if let Some(existing) = self
+            .items
+            .iter_mut()
+            .find(|existing| existing.meta.item_id == item_id)
+        {
+            *existing = item.chat_item.clone();
+        }
+    }
+
+    fn remove_item(&mut self, item: &AChatItem) {
+        if self.current != Some((item.chat_info.chat_type(), item.chat_info.chat_id())) {
+            return;
+        }
+
+        let item_id = item.chat_item.meta.item_id;
+        self.items
+            .retain(|existing| existing.meta.item_id != item_id);
+        self.selected = self
+            .selected
+            .filter(|_| !self.items.is_empty())
+            .map(|selected| selected.min(self.items.len() - 1));
+    }
+
+    fn sort_chats(&mut self) {
+        self.chats
+            .sort_by_key(|entry| std::cmp::Reverse(entry.last_ts));
+    }
+
+    pub fn selected_item(&self) -> Option<&ChatItem> {
+        self.items.get(self.selected?)
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+
+        match self.mode {
+            Mode::Normal => self.handle_normal_key(key),
+            Mode::Compose | Mode::Reply(_) | Mode::Edit(_) => self.handle_input_key(key),
+            Mode::React(item_id) => self.handle_react_key(key, item_id),
+            Mode::Switch { .. } => self.handle_switch_key(key),
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Option<Action> {
+        self.status.clear();
+
+        match key.code {
+            KeyCode::Char('q') => return Some(Action::Quit),
+            KeyCode::Char('/') => self.start_switch(),
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.start_switch()
+            }
+            KeyCode::Tab => return self.cycle_chat(1),
+            KeyCode::BackTab => return self.cycle_chat(-1),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = match self.selected {
+                    Some(selected) => Some(selected.saturating_sub(1)),
+                    None => self.items.len().checked_sub(1),
+                };
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = self
+                    .selected
+                    .map(|selected| selected + 1)
+                    .filter(|selected| *selected < self.items.len());
+            }
+            KeyCode::Esc => self.selected = None,
+            KeyCode::Char('i') | KeyCode::Enter if self.current.is_some() => {
+                self.mode = Mode::Compose
+            }
+            KeyCode::Char('r') => {
+                if let Some(item) = self.selected_item() {
+                    self.mode = Mode::Reply(item.meta.item_id);
+                }
+            }
+            KeyCode::Char('e') => match self.selected_item() {
+                Some(item) if is_sent(item) && item.meta.editable => {
+                    let (text, item_id) = (item_text(item), item.meta.item_id);
+                    self.input = text;
+                    self.mode = Mode::Edit(item_id);
+                }
+                Some(_) => self.status = "only your own recent messages can be edited".into(),
+                None => {}
+            },
+            KeyCode::Char('+') => {
+                if let Some(item) = self.selected_item() {
+                    self.mode = Mode::React(item.meta.item_id);
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Enter => {
+                let text = self.input.trim().to_string();
+                let chat = self.current?;
+                if text.is_empty() {
+                    return None;
+                }
+
+                self.input.clear();
+                let action = match std::mem::replace(&mut self.mode, Mode::Normal) {
+                    Mode::Edit(item_id) => Action::Edit {
+                        chat,
+                        item_id,
+                        text,
+                    },
+                    Mode::Reply(item_id) => Action::Send {
+                        chat,
+                        text,
+                        quoted_item_id: Some(item_id),
+                    },
+                    _ => Action::Send {
+                        chat,
+                        text,
+                        quoted_item_id: None,
+                    },
+                };
+                self.selected = None;
+                return Some(action);
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn handle_react_key(&mut self, key: KeyEvent, item_id: ChatItemId) -> Option<Action> {
+        self.mode = Mode::Normal;
+
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+        let emoji = REACTIONS.get(c.to_digit(10)?.checked_sub(1)? as usize)?;
+        let chat = self.current?;
+        let reaction = MsgReaction::emoji(*emoji);
+
+        // Picking a reaction the user already gave takes it back.
+        let user_reacted = self
+            .items
+            .iter()
+            .find(|item| item.meta.item_id == item_id)
+            .is_some_and(|item| {
+                item.reactions
+                    .iter()
+                    .any(|count| count.reaction == reaction && count.user_reacted)
+            });
+
+        Some(Action::React {
+            chat,
+            item_id,
+            add: !user_reacted,
+            reaction,
+        })
+    }
+
+    fn handle_switch_key(&mut self, key: KeyEvent) -> Option<Action> {
+        let Mode::Switch {
+            query,
+            matches,
+            selected,
+        } = &mut self.mode
+        else {
+            return None;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Up => *selected = selected.saturating_sub(1),
+            KeyCode::Down => *selected = (*selected + 1).min(matches.len().saturating_sub(1)),
+            KeyCode::Enter => {
+                let index = *matches.get(*selected)?;
+                self.mode = Mode::Normal;
+                return Some(Action::OpenChat(self.chats[index].key()));
+            }
+            KeyCode::Backspace => {
+                query.pop();
+                let query = query.clone();
+                self.update_matches(query);
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                let query = query.clone();
+                self.update_matches(query);
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn start_switch(&mut self) {
+        self.mode = Mode::Switch {
+            query: String::new(),
+            matches: vec![],
+            selected: 0,
+        };
+        self.update_matches(String::new());
+    }
+
+    fn update_matches(&mut self, new_query: String) {
+        let names: Vec<ChatName> = self
+            .chats
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| ChatName {
+                index,
+                name: entry.chat_info.display_name(),
+            })
+            .collect();
+        let new_matches: Vec<usize> = fuzzy_match(&new_query, names)
+            .into_iter()
+            .map(|name| name.index)
+            .collect();
+
+        if let Mode::Switch {
+            query,
+            matches,
+            selected,
+        } = &mut self.mode
+        {
+            *query = new_query;
+            *matches = new_matches;
+            *selected = 0;
+        }
+    }
+
+    fn cycle_chat(&mut self, step: isize) -> Option<Action> {
+        if self.chats.is_empty() {
+            return None;
+        }
+
+        let position = self
+            .current
+            .and_then(|current| self.chats.iter().position(|entry| entry.key() == current))
+            .map(|position| position as isize + step)
+            .unwrap_or(0);
+        let next = position.rem_euclid(self.chats.len() as isize) as usize;
+
+        Some(Action::OpenChat(self.chats[next].key()))
+    }
+}
+
+struct ChatName<'a> {
+    index: usize,
+    name: &'a str,
+}
+
+impl AsRef<str> for ChatName<'_> {
+    fn as_ref(&self) -> &str {
+        self.name
+    }
+}
+
+pub fn is_sent(item: &ChatItem) -> bool {
+    matches!(
+        item.chat_dir.direction_type,
+        DirectionType::DirectSnd | DirectionType::GroupSnd
+    )
+}
+
+pub fn item_text(item: &ChatItem) -> String {
+    extract_text_content(item.content.clone()).unwrap_or_else(|| item.meta.item_text.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use muchat_providers::chat::fixtures::{contact, user};
+    use ratatui::crossterm::event::KeyEventKind;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn contact_info(contact_id: u64, name: &str) -> Value {
+        json!({ "type": "direct", "contact": contact(contact_id, name) })
+    }
+
+    fn item(item_id: u64, dir: &str, text: &str) -> Value {
+        let content_type = if dir.ends_with("Snd") {
+            "sndMsgContent"
+        } else {
+            "rcvMsgContent"
+        };
+
+        json!({
+            "chatDir": { "type": dir },
+            "meta": {
+                "itemId": item_id,
+                "itemTs": format!("2024-12-20T10:{:02}:00Z", item_id),
+                "itemText": text,
+                "itemStatus": { "type": "rcvNew" },
+                "createdAt": "2024-12-20T10:30:00Z",
+                "updatedAt": "2024-12-20T10:30:00Z",
+                "itemSharedMsgId": format!("msg-{}", item_id),
+                "itemEdited": false,
+                "editable": true,
+                "deletable": true
+            },
+            "content": { "type": content_type, "msgContent": { "type": "text", "text": text } },
+            "formattedText": null,
+            "quotedItem": null,
+            "reactions": []
+        })
+    }
+
+    fn chat(contact_id: u64, name: &str, items: Vec<Value>) -> Chat {
+        serde_json::from_value(json!({
+            "chatInfo": contact_info(contact_id, name),
+            "chatItems": items,
+            "chatStats": { "unreadCount": 1 }
+        }))
+        .unwrap()
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: ratatui::crossterm::event::KeyEventState::NONE,
+        }
+    }
+
+    fn app() -> App {
+        let mut app = App::new(
+            "bot".to_string(),
+            vec![
+                chat(2, "alice", vec![item(1, "directRcv", "hi")]),
+                chat(3, "bob", vec![item(2, "directRcv", "hey")]),
+            ],
+        );
+        app.open(chat(2, "alice", vec![item(1, "directRcv", "hi")]));
+        app
+    }
+
+    #[test]
+    fn test_new_items_update_chats() {
+        let mut app = app();
+        assert_eq!(app.chats[0].chat_info.display_name(), "bob");
+        assert_eq!(app.current_entry().unwrap().unread, 0);
+
+        let response: ChatResponse = serde_json::from_value(json!({
+            "type": "newChatItems",
+            "user": user(),
+            "chatItems": [
+                { "chatInfo": contact_info(3, "bob"), "chatItem": item(3, "directRcv", "ping") },
+                { "chatInfo": contact_info(2, "alice"), "chatItem": item(4, "directRcv", "more") }
+            ]
+        }))
+        .unwrap();
+        app.apply(&response);
+
+        // The open chat gets the item, the other one counts it as unread.
+        assert_eq!(app.items.len(), 2);
+        let bob = app.chats.iter().find(|entry| entry.key().1 == 3).unwrap();
+        assert_eq!(bob.unread, 2);
+        assert_eq!(bob.preview, "ping");
+        assert_eq!(app.chats[0].key().1, 2);
+    }
+
+    #[test]
+    fn test_reply_and_edit() {
+        let mut app = app();
+        app.items
+            .push(serde_json::from_value(item(5, "directSnd", "helo")).unwrap());
+
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Char('e')));
+        assert_eq!(app.mode, Mode::Edit(5));
+        assert_eq!(app.input, "helo");
+
+        app.handle_key(key(KeyCode::Backspace));
+        for c in "lo".chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(
+            app.handle_key(key(KeyCode::Enter)),
+            Some(Action::Edit {
+                chat: (ChatInfoType::Direct, 2),
+                item_id: 5,
+                text: "hello".to_string()
+            })
+        );
+
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Char('r')));
+        app.handle_key(key(KeyCode::Char('!')));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Enter)),
+            Some(Action::Send {
+                chat: (ChatInfoType::Direct, 2),
+                text: "!".to_string(),
+                quoted_item_id: Some(1)
+            })
+        );
+    }
+
+    #[test]
+    fn test_react_and_switch() {
+        let mut app = app();
+
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Char('+')));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('1'))),
+            Some(Action::React {
+                chat: (ChatInfoType::Direct, 2),
+                item_id: 1,
+                add: true,
+                reaction: MsgReaction::emoji("👍")
+            })
+        );
+
+        app.handle_key(key(KeyCode::Char('/')));
+        for c in "bo".chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(
+            app.handle_key(key(KeyCode::Enter)),
+            Some(Action::OpenChat((ChatInfoType::Direct, 3)))
+        );
+    }
+}
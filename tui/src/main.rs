@@ -0,0 +1,232 @@
+use std::{process::ExitCode, thread, time::Duration};
+
+use app::{Action, App};
+use clap::Parser;
+use futures::StreamExt;
+use muchat_providers::chat::{
+    client::ChatClient,
+    commands::ComposedMessage,
+    response::{ChatResponse, MCText, MsgContent},
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyEventKind},
+    DefaultTerminal,
+};
+use tokio::sync::mpsc;
+
+mod app;
+mod ui;
+
+/// How many items are loaded when a chat is opened.
+const HISTORY_SIZE: usize = 100;
+
+#[derive(Parser)]
+#[command(
+    name = "muchat-tui",
+    version,
+    about = "Terminal client for simplex-chat"
+)]
+struct Cli {
+    /// Websocket url of a running simplex-chat instance (`simplex-chat -p 5225`).
+    #[arg(long, env = "MUCHAT_SERVER", default_value = "ws://localhost:5225")]
+    server: String,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let (client, stream) = match ChatClient::new(cli.server.clone()).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("error: could not connect to {}: {:?}", cli.server, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // The stream has to be read for requests to get their responses, so it is read in the
+    // background and events are handed to the main loop through a channel.
+    let (event_sender, events) = mpsc::unbounded_channel();
+    let mut stream = Box::pin(stream.await);
+    tokio::spawn(async move {
+        while let Some(message) = stream.next().await {
+            if let Ok(message) = message {
+                if event_sender.send(message.resp).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut app = match load(&client).await {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &client, &mut app, events).await;
+    ratatui::restore();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Loads the chat list and opens the most recently active chat.
+async fn load(client: &ChatClient) -> Result<App, String> {
+    let user = client
+        .active_user()
+        .await
+        .map_err(|e| format!("could not get the active user: {:?}", e))?;
+    let chats = client
+        .get_chats(user.user_id)
+        .await
+        .map_err(|e| format!("could not list chats: {:?}", e))?;
+
+    let mut app = App::new(user.local_display_name, chats);
+    if let Some(key) = app.chats.first().map(|entry| entry.key()) {
+        perform(client, &mut app, Action::OpenChat(key)).await;
+    }
+
+    Ok(app)
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    client: &ChatClient,
+    app: &mut App,
+    mut events: mpsc::UnboundedReceiver<ChatResponse>,
+) -> Result<(), String> {
+    let mut keys = read_keys();
+
+    loop {
+        terminal
+            .draw(|frame| ui::draw(frame, app))
+            .map_err(|e| e.to_string())?;
+
+        tokio::select! {
+            key = keys.recv() => {
+                let Some(key) = key else {
+                    return Ok(());
+                };
+                app.status.clear();
+
+                match app.handle_key(key) {
+                    Some(Action::Quit) => return Ok(()),
+                    Some(action) => perform(client, app, action).await,
+                    None => {}
+                }
+            }
+            event = events.recv() => {
+                let Some(event) = event else {
+                    return Err("connection to simplex-chat closed".to_string());
+                };
+                app.apply(&event);
+            }
+        }
+    }
+}
+
+/// Reads key presses on a thread of their own, since crossterm's reads block.
+fn read_keys() -> mpsc::UnboundedReceiver<event::KeyEvent> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    thread::spawn(move || loop {
+        match event::poll(Duration::from_millis(250)) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                    if sender.send(key).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            },
+            Ok(false) if sender.is_closed() => break,
+            Ok(false) => {}
+            Err(_) => break,
+        }
+    });
+
+    receiver
+}
+
+/// Asks simplex-chat to carry out an action, reporting failures in the status line.
+///
+/// Sent messages, edits and reactions are shown once simplex-chat reports them as events, so
+/// the results of those commands are not applied here.
+async fn perform(client: &ChatClient, app: &mut App, action: Action) {
+    let result = match action {
+        Action::OpenChat((chat_type, chat_id)) => {
+            match client
+                .get_chat(chat_type, chat_id, None, HISTORY_SIZE)
+                .await
+            {
+                Ok(chat) => {
+                    let unread = chat.chat_stats.unread_count > 0;
+                    app.open(chat);
+                    if unread {
+                        client
+                            .mark_chat_read(chat_type, chat_id)
+                            .await
+                            .map_err(|e| format!("could not mark chat as read: {:?}", e))
+                    } else {
+                        Ok(())
+                    }
+                }
+                Err(e) => Err(format!("could not open chat: {:?}", e)),
+            }
+        }
+        Action::Send {
+            chat: (chat_type, chat_id),
+            text,
+            quoted_item_id,
+        } => {
+            let message = ComposedMessage {
+                file_path: None,
+                quoted_item_id,
+                msg_content: MsgContent::Text(MCText { text }),
+            };
+            client
+                .send_messages(chat_type, chat_id, &[message])
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("could not send message: {:?}", e))
+        }
+        Action::Edit {
+            chat: (chat_type, chat_id),
+            item_id,
+            text,
+        } => client
+            .update_message(
+                chat_type,
+                chat_id,
+                item_id,
+                &MsgContent::Text(MCText { text }),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("could not edit message: {:?}", e)),
+        Action::React {
+            chat: (chat_type, chat_id),
+            item_id,
+            add,
+            reaction,
+        } => client
+            .send_reaction(chat_type, chat_id, item_id, add, reaction)
+            .await
+            .map_err(|e| format!("could not react: {:?}", e)),
+        Action::Quit => Ok(()),
+    };
+
+    if let Err(status) = result {
+        app.status = status;
+    }
+}
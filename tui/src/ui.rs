@@ -0,0 +1,401 @@
+//! Drawing the client: the chat list, the open conversation and the input line.
+
+use muchat_providers::chat::response::{CIStatus, ChatItem, DirectionType, GroupMember};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use crate::app::{is_sent, item_text, App, Mode, REACTIONS};
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [main, input, status] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [chats, conversation] =
+        Layout::horizontal([Constraint::Length(28), Constraint::Min(20)]).areas(main);
+
+    draw_chats(frame, app, chats);
+    draw_conversation(frame, app, conversation);
+    draw_input(frame, app, input);
+    frame.render_widget(
+        Paragraph::new(status_line(app)).style(Style::default().fg(Color::DarkGray)),
+        status,
+    );
+
+    if let Mode::Switch { .. } = app.mode {
+        draw_switcher(frame, app, main);
+    }
+}
+
+fn draw_chats(frame: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .chats
+        .iter()
+        .map(|entry| {
+            let mut spans = vec![Span::raw(entry.chat_info.display_name().to_string())];
+            if entry.unread > 0 {
+                spans.push(Span::styled(
+                    format!(" ({})", entry.unread),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            ListItem::new(vec![
+                Line::from(spans),
+                Line::styled(
+                    entry.preview.lines().next().unwrap_or_default().to_string(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+
+    let mut state = ListState::default().with_selected(
+        app.current
+            .and_then(|current| app.chats.iter().position(|entry| entry.key() == current)),
+    );
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Chats"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        area,
+        &mut state,
+    );
+}
+
+fn draw_conversation(frame: &mut Frame, app: &App, area: Rect) {
+    let title = app
+        .current_entry()
+        .map(|entry| entry.chat_info.display_name().to_string())
+        .unwrap_or_else(|| "No chat open".to_string());
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let width = inner.width.saturating_sub(1) as usize;
+    let mut lines = Vec::new();
+    let mut selected_end = None;
+
+    for (index, item) in app.items.iter().enumerate() {
+        let highlight = if app.selected == Some(index) {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+
+        for line in item_lines(app, item, width) {
+            lines.push(line.patch_style(highlight));
+        }
+
+        if app.selected == Some(index) {
+            selected_end = Some(lines.len());
+        }
+    }
+
+    // Keep the selected item, or the latest one, at the bottom of the view.
+    let end = selected_end.unwrap_or(lines.len());
+    let start = end.saturating_sub(inner.height as usize);
+    let visible: Vec<Line> = lines.drain(start..end).collect();
+
+    frame.render_widget(Paragraph::new(visible), inner);
+}
+
+fn item_lines<'a>(app: &App, item: &'a ChatItem, width: usize) -> Vec<Line<'a>> {
+    let mut lines = Vec::new();
+    let dim = Style::default().fg(Color::DarkGray);
+
+    if let Some(quote) = &item.quoted_item {
+        let sender = quote
+            .chat_dir
+            .as_ref()
+            .map(|dir| sender_name(app, &dir.direction_type, dir.group_member.as_ref()))
+            .unwrap_or_default();
+        let text = format!("┃ {}: {}", sender, quote.content.text());
+        lines.push(Line::styled(truncate(&text, width), dim));
+    }
+
+    let sender = sender_name(
+        app,
+        &item.chat_dir.direction_type,
+        item.chat_dir.group_member.as_ref(),
+    );
+    let sender_style = if is_sent(item) {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default().fg(Color::Green)
+    };
+    let mut suffix = String::new();
+    if item.meta.item_edited {
+        suffix.push_str(" (edited)");
+    }
+    if let Some(glyph) = status_glyph(&item.meta.item_status) {
+        suffix.push(' ');
+        suffix.push_str(glyph);
+    }
+
+    let prefix = format!("{} {}: ", item.meta.item_ts.format("%H:%M"), sender);
+    let text = format!("{}{}", item_text(item), suffix);
+    let mut wrapped = wrap(&text, width.saturating_sub(prefix.chars().count()).max(10));
+
+    let first = if wrapped.is_empty() {
+        String::new()
+    } else {
+        wrapped.remove(0)
+    };
+    lines.push(Line::from(vec![
+        Span::styled(item.meta.item_ts.format("%H:%M ").to_string(), dim),
+        Span::styled(format!("{}: ", sender), sender_style),
+        Span::raw(first),
+    ]));
+    let indent = " ".repeat(prefix.chars().count());
+    for rest in wrapped {
+        lines.push(Line::raw(format!("{}{}", indent, rest)));
+    }
+
+    if !item.reactions.is_empty() {
+        let reactions: Vec<String> = item
+            .reactions
+            .iter()
+            .map(|count| {
+                format!(
+                    "{} {}",
+                    count.reaction.as_emoji().unwrap_or("?"),
+                    count.total_reacted
+                )
+            })
+            .collect();
+        lines.push(Line::styled(
+            format!("{}{}", indent, reactions.join("  ")),
+            dim,
+        ));
+    }
+
+    lines
+}
+
+fn sender_name(app: &App, direction: &DirectionType, member: Option<&GroupMember>) -> String {
+    match direction {
+        DirectionType::DirectSnd | DirectionType::GroupSnd => app.user_name.clone(),
+        DirectionType::DirectRcv => app
+            .current_entry()
+            .map(|entry| entry.chat_info.display_name().to_string())
+            .unwrap_or_default(),
+        DirectionType::GroupRcv => member
+            .map(|member| match &member.member_profile {
+                Some(profile) => profile.display_name.clone(),
+                None => member.local_display_name.clone(),
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// A mark for how far a sent message got, like the check marks of the mobile apps.
+pub fn status_glyph(status: &CIStatus) -> Option<&'static str> {
+    if status.is_error() {
+        return Some("!");
+    }
+
+    match status {
+        CIStatus::SndNew(_) => Some("…"),
+        CIStatus::SndWarning(_) => Some("⚠"),
+        _ if status.is_delivered(true) => Some("✓✓"),
+        _ if status.is_delivered(false) => Some("✓✓…"),
+        _ if status.is_sent(true) => Some("✓"),
+        _ if status.is_sent(false) => Some("✓…"),
+        _ => None,
+    }
+}
+
+fn draw_input(frame: &mut Frame, app: &App, area: Rect) {
+    let title = match &app.mode {
+        Mode::Compose => "Message".to_string(),
+        Mode::Reply(item_id) => {
+            let quoted = app
+                .items
+                .iter()
+                .find(|item| item.meta.item_id == *item_id)
+                .map(item_text)
+                .unwrap_or_default();
+            format!("Reply to: {}", truncate(&quoted, 40))
+        }
+        Mode::Edit(_) => "Edit".to_string(),
+        Mode::React(_) => {
+            let choices: Vec<String> = REACTIONS
+                .iter()
+                .enumerate()
+                .map(|(index, emoji)| format!("{} {}", index + 1, emoji))
+                .collect();
+            format!("React: {}", choices.join("  "))
+        }
+        Mode::Normal | Mode::Switch { .. } => String::new(),
+    };
+
+    let typing = matches!(app.mode, Mode::Compose | Mode::Reply(_) | Mode::Edit(_));
+    let style = if typing {
+        Style::default()
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+
+    frame.render_widget(
+        Paragraph::new(app.input.as_str())
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+
+    if typing {
+        let cursor = app.input.chars().count() as u16;
+        frame.set_cursor_position((
+            (area.x + 1 + cursor).min(area.right().saturating_sub(2)),
+            area.y + 1,
+        ));
+    }
+}
+
+fn status_line(app: &App) -> String {
+    if !app.status.is_empty() {
+        return app.status.clone();
+    }
+
+    match app.mode {
+        Mode::Normal => {
+            "i compose  ↑↓ select  r reply  e edit  + react  / switch  tab next chat  q quit".into()
+        }
+        Mode::Compose | Mode::Reply(_) | Mode::Edit(_) => "enter send  esc cancel".into(),
+        Mode::React(_) => "1-8 react  esc cancel".into(),
+        Mode::Switch { .. } => "type to filter  ↑↓ select  enter open  esc cancel".into(),
+    }
+}
+
+fn draw_switcher(frame: &mut Frame, app: &App, area: Rect) {
+    let Mode::Switch {
+        query,
+        matches,
+        selected,
+    } = &app.mode
+    else {
+        return;
+    };
+
+    let width = area.width.min(50);
+    let height = area.height.min(12);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let items: Vec<ListItem> = matches
+        .iter()
+        .map(|index| {
+            let entry = &app.chats[*index];
+            let mut name = entry.chat_info.display_name().to_string();
+            if entry.unread > 0 {
+                name.push_str(&format!(" ({})", entry.unread));
+            }
+            ListItem::new(name)
+        })
+        .collect();
+
+    frame.render_widget(Clear, popup);
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Switch to: {}", query)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        popup,
+        &mut ListState::default().with_selected(Some(*selected)),
+    );
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() <= width {
+        line.to_string()
+    } else {
+        let mut truncated: String = line.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+/// Splits text into lines of at most `width` characters, breaking at spaces where possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let line_len = line.chars().count();
+            let word_len = word.chars().count();
+
+            if line_len > 0 && line_len + 1 + word_len > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+
+            let mut chars = word.chars().peekable();
+            while chars.peek().is_some() {
+                let room = width - line.chars().count();
+                if room == 0 {
+                    lines.push(std::mem::take(&mut line));
+                    continue;
+                }
+                line.extend(chars.by_ref().take(room));
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("hello there world", 11), vec!["hello there", "world"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("one\ntwo", 10), vec!["one", "two"]);
+        assert_eq!(wrap("", 10), vec![""]);
+    }
+
+    #[test]
+    fn test_status_glyph() {
+        let status = |value| serde_json::from_value::<CIStatus>(value).unwrap();
+
+        assert_eq!(
+            status_glyph(&status(serde_json::json!({ "type": "sndNew" }))),
+            Some("…")
+        );
+        assert_eq!(
+            status_glyph(&status(serde_json::json!({
+                "type": "sndRcvd",
+                "msgRcptStatus": "ok",
+                "sndProgress": "complete"
+            }))),
+            Some("✓✓")
+        );
+        assert_eq!(
+            status_glyph(&status(serde_json::json!({ "type": "rcvRead" }))),
+            None
+        );
+    }
+}
@@ -1,25 +1,95 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use nucleo::{
-    pattern::{CaseMatching, Normalization, Pattern},
-    Config, Matcher,
+mod notifications;
+
+use futures::StreamExt;
+use muchat_providers::{
+    chat::{
+        archive::{self, BackupReport},
+        client::ChatClient,
+        link::{ConnectionLink, LinkError},
+        matcher::fuzzy_match,
+        qr,
+        response::AChatItem,
+    },
+    store::{MessageStore, SearchHit, SearchQuery},
 };
+use notifications::{DesktopNotifier, NotificationWatch};
+use tauri::{AppHandle, Manager, State};
 
 #[tauri::command]
 fn match_string(pattern: String, paths: Vec<String>) -> Vec<String> {
-    if paths.is_empty() {
-        return vec![];
+    fuzzy_match(&pattern, paths)
+}
+
+/// Adds items received by the UI to the local message store, so they can be searched.
+#[tauri::command]
+fn index_chat_items(
+    store: State<'_, MessageStore>,
+    chat_items: Vec<AChatItem>,
+) -> Result<(), String> {
+    for item in &chat_items {
+        store.save_item(item).map_err(|e| e.to_string())?;
     }
 
-    let mut matcher = Matcher::new(Config::DEFAULT);
-    let matches = Pattern::parse(&pattern, CaseMatching::Ignore, Normalization::Smart)
-        .match_list(paths, &mut matcher);
+    Ok(())
+}
+
+#[tauri::command]
+fn search_messages(
+    store: State<'_, MessageStore>,
+    query: SearchQuery,
+) -> Result<Vec<SearchHit>, String> {
+    store.search(&query).map_err(|e| e.to_string())
+}
+
+/// Classifies a pasted link, failing with the reason if it is not a SimpleX link.
+#[tauri::command]
+fn parse_link(link: String) -> Result<ConnectionLink, String> {
+    link.parse().map_err(|e: LinkError| e.to_string())
+}
 
-    matches
-        .into_iter()
-        .map(|(matched_text, _score)| matched_text)
-        .collect()
+/// An SVG QR code of a link, for others to scan.
+#[tauri::command]
+fn link_qr_svg(link: String) -> Result<String, String> {
+    qr::to_svg(&link).map_err(|e| e.to_string())
+}
+
+/// Reads the link in a PNG image of a QR code, like a screenshot of someone's address.
+#[tauri::command]
+fn scan_qr(path: String) -> Result<ConnectionLink, String> {
+    qr::scan_link(path).map_err(|e| e.to_string())
+}
+
+/// Archives the simplex-chat database served at `server` into the app's backup directory.
+#[tauri::command]
+async fn backup_now(app: AppHandle, server: String) -> Result<BackupReport, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("backups");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let (client, stream) = ChatClient::new(server).await.map_err(|e| e.to_string())?;
+    let stream = Box::pin(stream.await);
+    let reader = tokio::spawn(stream.for_each(|_| async {}));
+
+    let report = archive::backup_now(&client, &dir).await;
+    reader.abort();
+    report.map_err(|e| e.to_string())
+}
+
+/// Raises native notifications for messages received from the simplex-chat served at `server`,
+/// following the user's and each chat's notification settings.
+#[tauri::command]
+async fn watch_notifications(
+    app: AppHandle,
+    watch: State<'_, NotificationWatch>,
+    server: String,
+) -> Result<(), String> {
+    watch.start(server, DesktopNotifier::new(app.clone())).await
 }
 
 #[tokio::main]
@@ -30,7 +100,24 @@ async fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_websocket::init())
-        .invoke_handler(tauri::generate_handler![match_string])
+        .plugin(tauri_plugin_notification::init())
+        .manage(NotificationWatch::default())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            app.manage(MessageStore::open(data_dir.join("messages.db"))?);
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            match_string,
+            index_chat_items,
+            search_messages,
+            parse_link,
+            link_qr_svg,
+            scan_qr,
+            backup_now,
+            watch_notifications
+        ])
         .run(tauri::generate_context!())
         .expect("failed to run app");
 }
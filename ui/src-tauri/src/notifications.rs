@@ -0,0 +1,61 @@
+//! Native notifications for messages received while the app is open.
+
+use std::sync::Mutex;
+
+use muchat_providers::chat::{
+    client::ChatClient,
+    notifications::{Notification, Notifications, Notifier, NotifyError},
+};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_notification::NotificationExt;
+use tokio::task::JoinHandle;
+
+pub struct DesktopNotifier<R: Runtime> {
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> DesktopNotifier<R> {
+    pub fn new(app: AppHandle<R>) -> Self {
+        DesktopNotifier { app }
+    }
+}
+
+impl<R: Runtime> Notifier for DesktopNotifier<R> {
+    fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        self.app
+            .notification()
+            .builder()
+            .title(&notification.title)
+            .body(&notification.body)
+            .show()
+            .map_err(|e| NotifyError(e.to_string()))
+    }
+}
+
+/// The task watching simplex-chat for messages, if one was started.
+#[derive(Default)]
+pub struct NotificationWatch(pub Mutex<Option<JoinHandle<()>>>);
+
+impl NotificationWatch {
+    /// Watches the simplex-chat served at `server`, replacing the previous watch.
+    pub async fn start(
+        &self,
+        server: String,
+        notifier: impl Notifier + Send + Sync + 'static,
+    ) -> Result<(), String> {
+        let (client, stream) = ChatClient::new(server).await.map_err(|e| e.to_string())?;
+
+        let task = tokio::spawn(async move {
+            // Keeps the connection open for as long as the stream is read.
+            let _client = client;
+            Notifications::default()
+                .watch(stream.await, &notifier, |e| log::warn!("{}", e))
+                .await;
+        });
+
+        if let Some(previous) = self.0.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+        Ok(())
+    }
+}